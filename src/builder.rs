@@ -1,32 +1,81 @@
 pub mod builder_error;
 pub mod builder_configuration;
+pub mod build_cache;
+pub mod build_report;
+pub mod build_format;
+pub mod split_output;
+pub mod watched_dossier;
 mod constants;
 
 
-use std::{borrow::Borrow, collections::HashSet, path::PathBuf, sync::Arc, time::Instant};
+use std::{borrow::Borrow, collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
+use build_cache::BuildCache;
+use build_format::BuildFormat;
+use split_output::{SPLIT_INDEX_FILE_NAME, SPLIT_NAVIGATION_STYLE};
+use build_report::{BuildReport, BuildStage, DocumentCompilationMeasure, DocumentLoadMeasure};
 use builder_configuration::BuilderConfiguration;
 use builder_error::BuilderError;
 use nmd_core::artifact::Artifact;
 use nmd_core::assembler::assembler_configuration::AssemblerConfiguration;
+use nmd_core::bibliography::Bibliography;
 use nmd_core::compilation::compilable::Compilable;
 use nmd_core::compilation::compilation_configuration::compilation_configuration_overlay::CompilationConfigurationOverLay;
-use nmd_core::constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME};
+use nmd_core::compilation::compilation_configuration::CompilationConfiguration;
+use nmd_core::compilation::compilation_outcome::CompilationOutcome;
 use nmd_core::dossier::document::Document;
 use nmd_core::dossier::dossier_configuration::DossierConfiguration;
-use nmd_core::dossier::Dossier;
+use nmd_core::dossier::{self, Dossier};
 use nmd_core::dumpable::{DumpConfiguration, Dumpable};
 use nmd_core::load::{LoadConfiguration, LoadConfigurationOverLay};
 use nmd_core::output_format::OutputFormat;
+use nmd_core::compilation::compilation_error::CompilationError;
+use nmd_core::resource::resource_reference::ResourceReference;
+use nmd_core::table_of_contents::TableOfContents;
 use nmd_core::theme::Theme;
 use nmd_core::utility::file_utility;
 use nmd_core::utility::nmd_unique_identifier::assign_nuid_to_document_paragraphs;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use tokio::{sync::RwLock as TokioRwLock, task::JoinSet};
-use crate::preview::{html_preview::PREVIEW_URL, Preview};
-use crate::{preview::html_preview::HtmlPreview, watcher::{NmdWatcher, WatcherError}};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use serde::Serialize;
+use tokio::{sync::RwLock as TokioRwLock, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+use crate::preview::{Preview, PreviewError, PreviewFailure};
+use self::constants::BUILD_CACHE_DIR;
+use crate::{preview::html_preview::HtmlPreview, utility::normalize_path, watcher::{NmdWatcher, WatchRoots, WatcherError}};
+use watched_dossier::WatchedDossier;
+use notify::{event::ModifyKind, EventKind};
 
 
 
+/// Compiled parts of a dossier, not assembled yet
+struct CompiledDossierParts {
+    documents_outcomes: Vec<CompilationOutcome>,
+    documents_compilation_measures: Vec<DocumentCompilationMeasure>,
+
+    /// Names of all dossier documents, in order (also documents which are not compiled)
+    documents_names: Vec<String>,
+
+    /// Documents (by index) whose compilation is skipped in fast draft, with their output if it is in build cache
+    skipped_documents: HashMap<usize, Option<CompilationOutcome>>,
+
+    compiled_toc: Option<CompilationOutcome>,
+    compiled_bib: Option<CompilationOutcome>,
+    assembler_configuration: AssemblerConfiguration,
+}
+
+/// Result of compilation of a single document of dossier
+enum DocumentCompilation {
+    Compiled(CompilationOutcome, DocumentCompilationMeasure),
+    Skipped(Option<CompilationOutcome>),
+}
+
+/// JSON artifact dumped in background while other formats are compiled
+struct JsonDump {
+    start: Instant,
+    output_location: PathBuf,
+    handle: JoinHandle<Result<(), BuilderError>>,
+}
+
+
 pub struct Builder {
 }
 
@@ -72,15 +121,226 @@ impl Builder {
         Ok(dossier)
     }
 
+    /// Load and build dossier, recording in `report` outcome of each stage and information about each document.
+    /// `builder_configuration` is merged with dossier configuration
+    pub async fn load_and_build_dossier_with_report(builder_configuration: &mut BuilderConfiguration, report: &mut BuildReport) -> Result<(), BuilderError> {
+
+        let load_start = Instant::now();
+
+        let dossier = Self::load_dossier(builder_configuration).await;
+
+        report.record_stage(BuildStage::Load, load_start, &dossier);
+
+        let mut dossier = dossier?;
+
+        // documents are loaded by nmd-core (in parallel), so only their paths are known
+        // paths are normalized like during compilation, because documents are identified by their paths in report
+        let configured_documents_paths: Vec<PathBuf> = dossier.configuration().documents_paths().iter().map(|path| normalize_path(Path::new(path))).collect();
+
+        for (document, path) in dossier.documents().iter().zip(watched_dossier::align_documents_paths(&configured_documents_paths, dossier.documents())) {
+            report.record_document_load(DocumentLoadMeasure {
+                name: document.name().clone(),
+                path: path.unwrap_or_else(|| PathBuf::from(document.name())),
+                time_ms: None,
+            });
+        }
+
+        builder_configuration.merge_dossier_configuration(dossier.configuration());
+
+        report.set_configuration(builder_configuration);
+
+        Self::check_cancellation(builder_configuration)?;
+
+        Self::build_dossier_compiling_subset_reporting(&mut dossier, builder_configuration, None, Some(report)).await
+    }
+
     pub async fn build_dossier(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration) -> Result<(), BuilderError> {
         Self::build_dossier_compiling_subset(dossier, builder_configuration, None).await
     }
 
-    pub async fn build_dossier_compiling_subset(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<String>>) -> Result<(), BuilderError> {
-        
-        log::info!("start to compile dossier");
+    pub async fn build_dossier_compiling_subset(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<PathBuf>>) -> Result<(), BuilderError> {
+        Self::build_dossier_compiling_subset_reporting(dossier, builder_configuration, subset_documents_to_parse, None).await
+    }
 
-        let compilation_start = Instant::now();
+    async fn build_dossier_compiling_subset_reporting(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<PathBuf>>, mut report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
+
+        Self::check_cancellation(builder_configuration)?;
+
+        // JSON is produced from loaded dossier, so it must be serialized before compilation
+        let json_dump = Self::spawn_json_dump_if_requested(&*dossier, dossier.name(), builder_configuration)?;
+
+        let compiled_dump_result = if builder_configuration.needs_compilation() {
+
+            Self::compile_and_dump_dossier(dossier, builder_configuration, subset_documents_to_parse, report.as_deref_mut())
+
+        } else {
+
+            Ok(())
+        };
+
+        let json_dump_result = Self::await_json_dump(json_dump, report).await;
+
+        compiled_dump_result?;
+        json_dump_result?;
+
+        Ok(())
+    }
+
+    fn compile_and_dump_dossier(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<PathBuf>>, mut report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
+
+        if builder_configuration.split().unwrap_or(false) {
+            return Self::compile_and_dump_split_dossier(dossier, builder_configuration, subset_documents_to_parse, report)
+        }
+
+        let compiled_dossier = Self::compile_dossier_reporting(dossier, builder_configuration, subset_documents_to_parse, report.as_deref_mut())?;
+
+        Self::check_cancellation(builder_configuration)?;
+
+        log::info!("dumping...");
+
+        let build_format = BuildFormat::from(builder_configuration.format());
+
+        let output_location = Self::output_location(builder_configuration, dossier.name(), &build_format);
+
+        let dump_configuration = DumpConfiguration::new(
+                                                        output_location.clone(),
+                                                        builder_configuration.force_output().unwrap_or(false)
+                                                    );
+
+        let mut artifact = Artifact::from(compiled_dossier.content());
+
+        let dump_start = Instant::now();
+
+        let dump_result = Self::dump_artifact(&mut artifact, &dump_configuration);
+
+        if let Some(report) = report {
+
+            report.record_stage(BuildStage::Dump, dump_start, &dump_result);
+
+            if dump_result.is_ok() {
+                report.add_output(&build_format, output_location.clone(), fs::metadata(&output_location).map(|m| m.len()).unwrap_or(0));
+            }
+        }
+
+        dump_result?;
+
+        Ok(())
+    }
+
+    /// Return an error if build is cancelled (e.g. by CTRL + C), so it is stopped between its stages
+    fn check_cancellation(builder_configuration: &BuilderConfiguration) -> Result<(), BuilderError> {
+
+        if builder_configuration.cancellation_token().as_ref().is_some_and(|token| token.is_cancelled()) {
+
+            log::warn!("build cancelled");
+
+            return Err(BuilderError::Cancelled)
+        }
+
+        Ok(())
+    }
+
+    /// Dump `artifact` in a temporary file which is then renamed, so an interrupted dump never leaves a partial output file
+    fn dump_artifact(artifact: &mut Artifact, dump_configuration: &DumpConfiguration) -> Result<(), BuilderError> {
+
+        let output_location = dump_configuration.output_path();
+
+        let temporary_file_name = format!(".{}.tmp", output_location.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default());
+
+        let mut temporary_dump_configuration = dump_configuration.clone();
+        temporary_dump_configuration.set_output_path(output_location.with_file_name(temporary_file_name));
+
+        artifact.dump(&temporary_dump_configuration)?;
+
+        if let Err(err) = fs::rename(temporary_dump_configuration.output_path(), output_location) {
+
+            let _ = fs::remove_file(temporary_dump_configuration.output_path());
+
+            return Err(BuilderError::from(err))
+        }
+
+        Ok(())
+    }
+
+    /// Output location of `format` artifact. If output location is a directory, file name is built from resource name;
+    /// if more formats are requested, extension of output file is replaced with the right one
+    fn output_location(builder_configuration: &BuilderConfiguration, name: &str, format: &BuildFormat) -> PathBuf {
+
+        let output_location = builder_configuration.output_location();
+
+        if output_location.is_dir() {
+            return output_location.join(file_utility::build_output_file_name(
+                name,
+                Some(&format.get_extension())
+            ));
+        }
+
+        if builder_configuration.formats().len() > 1 {
+            return output_location.with_extension(format.get_extension());
+        }
+
+        output_location.clone()
+    }
+
+    /// Serialize `resource` (if JSON is a requested format) and dump it in a blocking task, so it runs in parallel with compilation
+    fn spawn_json_dump_if_requested<T: Serialize>(resource: &T, name: &str, builder_configuration: &BuilderConfiguration) -> Result<Option<JsonDump>, BuilderError> {
+
+        if !builder_configuration.formats().contains(&BuildFormat::Json) {
+            return Ok(None)
+        }
+
+        let start = Instant::now();
+
+        let output_location = Self::output_location(builder_configuration, name, &BuildFormat::Json);
+
+        let mut artifact = Artifact::from(serde_json::to_string(resource)?);
+
+        let dump_configuration = DumpConfiguration::new(output_location.clone(), builder_configuration.force_output().unwrap_or(false));
+
+        log::info!("dumping JSON in {:?}...", output_location);
+
+        let handle = tokio::task::spawn_blocking(move || {
+            Self::dump_artifact(&mut artifact, &dump_configuration)
+        });
+
+        Ok(Some(JsonDump {
+            start,
+            output_location,
+            handle,
+        }))
+    }
+
+    async fn await_json_dump(json_dump: Option<JsonDump>, report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
+
+        let json_dump = match json_dump {
+            Some(json_dump) => json_dump,
+            None => return Ok(()),
+        };
+
+        let dump_result = match json_dump.handle.await {
+            Ok(result) => result,
+            Err(err) => Err(BuilderError::from(err)),
+        };
+
+        if let Some(report) = report {
+
+            report.record_stage(BuildStage::Dump, json_dump.start, &dump_result);
+
+            if dump_result.is_ok() {
+                report.add_output(&BuildFormat::Json, json_dump.output_location.clone(), fs::metadata(&json_dump.output_location).map(|m| m.len()).unwrap_or(0));
+            }
+        }
+
+        dump_result
+    }
+
+    /// Compile dossier based on `BuilderConfiguration` without dumping it
+    pub async fn compile_dossier(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration) -> Result<CompilationOutcome, BuilderError> {
+        Self::compile_dossier_reporting(dossier, builder_configuration, None, None)
+    }
+
+    /// Build compilation configuration and overlay used to compile dossier
+    fn prepare_dossier_compilation(dossier: &Dossier, builder_configuration: &BuilderConfiguration) -> (CompilationConfiguration, CompilationConfigurationOverLay) {
 
         let mut compilation_configuration = builder_configuration.generate_compilation_configuration();
 
@@ -114,12 +374,18 @@ impl Builder {
                 
                     assembler_configuration.set_theme(t.clone());
                 }
+
+                // style files passed by user (e.g. --style-file)
+                assembler_configuration.external_styles_paths_mut().extend(builder_configuration.styles_raw_path().iter().map(PathBuf::from));
         
                 if let Some(there_is_preview) = builder_configuration.preview() {
                     if there_is_preview {
 
-                        assembler_configuration.external_scripts_mut()
-                                                    .push(include_str!("preview/check_preview_updates.js").to_string());
+                        if let Some(preview_url) = builder_configuration.preview_url() {
+
+                            assembler_configuration.external_scripts_mut()
+                                                        .push(HtmlPreview::client_script(preview_url));
+                        }
                     }
                 }
                 
@@ -129,49 +395,480 @@ impl Builder {
 
         compilation_configuration_overlay.set_assembler_configuration(assembler_configuration);
 
-        if let Some(subset) = subset_documents_to_parse {
+        (compilation_configuration, compilation_configuration_overlay)
+    }
 
-            compilation_configuration_overlay.set_compile_only_documents(Some(subset));
+    fn compile_dossier_reporting(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<PathBuf>>, mut report: Option<&mut BuildReport>) -> Result<CompilationOutcome, BuilderError> {
+        
+        log::info!("start to compile dossier");
+
+        let compilation_start = Instant::now();
+
+        let (compilation_configuration, compilation_configuration_overlay) = Self::prepare_dossier_compilation(dossier, builder_configuration);
+
+        let use_build_cache = builder_configuration.build_cache().unwrap_or(false);
+
+        // documents are compiled one by one only if needed, i.e. to use build cache or to compile a subset of documents
+        // (which are identified by their paths, while nmd-core identifies them by name); only in this case their compilation is reported
+        let compilation_result = if use_build_cache || subset_documents_to_parse.is_some() {
+
+            Self::compile_dossier_by_documents(dossier, builder_configuration, &compilation_configuration, compilation_configuration_overlay, subset_documents_to_parse.as_ref(), use_build_cache)
+
+        } else {
+
+            dossier.compile(builder_configuration.format(), &builder_configuration.codex(), &compilation_configuration, compilation_configuration_overlay)
+                    .map(|outcome| (outcome, Vec::new()))
+                    .map_err(BuilderError::from)
+        };
+
+        if let Some(report) = report.as_deref_mut() {
+            report.record_stage(BuildStage::Compile, compilation_start, &compilation_result);
         }
 
-        let compiled_dossier = dossier.compile(builder_configuration.format(), &builder_configuration.codex(), &compilation_configuration, compilation_configuration_overlay)?;
+        let (compiled_dossier, documents_compilation_measures) = compilation_result?;
+
+        if let Some(report) = report {
+            for measure in documents_compilation_measures {
+                report.record_document_compilation(measure);
+            }
+        }
 
         log::info!("dossier compiled in {} ms", compilation_start.elapsed().as_millis());
 
-        log::info!("dumping...");
+        Ok(compiled_dossier)
+    }
 
-        let mut output_location = compilation_configuration.output_location().clone();
+    /// Compile dossier like `Dossier::compile`, but documents are compiled one by one. If `use_build_cache`, documents which are not changed
+    /// from last build are taken from build cache.
+    /// 
+    /// It returns compiled dossier and compilation measure of each compiled document
+    fn compile_dossier_by_documents(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, compilation_configuration: &CompilationConfiguration, compilation_configuration_overlay: CompilationConfigurationOverLay, subset_documents_to_parse: Option<&HashSet<PathBuf>>, use_build_cache: bool) -> Result<(CompilationOutcome, Vec<DocumentCompilationMeasure>), BuilderError> {
 
-        if output_location.is_dir() {
-            output_location = output_location.join(file_utility::build_output_file_name(
-               &dossier.name(),
-                Some(&builder_configuration.format().get_extension())
-            ));
+        let parts = Self::compile_dossier_parts(dossier, builder_configuration, compilation_configuration, compilation_configuration_overlay, subset_documents_to_parse, use_build_cache)?;
+
+        let assembled_dossier = builder_configuration.codex().assembler().assemble_dossier(&parts.documents_outcomes, parts.compiled_toc.as_ref(), parts.compiled_bib.as_ref(), dossier.configuration(), &parts.assembler_configuration)?;
+
+        Ok((CompilationOutcome::from(assembled_dossier), parts.documents_compilation_measures))
+    }
+
+    /// Assign to headings of document the same resource references which they would receive during compilation
+    fn assign_headings_resource_references(document: &mut Document) -> Result<(), CompilationError> {
+
+        let document_name = document.name().clone();
+
+        for chapter in document.content_mut().chapters_mut() {
+
+            let heading = chapter.header_mut().heading_mut();
+
+            let resource_reference = ResourceReference::of_internal_from_without_sharp(heading.title(), Some(&document_name))?;
+
+            heading.set_resource_reference(Some(resource_reference));
         }
 
-        let dump_configuration = DumpConfiguration::new(
-                                                        output_location,
-                                                        builder_configuration.force_output().unwrap_or(false)
-                                                    );
+        Ok(())
+    }
 
-        let mut artifact = Artifact::from(compiled_dossier.content());
+    /// Compile documents (one by one), table of contents and bibliography of dossier without assembling them.
+    /// Documents of `subset_documents_to_parse` (normalized paths) are known to be changed; in fast draft, they are the only compiled documents
+    fn compile_dossier_parts(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, compilation_configuration: &CompilationConfiguration, mut compilation_configuration_overlay: CompilationConfigurationOverLay, subset_documents_to_parse: Option<&HashSet<PathBuf>>, use_build_cache: bool) -> Result<CompiledDossierParts, BuilderError> {
+
+        let build_cache = if use_build_cache {
+
+            Some(BuildCache::new(
+                builder_configuration.input_location().join(BUILD_CACHE_DIR),
+                builder_configuration.input_location(),
+                BuildCache::configuration_key(builder_configuration, dossier.configuration())?
+            ))
+
+        } else {
+
+            None
+        };
+
+        log::info!("compile dossier {} by documents ({} documents, parallelization: {}, build cache: {})", dossier.name(), dossier.documents().len(), compilation_configuration.parallelization(), use_build_cache);
+
+        compilation_configuration_overlay.set_dossier_name(Some(dossier.name().clone()));
 
-        artifact.dump(&dump_configuration)?;
+        let format = builder_configuration.format();
+        let codex = builder_configuration.codex();
+
+        let configured_documents_paths: Vec<PathBuf> = dossier.configuration().documents_paths().iter()
+                                                            .map(|path| normalize_path(Path::new(path)))
+                                                            .collect();
+
+        let documents_paths = watched_dossier::align_documents_paths(&configured_documents_paths, dossier.documents());
+
+        let fast_draft = compilation_configuration.fast_draft();
+
+        // None if there is no subset or document path is unknown (so document is compiled anyway)
+        let in_subset = |document_path: &Option<PathBuf>| {
+            subset_documents_to_parse.zip(document_path.as_ref()).map(|(subset, path)| subset.contains(path))
+        };
+
+        let compile_document = |(document, document_path): (&mut Document, &Option<PathBuf>)| -> Result<DocumentCompilation, BuilderError> {
+
+            let now = Instant::now();
+
+            let key = build_cache.as_ref().zip(document_path.as_ref()).and_then(|(build_cache, path)| {
+                file_utility::read_file_content(path).ok()
+                    .map(|content| build_cache.document_key(path, &content))
+            });
+
+            if fast_draft && in_subset(document_path) == Some(false) {
+
+                log::info!("document {} compilation is skipped", document.name());
+
+                // headings of skipped document are still listed in table of contents
+                Self::assign_headings_resource_references(document)
+                    .map_err(|err| BuilderError::DocumentCompilationError(document.name().clone(), err))?;
+
+                // skipped documents are not compiled, but their last output can be still used (e.g. in split output)
+                let cached_outcome = build_cache.as_ref().zip(document_path.as_ref()).zip(key.as_ref())
+                                        .and_then(|((build_cache, path), key)| build_cache.load(path, key));
+
+                return Ok(DocumentCompilation::Skipped(cached_outcome))
+            }
+
+            // documents of subset are known to be changed (e.g. a referenced image), even if their content is the same
+            let changed = in_subset(document_path) == Some(true);
+
+            if let (Some(build_cache), Some(document_path), Some(key), false) = (build_cache.as_ref(), document_path.as_ref(), key.as_ref(), changed) {
+                if let Some(outcome) = build_cache.load(document_path, key) {
+
+                    log::info!("document '{}' is not changed, build cache is used", document.name());
+
+                    // headings of cached document are not compiled, but their ids are needed to build table of contents
+                    Self::assign_headings_resource_references(document)
+                        .map_err(|err| BuilderError::DocumentCompilationError(document.name().clone(), err))?;
+
+                    let measure = DocumentCompilationMeasure {
+                        name: document.name().clone(),
+                        path: document_path.clone(),
+                        time_ms: now.elapsed().as_millis(),
+                        compiled_size: outcome.content().len(),
+                        from_build_cache: true,
+                    };
+
+                    return Ok(DocumentCompilation::Compiled(outcome, measure))
+                }
+            }
+
+            let outcome = document.compile(format, &codex, compilation_configuration, compilation_configuration_overlay.clone())
+                                    .map_err(|err| BuilderError::DocumentCompilationError(document.name().clone(), err))?;
+
+            let compilation_time = now.elapsed().as_millis();
+
+            log::info!("document '{}' compiled in {} ms", document.name(), compilation_time);
+
+            if let (Some(build_cache), Some(document_path), Some(key)) = (build_cache.as_ref(), document_path.as_ref(), key.as_ref()) {
+                if let Err(err) = build_cache.store(document_path, key, &outcome) {
+                    log::warn!("document '{}' can not be stored in build cache: {}", document.name(), err);
+                }
+            }
+
+            let measure = DocumentCompilationMeasure {
+                name: document.name().clone(),
+                path: document_path.clone().unwrap_or_else(|| PathBuf::from(document.name())),
+                time_ms: compilation_time,
+                compiled_size: outcome.content().len(),
+                from_build_cache: false,
+            };
+
+            Ok(DocumentCompilation::Compiled(outcome, measure))
+        };
+
+        let documents_results: Vec<Result<DocumentCompilation, BuilderError>> = if compilation_configuration.parallelization() {
+
+            dossier.documents_mut().par_iter_mut().zip(documents_paths.par_iter()).map(compile_document).collect()
+
+        } else {
+
+            dossier.documents_mut().iter_mut().zip(documents_paths.iter()).map(compile_document).collect()
+        };
+
+        // all documents errors are reported, not only the first one
+        let mut documents_outcomes: Vec<CompilationOutcome> = Vec::new();
+        let mut documents_compilation_measures: Vec<DocumentCompilationMeasure> = Vec::new();
+        let mut skipped_documents: HashMap<usize, Option<CompilationOutcome>> = HashMap::new();
+        let mut errors: Vec<BuilderError> = Vec::new();
+
+        for (index, result) in documents_results.into_iter().enumerate() {
+            match result {
+                Ok(DocumentCompilation::Compiled(outcome, measure)) => {
+                    documents_outcomes.push(outcome);
+                    documents_compilation_measures.push(measure);
+                },
+                Ok(DocumentCompilation::Skipped(cached_outcome)) => {
+                    skipped_documents.insert(index, cached_outcome);
+                },
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if errors.len() == 1 {
+            return Err(errors.remove(0))
+        }
+
+        if !errors.is_empty() {
+            return Err(BuilderError::BucketOfErrors(errors))
+        }
+
+        let mut compiled_toc: Option<CompilationOutcome> = None;
+        let mut compiled_bib: Option<CompilationOutcome> = None;
+
+        let toc_configuration = dossier.configuration().table_of_contents_configuration();
+
+        if toc_configuration.include_in_output() {
+
+            log::info!("dossier table of contents will be included in output");
+
+            let headings = dossier.documents().iter()
+                                .flat_map(|document| document.content().chapters())
+                                .map(|chapter| chapter.header().heading().clone())
+                                .collect();
+
+            let mut table_of_contents = TableOfContents::new(
+                toc_configuration.title().clone(),
+                toc_configuration.page_numbers(),
+                toc_configuration.plain(),
+                toc_configuration.maximum_heading_level(),
+                headings
+            );
+
+            compiled_toc = Some(table_of_contents.compile(format, &codex, compilation_configuration, compilation_configuration_overlay.clone())?);
+        }
+
+        if dossier.configuration().bibliography().include_in_output() {
+
+            let mut bibliography = Bibliography::new(
+                dossier.configuration().bibliography().title().clone(),
+                dossier.configuration().bibliography().records().clone()
+            );
+
+            compiled_bib = Some(bibliography.compile(format, &codex, compilation_configuration, compilation_configuration_overlay.clone())?);
+        }
+
+        Ok(CompiledDossierParts {
+            documents_outcomes,
+            documents_compilation_measures,
+            documents_names: dossier.documents().iter().map(|document| document.name().clone()).collect(),
+            skipped_documents,
+            compiled_toc,
+            compiled_bib,
+            assembler_configuration: compilation_configuration_overlay.assembler_configuration().clone(),
+        })
+    }
+
+    /// Compile dossier and dump each document in its own page, plus an index page with table of contents.
+    /// Output location must be a directory
+    fn compile_and_dump_split_dossier(dossier: &mut Dossier, builder_configuration: &BuilderConfiguration, subset_documents_to_parse: Option<HashSet<PathBuf>>, mut report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
+
+        let output_dir = builder_configuration.output_location().clone();
+
+        if !output_dir.is_dir() {
+
+            if output_dir.exists() || !builder_configuration.force_output().unwrap_or(false) {
+                return Err(BuilderError::InvalidSplitOutputLocation(output_dir))
+            }
+
+            fs::create_dir_all(&output_dir).map_err(|_| BuilderError::InvalidSplitOutputLocation(output_dir.clone()))?;
+        }
+
+        log::info!("start to compile dossier (split output)");
+
+        let compilation_start = Instant::now();
+
+        let (compilation_configuration, compilation_configuration_overlay) = Self::prepare_dossier_compilation(dossier, builder_configuration);
+
+        let use_build_cache = builder_configuration.build_cache().unwrap_or(false);
+
+        let compilation_result = Self::compile_dossier_parts(dossier, builder_configuration, &compilation_configuration, compilation_configuration_overlay, subset_documents_to_parse.as_ref(), use_build_cache);
+
+        if let Some(report) = report.as_deref_mut() {
+            report.record_stage(BuildStage::Compile, compilation_start, &compilation_result);
+        }
+
+        let parts = compilation_result?;
+
+        log::info!("dossier compiled in {} ms", compilation_start.elapsed().as_millis());
+
+        Self::check_cancellation(builder_configuration)?;
+
+        let dump_start = Instant::now();
+
+        let dump_result = Self::dump_split_dossier(dossier, builder_configuration, &parts, &output_dir);
+
+        if let Some(report) = report {
+
+            report.record_stage(BuildStage::Dump, dump_start, &dump_result);
+
+            for measure in parts.documents_compilation_measures {
+                report.record_document_compilation(measure);
+            }
+
+            if let Ok(outputs) = dump_result.as_ref() {
+                for output in outputs {
+                    report.add_output(&BuildFormat::Html, output.clone(), fs::metadata(output).map(|m| m.len()).unwrap_or(0));
+                }
+            }
+        }
+
+        dump_result?;
 
         Ok(())
     }
 
+    /// Dump a page for each compiled document and index page, returning their paths.
+    /// Pages of skipped documents are dumped from build cache if possible, otherwise page already on disk is kept
+    fn dump_split_dossier(dossier: &Dossier, builder_configuration: &BuilderConfiguration, parts: &CompiledDossierParts, output_dir: &Path) -> Result<Vec<PathBuf>, BuilderError> {
+
+        let pages = split_output::split_pages(&parts.documents_names);
+
+        let mut compiled_outcomes = parts.documents_outcomes.iter();
+
+        // None if page is not dumped again, because its document is skipped and not in build cache
+        let pages_outcomes: Vec<Option<&CompilationOutcome>> = (0..pages.len()).map(|index| match parts.skipped_documents.get(&index) {
+            Some(cached_outcome) => cached_outcome.as_ref(),
+            None => compiled_outcomes.next(),
+        }).collect();
+
+        // each id is linked to page in which it is defined, so links to other pages can be fixed
+        let mut ids_pages: HashMap<String, String> = HashMap::new();
+
+        for (page, outcome) in pages.iter().zip(pages_outcomes.iter()) {
+
+            let content = match outcome {
+                Some(outcome) => outcome.content().clone(),
+                None => match fs::read_to_string(output_dir.join(&page.file_name)) {
+                    Ok(content) => content,
+                    Err(_) => {
+                        log::warn!("page {} of skipped document '{}' is not found, a full build is needed to create it", page.file_name, page.title);
+
+                        continue
+                    },
+                },
+            };
+
+            for id in split_output::collect_ids(&content) {
+                ids_pages.entry(id).or_insert(page.file_name.clone());
+            }
+        }
+
+        if let Some(compiled_bib) = parts.compiled_bib.as_ref() {
+            for id in split_output::collect_ids(compiled_bib.content()) {
+                ids_pages.entry(id).or_insert(SPLIT_INDEX_FILE_NAME.to_string());
+            }
+        }
+
+        let codex = builder_configuration.codex();
+        let assembler = codex.assembler();
+
+        let mut assembler_configuration = parts.assembler_configuration.clone();
+
+        // standalone documents don't include dossier styles
+        assembler_configuration.external_styles_paths_mut().splice(0..0, dossier.configuration().style().styles_references().into_iter().map(PathBuf::from));
+        assembler_configuration.external_styles_mut().push(SPLIT_NAVIGATION_STYLE.to_string());
+
+        log::info!("dumping {} pages in {:?}...", pages.len() + 1, output_dir);
+
+        let mut outputs: Vec<PathBuf> = Vec::new();
+
+        for (index, (page, outcome)) in pages.iter().zip(pages_outcomes.iter()).enumerate() {
+
+            let Some(outcome) = outcome else {
+
+                log::info!("page {} is kept as is", page.file_name);
+
+                continue
+            };
+
+            let navigation = split_output::navigation(
+                index.checked_sub(1).and_then(|previous| pages.get(previous)),
+                pages.get(index + 1)
+            );
+
+            let content = split_output::rewrite_local_links(outcome.content(), &page.file_name, &ids_pages);
+
+            let page_outcome = CompilationOutcome::from(format!("{}{}{}", navigation, content, navigation));
+
+            let title = split_output::escape_html(&format!("{} - {}", dossier.name(), page.title));
+
+            let assembled_page = assembler.assemble_document_standalone(&title, &page_outcome, None, None, &assembler_configuration)?;
+
+            outputs.push(Self::dump_split_page(output_dir, &page.file_name, assembled_page)?);
+        }
+
+        let mut index_content: Vec<CompilationOutcome> = Vec::new();
+
+        if parts.compiled_toc.is_none() {
+            index_content.push(CompilationOutcome::from(split_output::pages_list(&pages)));
+        }
+
+        let compiled_toc = parts.compiled_toc.as_ref().map(|toc| CompilationOutcome::from(split_output::rewrite_local_links(toc.content(), SPLIT_INDEX_FILE_NAME, &ids_pages)));
+
+        let mut index_assembler_configuration = parts.assembler_configuration.clone();
+        index_assembler_configuration.external_styles_mut().push(SPLIT_NAVIGATION_STYLE.to_string());
+
+        let assembled_index = assembler.assemble_dossier(&index_content, compiled_toc.as_ref(), parts.compiled_bib.as_ref(), dossier.configuration(), &index_assembler_configuration)?;
+
+        outputs.push(Self::dump_split_page(output_dir, SPLIT_INDEX_FILE_NAME, assembled_index)?);
+
+        Ok(outputs)
+    }
+
+    fn dump_split_page(output_dir: &Path, file_name: &str, content: String) -> Result<PathBuf, BuilderError> {
+
+        let output_location = output_dir.join(file_name);
+
+        let mut artifact = Artifact::from(content);
+
+        Self::dump_artifact(&mut artifact, &DumpConfiguration::new(output_location.clone(), true))?;
+
+        Ok(output_location)
+    }
+
+    /// Report a failed build of watch mode: error is logged and pushed to preview (if any), so it can be shown over last good output.
+    /// Returned error ends only current elaboration, watcher keeps watching
+    async fn watch_failure(err: BuilderError, document: Option<String>, preview: Option<Arc<TokioRwLock<HtmlPreview>>>) -> WatcherError {
+
+        log::error!("error during compilation: {}", err);
+
+        let document = document.or_else(|| err.failing_document().cloned());
+
+        if let Some(preview) = preview {
+
+            if let Err(preview_err) = preview.write().await.fail(PreviewFailure::new(err.to_string(), document)).await {
+                return WatcherError::PreviewError(preview_err)
+            }
+        }
+
+        WatcherError::ElaborationError(err.to_string())
+    }
+
+    /// Absolute input location, an error is returned if it doesn't exist or it can not be read
+    fn canonical_input_location(builder_configuration: &BuilderConfiguration) -> Result<PathBuf, BuilderError> {
+        builder_configuration.input_location().canonicalize()
+            .map_err(|err| BuilderError::InvalidInputLocation(builder_configuration.input_location().clone(), err))
+    }
+
     /// Watch filesystem and compile dossier if any changes occur
     /// 
-    /// - min_elapsed_time_between_events_in_secs is the minimum time interval between two compilation
-    pub async fn watch_compile_dossier(mut builder_configuration: BuilderConfiguration, min_elapsed_time_between_events_in_secs: u64, preview: Option<Arc<TokioRwLock<HtmlPreview>>>) -> Result<(), BuilderError> {
+    /// - debounce_time is the quiet time after last change before compilation (changes in this time are compiled together)
+    /// - shutdown_token stops watching (in-flight compilation is completed before)
+    pub async fn watch_compile_dossier(mut builder_configuration: BuilderConfiguration, debounce_time: Duration, preview: Option<Arc<TokioRwLock<HtmlPreview>>>, shutdown_token: CancellationToken) -> Result<(), BuilderError> {
 
-        let input_location_abs = Arc::new(builder_configuration.input_location().canonicalize().unwrap()); 
+        let input_location_abs = Arc::new(Self::canonical_input_location(&builder_configuration)?);
 
         let dossier = Self::load_dossier(&builder_configuration).await?;
 
         builder_configuration.merge_dossier_configuration(dossier.configuration());
 
+        let dossier = WatchedDossier::new(dossier, &builder_configuration);
+
+        // styles and referenced images can be outside of dossier directory
+        let watch_roots = WatchRoots::new();
+        watch_roots.set(dossier.dependencies_paths(&builder_configuration));
+
         let dossier = Arc::new(TokioRwLock::new(dossier));
 
         let builder_configuration = Arc::new(TokioRwLock::new(builder_configuration.clone()));
@@ -179,8 +876,9 @@ impl Builder {
         let mut watcher = tokio::spawn(async move {
 
             NmdWatcher::new(
-                min_elapsed_time_between_events_in_secs,
+                debounce_time,
                 &input_location_abs.clone(),
+                watch_roots.clone(),
                 Box::new({
     
                     let preview = preview.clone();
@@ -202,19 +900,19 @@ impl Builder {
                             async move {
     
                                 let compilation_result = tokio::spawn(async move {
-                                    Self::build_dossier(&mut (*dossier.write().await), &builder_configuration.read().await.clone()).await
-                                });
+                                    Self::build_dossier(dossier.write().await.dossier_mut(), &builder_configuration.read().await.clone()).await
+                                }).await?;
+
+                                println!("\n\n");
+                                log::info!("watch mode ON: modification to the dossier files will cause recompilation");
+                                log::info!("start watching: {:?}", input_location_abs);
+                                log::info!("press CTRL + C to terminate");
+                                println!("\n\n");
     
-                                match compilation_result.await {
+                                match compilation_result {
                                     Ok(_) => {
                 
                                         log::info!("compilation OK");
-    
-                                        println!("\n\n");
-                                        log::info!("watch mode ON: modification to the dossier files will cause recompilation");
-                                        log::info!("start watching: {:?}", input_location_abs);
-                                        log::info!("press CTRL + C to terminate");
-                                        println!("\n\n");
                                         
                                         if let Some(preview) = preview {
     
@@ -223,52 +921,45 @@ impl Builder {
                                             }).await??;
                                         }
                 
-                                        return Ok(())
+                                        Ok(())
                                     },
-                                    Err(err) => {
-                                        log::error!("error during compilation: {:?}", err);
-                
-                                        return Err(WatcherError::ElaborationError(err.to_string()))
-                                    }
+                                    Err(err) => Err(Self::watch_failure(err, None, preview).await),
                                 }
                             }
                         })
                     }
                 }),
-                Box::new({
-    
-                    let input_location_abs = input_location_abs.clone();
-    
-                    move |event| {
-    
-                        let input_location_abs = input_location_abs.clone();
-        
-                        Box::pin(async move {
-        
-                            if event.paths.contains(&input_location_abs.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME)) ||
-                                event.paths.contains(&input_location_abs.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME)) {
-            
-                                log::info!("recompilation needed");
-                                return Ok(true)
-                            }
-            
-                            Ok(false)
-                        })
-                    }
-                }),
                 Box::new({
     
                     let builder_configuration = Arc::clone(&builder_configuration);
         
                     let input_location_abs = input_location_abs.clone();
+
+                    let watch_roots = watch_roots.clone();
     
                     move |event| {
     
                         let builder_configuration = Arc::clone(&builder_configuration);
         
                         let input_location_abs = input_location_abs.clone();
+
+                        let watch_roots = watch_roots.clone();
         
                         Box::pin(async move {
+
+                            match event.kind {
+                                EventKind::Access(_) => return Ok(false),
+                                EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => log::info!("file(s) created, removed or renamed: {:?}", event.paths),
+                                _ => (),
+                            }
+
+                            let paths: Vec<PathBuf> = event.paths.iter().map(|path| normalize_path(path)).collect();
+
+                            if paths.iter().any(|path| watched_dossier::configuration_paths(&input_location_abs).contains(path) || watch_roots.contains(path)) {
+            
+                                log::info!("recompilation needed");
+                                return Ok(true)
+                            }
         
                             let original_log_max_level = log::max_level();
         
@@ -278,44 +969,29 @@ impl Builder {
         
                             log::set_max_level(original_log_max_level);
         
-                            if let Err(err) = dc {
-                                log::error!("error during dossier configuration loading: {}", err);
-        
-                                return Ok(false)
-                            }
-        
-                            let dc = dc.unwrap();
-        
-                            let mut relative_paths_to_monitoring = dc.raw_documents_paths().clone();
-                            relative_paths_to_monitoring.push(String::from("assets/"));
-        
-                            let relative_paths_to_monitoring = Arc::new(relative_paths_to_monitoring);
-        
-                            if let Some(_) = event.paths.par_iter().find_any(|path| {
-                
-                                let path = path.strip_prefix(&*input_location_abs.clone());
-        
-                                if let Ok(path) = path {
-                                    let matched = relative_paths_to_monitoring.par_iter().find_any(|rptm| {
-                                        log::debug!("{:?} contains {:?} -> {}", *rptm, path.to_string_lossy().to_string().as_str(), rptm.contains(path.to_string_lossy().to_string().as_str()));
-                                        
-                                        rptm.contains(path.to_string_lossy().to_string().as_str())
-                                    });
+                            let dc = match dc {
+                                Ok(dc) => dc,
+                                Err(err) => {
+                                    log::error!("error during dossier configuration loading: {}", err);
         
-                                    return matched.is_some()
+                                    return Ok(false)
                                 }
+                            };
         
-                                false
-                            }) {
+                            let documents_paths = watched_dossier::listed_documents_paths(&dc, &*builder_configuration.read().await);
+
+                            let assets_path = input_location_abs.join(dossier::ASSETS_DIR);
+
+                            if paths.iter().any(|path| documents_paths.contains(path) || path.starts_with(&assets_path)) {
+
                                 log::info!("recompilation needed");
         
-                                return Ok(true)
-        
+                                Ok(true)
         
                             } else {
                                 log::info!("recompilation not needed");
         
-                                return Ok(false)
+                                Ok(false)
                             }
                         })
                     }
@@ -323,7 +999,6 @@ impl Builder {
                 Box::new({
                     let builder_configuration = Arc::clone(&builder_configuration);
     
-                    // let preview = Arc::clone(&preview);
                     let preview = preview.clone();
     
                     move |paths| {
@@ -332,115 +1007,98 @@ impl Builder {
                             let builder_configuration = Arc::clone(&builder_configuration);
                             let preview = preview.clone();
                             let dossier = dossier.clone();
+                            let watch_roots = watch_roots.clone();
         
                             async move {
-    
-                                let documents_to_parse: Option<HashSet<String>>;        // None => all documents
-    
-                                // check if nmd.yml or nmd.json is changed => load whole dossier
-                                if paths.iter()
-                                        .map(|p| p.file_name())
-                                        .filter(|f| f.is_some())
-                                        .map(|f| f.unwrap().to_string_lossy().to_string())
-                                        .find(|f| f.eq(DOSSIER_CONFIGURATION_YAML_FILE_NAME))
-                                        .is_some() {
-    
-                                    documents_to_parse = None;
-    
-                                    match Self::load_dossier(&*builder_configuration.clone().read().await).await {
-                                        Ok(d) => {
 
-                                            builder_configuration.write().await.merge_dossier_configuration(d.configuration());
+                                let paths: HashSet<PathBuf> = paths.iter().map(|path| normalize_path(path)).collect();
 
-                                            *dossier.write().await = d;
-                                        },
-                                        Err(err) => return Err(WatcherError::ElaborationError(err.to_string())),
-                                    }
-    
-                                } else {        // load dossier partially
-                                    let codex = Arc::new(builder_configuration.read().await.codex());
-    
-                                    let mut dtp: HashSet<String> = HashSet::new();
-    
-                                    let mut document_read_handles = JoinSet::new();
-                                    for path in &paths {
-    
-                                        if dossier.read().await.configuration().raw_documents_paths().par_iter().find_any(|raw_path| {
-                                            let document_path = PathBuf::from(raw_path);
-    
-                                            if let Some(document_name) = document_path.file_name() {
-    
-                                                if let Some(file_name) = path.file_name() {
-                                                    return document_name.eq(file_name);
-                                                }
-                                            }
-    
-                                            false
-                                        }).is_some() {
-    
-                                            let path = path.clone();
-                                            let codex = codex.clone();
-        
-                                            document_read_handles.spawn(async move {
-        
-                                                let document = Document::load_document_from_path(&path, &codex, &LoadConfiguration::default(), LoadConfigurationOverLay::default());
-    
-                                                document
-                                            });
-                                        }
-                                    }
-    
-                                    while let Some(document_read_res) = document_read_handles.join_next().await {
-                                        if let Ok(document) = document_read_res? {
-    
-                                            let name = document.name().clone();
-    
-                                            let res = dossier.write().await.replace_document(&name, document);
-    
-                                            dtp.insert(name);
-    
-                                            res
-                                        }
-                                    }
-    
-                                    documents_to_parse = Some(dtp);
+                                let sync = dossier.write().await.sync(&paths, &*builder_configuration.read().await);
+
+                                let sync = match sync {
+                                    Ok(sync) => sync,
+                                    Err(err) => {
+
+                                        // broken dossier configuration is reported as failing document
+                                        let document = watched_dossier::configuration_paths(&normalize_path(builder_configuration.read().await.input_location())).into_iter()
+                                                            .find(|path| paths.contains(path))
+                                                            .and_then(|path| path.file_name().map(|file_name| file_name.to_string_lossy().to_string()));
+
+                                        return Err(Self::watch_failure(err, document, preview).await)
+                                    },
+                                };
+
+                                if sync.configuration_changed {
+                                    builder_configuration.write().await.merge_dossier_configuration(dossier.read().await.dossier().configuration());
+                                }
+
+                                // documents can reference new images, and dossier configuration new styles
+                                watch_roots.set(dossier.read().await.dependencies_paths(&*builder_configuration.read().await));
+
+                                if sync.styles_changed {
+                                    log::info!("style file(s) changed, output is assembled again");
                                 }
+
+                                // None => all documents
+                                let documents_to_parse: Option<HashSet<PathBuf>> = if sync.configuration_changed {
+
+                                    None
+
+                                } else {
+
+                                    Some(sync.loaded_documents.union(&sync.affected_documents).cloned().collect())
+                                };
+
+                                let dossier_path = normalize_path(builder_configuration.read().await.input_location());
+
+                                // if compilation error doesn't report its document, changed documents are the most likely culprits
+                                let changed_documents = documents_to_parse.as_ref()
+                                                            .filter(|dtp| !dtp.is_empty())
+                                                            .map(|dtp| {
+                                                                let mut paths: Vec<String> = dtp.iter()
+                                                                                                .map(|path| path.strip_prefix(&dossier_path).unwrap_or(path).to_string_lossy().to_string())
+                                                                                                .collect();
+                                                                paths.sort();
+
+                                                                paths.join(", ")
+                                                            });
     
                                 let build_result = tokio::spawn(async move {
     
-                                    Self::build_dossier_compiling_subset(&mut *dossier.write().await, builder_configuration.read().await.borrow(), documents_to_parse).await
+                                    Self::build_dossier_compiling_subset(dossier.write().await.dossier_mut(), builder_configuration.read().await.borrow(), documents_to_parse).await
                                 });
                 
                                 let preview = preview.clone();
     
-                                match build_result.await {
+                                match build_result.await? {
                                     Ok(_) => {
                 
                                         log::info!("compilation OK");
                                         
                                         if let Some(preview) = preview {
     
-                                            tokio::spawn(async move {
-                                                preview.write().await.update().await
+                                            let preview_url = tokio::spawn(async move {
+                                                let mut preview = preview.write().await;
+
+                                                preview.update().await?;
+
+                                                Ok::<String, PreviewError>(preview.url())
                                             }).await??;
+
+                                            println!("\n\n");
+                                            log::info!("preview is available on {}", preview_url);
+                                            println!("\n\n");
                                         }
-                
-                                        println!("\n\n");
-                                        log::info!("preview is available on {}", PREVIEW_URL);
-                                        println!("\n\n");
     
-                                        return Ok(())
+                                        Ok(())
                                     },
-                                    Err(err) => {
-                                        log::error!("error during compilation: {:?}", err);
-                
-                                        return Err(WatcherError::ElaborationError(err.to_string()))
-                                    }
+                                    Err(err) => Err(Self::watch_failure(err, changed_documents, preview).await),
                                 }
                             }
                         })
                     }
                 }),
+                shutdown_token,
             ).await
         }).await??;
 
@@ -459,6 +1117,16 @@ impl Builder {
     }
 
 
+    /// Paths of style files and local images used by document in `document_path` (normalized)
+    fn document_dependencies_paths(document_path: &Path, builder_configuration: &BuilderConfiguration) -> HashSet<PathBuf> {
+
+        let mut paths = watched_dossier::referenced_local_assets(document_path, builder_configuration.input_location());
+
+        paths.extend(builder_configuration.styles_raw_path().iter().map(|path| normalize_path(Path::new(path))));
+
+        paths
+    }
+
     /// Load document
     pub async fn load_document(builder_configuration: &BuilderConfiguration) -> Result<Document, BuilderError> {
 
@@ -492,12 +1160,109 @@ impl Builder {
     /// Standard file compilation based on `BuilderConfiguration`
     /// It loads, compiles and dumps a document
     pub async fn build_document(builder_configuration: &BuilderConfiguration) -> Result<(), BuilderError> {
+        Self::build_document_reporting(builder_configuration, None).await
+    }
+
+    /// Build document like `build_document`, recording in `report` outcome of each stage and information about document
+    pub async fn build_document_with_report(builder_configuration: &BuilderConfiguration, report: &mut BuildReport) -> Result<(), BuilderError> {
+
+        report.set_configuration(builder_configuration);
+
+        Self::build_document_reporting(builder_configuration, Some(report)).await
+    }
+
+    async fn build_document_reporting(builder_configuration: &BuilderConfiguration, mut report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
 
         log::info!("start to build document");
 
         let build_start = Instant::now();
 
-        let mut document = Self::load_document(builder_configuration).await?;        
+        let document = Self::load_document(builder_configuration).await;
+
+        if let Some(report) = report.as_deref_mut() {
+
+            report.record_stage(BuildStage::Load, build_start, &document);
+
+            if let Ok(document) = document.as_ref() {
+                report.record_document_load(DocumentLoadMeasure {
+                    name: document.name().clone(),
+                    path: builder_configuration.input_location().clone(),
+                    time_ms: Some(build_start.elapsed().as_millis()),
+                });
+            }
+        }
+
+        let mut document = document?;
+
+        Self::check_cancellation(builder_configuration)?;
+
+        let json_dump = Self::spawn_json_dump_if_requested(&document, document.name(), builder_configuration)?;
+
+        let compiled_dump_result = if builder_configuration.needs_compilation() {
+
+            Self::compile_and_dump_document(&mut document, builder_configuration, build_start, report.as_deref_mut())
+
+        } else {
+
+            Ok(())
+        };
+
+        let json_dump_result = Self::await_json_dump(json_dump, report).await;
+
+        compiled_dump_result?;
+        json_dump_result?;
+
+        log::info!("document build in {} ms", build_start.elapsed().as_millis());
+
+        Ok(())
+    }
+
+    fn compile_and_dump_document(document: &mut Document, builder_configuration: &BuilderConfiguration, build_start: Instant, mut report: Option<&mut BuildReport>) -> Result<(), BuilderError> {
+
+        let assembled_document = Self::compile_document_reporting(document, builder_configuration, report.as_deref_mut())?;
+
+        log::info!("document compiled in {} ms", build_start.elapsed().as_millis());
+
+        Self::check_cancellation(builder_configuration)?;
+
+        log::info!("dumping...");
+
+        let build_format = BuildFormat::from(builder_configuration.format());
+
+        let output_location = if builder_configuration.formats().len() > 1 {
+            builder_configuration.output_location().with_extension(build_format.get_extension())
+        } else {
+            builder_configuration.output_location().clone()
+        };
+
+        let dump_configuration = DumpConfiguration::new(output_location.clone(), builder_configuration.force_output().unwrap_or(false));
+
+        let mut artifact = Artifact::from(assembled_document);
+
+        let dump_start = Instant::now();
+
+        let dump_result = Self::dump_artifact(&mut artifact, &dump_configuration);
+
+        if let Some(report) = report {
+
+            report.record_stage(BuildStage::Dump, dump_start, &dump_result);
+
+            if dump_result.is_ok() {
+                report.add_output(&build_format, output_location.clone(), fs::metadata(&output_location).map(|m| m.len()).unwrap_or(0));
+            }
+        }
+
+        dump_result?;
+
+        Ok(())
+    }
+
+    /// Compile document based on `BuilderConfiguration` without dumping it. Output is a standalone page
+    pub async fn compile_document(document: &mut Document, builder_configuration: &BuilderConfiguration) -> Result<String, BuilderError> {
+        Self::compile_document_reporting(document, builder_configuration, None)
+    }
+
+    fn compile_document_reporting(document: &mut Document, builder_configuration: &BuilderConfiguration, report: Option<&mut BuildReport>) -> Result<String, BuilderError> {
 
         let compilation_configuration = builder_configuration.generate_compilation_configuration();
 
@@ -524,9 +1289,16 @@ impl Builder {
 
                 assembler_configuration.set_theme(builder_configuration.theme().clone().unwrap_or(Theme::default()));
 
+                // style files passed by user (e.g. --style-file)
+                assembler_configuration.external_styles_paths_mut().extend(builder_configuration.styles_raw_path().iter().map(PathBuf::from));
+
                 if let Some(there_is_preview) = builder_configuration.preview() {
                     if there_is_preview {
-                        assembler_configuration.external_styles_mut().push(include_str!("preview/check_preview_updates.js").to_string())
+
+                        if let Some(preview_url) = builder_configuration.preview_url() {
+
+                            assembler_configuration.external_scripts_mut().push(HtmlPreview::client_script(preview_url))
+                        }
                     }
                 }
 
@@ -534,23 +1306,173 @@ impl Builder {
             },
         };
 
-        compilation_configuration_overlay.set_assembler_configuration(assembler_configuration);
+        compilation_configuration_overlay.set_assembler_configuration(assembler_configuration.clone());
 
-        let compiled_document = document.compile(builder_configuration.format(), &builder_configuration.codex(), &compilation_configuration, compilation_configuration_overlay)?;
+        let codex = builder_configuration.codex();
 
-        log::info!("document compiled in {} ms", build_start.elapsed().as_millis());
+        let compilation_start = Instant::now();
 
-        log::info!("dumping...");
+        let compilation_result = document.compile(builder_configuration.format(), &codex, &compilation_configuration, compilation_configuration_overlay)
+                                    .map_err(|err| BuilderError::DocumentCompilationError(document.name().clone(), err))
+                                    .and_then(|compiled_document| {
+
+                                        // assemble a standalone page, so theme and preview scripts are included in output
+                                        Ok(codex.assembler().assemble_document_standalone(document.name(), &compiled_document, None, None, &assembler_configuration)?)
+                                    });
 
-        let output_location = builder_configuration.output_location().clone();
+        if let Some(report) = report {
 
-        let dump_configuration = DumpConfiguration::new(output_location, builder_configuration.force_output().unwrap_or(false));
+            report.record_stage(BuildStage::Compile, compilation_start, &compilation_result);
 
-        let mut artifact = Artifact::from(compiled_document.content());
+            if let Ok(assembled_document) = compilation_result.as_ref() {
+                report.record_document_compilation(DocumentCompilationMeasure {
+                    name: document.name().clone(),
+                    path: builder_configuration.input_location().clone(),
+                    time_ms: compilation_start.elapsed().as_millis(),
+                    compiled_size: assembled_document.len(),
+                    from_build_cache: false,
+                });
+            }
+        }
 
-        artifact.dump(&dump_configuration)?;
+        compilation_result
+    }
 
-        log::info!("document build in {} ms", build_start.elapsed().as_millis());
+    /// Watch filesystem and compile document if any changes occur. Document file and sibling assets directory are watched
+    /// 
+    /// - debounce_time is the quiet time after last change before compilation (changes in this time are compiled together)
+    /// - shutdown_token stops watching (in-flight compilation is completed before)
+    pub async fn watch_compile_document(builder_configuration: BuilderConfiguration, debounce_time: Duration, preview: Option<Arc<TokioRwLock<HtmlPreview>>>, shutdown_token: CancellationToken) -> Result<(), BuilderError> {
+
+        let input_location_abs = Arc::new(Self::canonical_input_location(&builder_configuration)?);
+
+        let watched_location_abs = Arc::new(input_location_abs.parent().unwrap().to_path_buf());
+
+        let assets_location_abs = Arc::new(watched_location_abs.join(dossier::ASSETS_DIR));
+
+        // styles and referenced images can be outside of document directory
+        let watch_roots = WatchRoots::new();
+        watch_roots.set(Self::document_dependencies_paths(&input_location_abs, &builder_configuration));
+
+        let builder_configuration = Arc::new(builder_configuration);
+
+        let mut watcher = NmdWatcher::new(
+            debounce_time,
+            &watched_location_abs.clone(),
+            watch_roots.clone(),
+            Box::new({
+
+                let preview = preview.clone();
+                let builder_configuration = Arc::clone(&builder_configuration);
+                let input_location_abs = input_location_abs.clone();
+
+                move || {
+
+                    let builder_configuration = Arc::clone(&builder_configuration);
+                    let preview = preview.clone();
+                    let input_location_abs = input_location_abs.clone();
+
+                    Box::pin(async move {
+
+                        let compilation_result = Self::build_document(&builder_configuration).await;
+
+                        println!("\n\n");
+                        log::info!("watch mode ON: modification to the document or to its assets will cause recompilation");
+                        log::info!("start watching: {:?}", input_location_abs);
+                        log::info!("press CTRL + C to terminate");
+                        println!("\n\n");
+
+                        match compilation_result {
+                            Ok(_) => {
+
+                                log::info!("compilation OK");
+
+                                if let Some(preview) = preview {
+
+                                    preview.write().await.render().await?;
+                                }
+
+                                Ok(())
+                            },
+                            Err(err) => Err(Self::watch_failure(err, None, preview).await),
+                        }
+                    })
+                }
+            }),
+            Box::new({
+
+                let input_location_abs = input_location_abs.clone();
+                let assets_location_abs = assets_location_abs.clone();
+                let watch_roots = watch_roots.clone();
+
+                move |event| {
+
+                    let input_location_abs = input_location_abs.clone();
+                    let assets_location_abs = assets_location_abs.clone();
+                    let watch_roots = watch_roots.clone();
+
+                    Box::pin(async move {
+
+                        if event.paths.iter().map(|path| normalize_path(path)).any(|path| path.eq(&*input_location_abs) || path.starts_with(&*assets_location_abs) || watch_roots.contains(&path)) {
+
+                            log::info!("recompilation needed");
+                            return Ok(true)
+                        }
+
+                        log::info!("recompilation not needed");
+
+                        Ok(false)
+                    })
+                }
+            }),
+            Box::new({
+
+                let builder_configuration = Arc::clone(&builder_configuration);
+                let preview = preview.clone();
+                let input_location_abs = input_location_abs.clone();
+                let watch_roots = watch_roots.clone();
+
+                move |_paths| {
+
+                    let builder_configuration = Arc::clone(&builder_configuration);
+                    let preview = preview.clone();
+                    let input_location_abs = input_location_abs.clone();
+                    let watch_roots = watch_roots.clone();
+
+                    Box::pin(async move {
+
+                        // document can reference new images
+                        watch_roots.set(Self::document_dependencies_paths(&input_location_abs, &builder_configuration));
+
+                        match Self::build_document(&builder_configuration).await {
+                            Ok(_) => {
+
+                                log::info!("compilation OK");
+
+                                if let Some(preview) = preview {
+
+                                    let mut preview = preview.write().await;
+
+                                    preview.update().await?;
+
+                                    println!("\n\n");
+                                    log::info!("preview is available on {}", preview.url());
+                                    println!("\n\n");
+                                }
+
+                                Ok(())
+                            },
+                            Err(err) => Err(Self::watch_failure(err, None, preview).await),
+                        }
+                    })
+                }
+            }),
+            shutdown_token,
+        ).await?;
+
+        watcher.start().await?;
+
+        log::info!("stop watching...");
 
         Ok(())
     }
@@ -558,4 +1480,159 @@ impl Builder {
 
 #[cfg(test)]
 mod test {
-}
\ No newline at end of file
+    use std::{collections::HashSet, fs, path::PathBuf};
+
+    use crate::utility::normalize_path;
+
+    use super::{build_format::BuildFormat, builder_configuration::BuilderConfiguration, constants::BUILD_CACHE_DIR, Builder};
+
+
+    #[tokio::test]
+    async fn cached_builds_have_the_same_output() {
+
+        let dossier_path = std::env::temp_dir().join(format!("nmd-test-build-cache-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dossier_path);
+
+        fs::create_dir_all(dossier_path.join("part-1")).unwrap();
+        fs::create_dir_all(dossier_path.join("part-2")).unwrap();
+
+        fs::write(dossier_path.join("nmd.yml"), r#"
+name: Cached dossier
+toc:
+  title: Table of contents
+  include_in_output: true
+  page_numbers: false
+  plain: false
+  maximum_heading_level: 4
+documents:
+- ./part-1/intro.nmd
+- ./part-2/intro.nmd
+"#).unwrap();
+
+        // same file name in different directories
+        fs::write(dossier_path.join("part-1").join("intro.nmd"), "# First part\n\nfirst paragraph\n").unwrap();
+        fs::write(dossier_path.join("part-2").join("intro.nmd"), "# Second part\n\nsecond paragraph\n").unwrap();
+
+        let mut builder_configuration = BuilderConfiguration::new(dossier_path.clone(), PathBuf::from("."));
+        builder_configuration.set_build_cache(Some(true));
+
+        // compiled output and, for each document, if it is taken from build cache
+        let build = || async {
+
+            let mut dossier = Builder::load_dossier(&builder_configuration).await.unwrap();
+
+            let (compilation_configuration, compilation_configuration_overlay) = Builder::prepare_dossier_compilation(&dossier, &builder_configuration);
+
+            let (outcome, measures) = Builder::compile_dossier_by_documents(&mut dossier, &builder_configuration, &compilation_configuration, compilation_configuration_overlay, None, true).unwrap();
+
+            (outcome.content().clone(), measures.iter().map(|measure| measure.from_build_cache).collect::<Vec<bool>>())
+        };
+
+        let (first_output, first_from_cache) = build().await;
+        let (second_output, second_from_cache) = build().await;
+
+        fs::write(dossier_path.join("part-2").join("intro.nmd"), "# Second part\n\nchanged paragraph\n").unwrap();
+
+        let (third_output, third_from_cache) = build().await;
+
+        assert!(dossier_path.join(BUILD_CACHE_DIR).join(".gitignore").is_file());
+
+        fs::remove_dir_all(&dossier_path).unwrap();
+
+        assert_eq!(first_from_cache, vec![false, false]);
+        assert_eq!(second_from_cache, vec![true, true]);
+        assert_eq!(third_from_cache, vec![true, false]);
+
+        assert_eq!(first_output, second_output);
+
+        assert!(second_output.contains("first paragraph"));
+        assert!(second_output.contains("second paragraph"));
+        assert!(!second_output.contains(r##"href="#""##));
+
+        assert!(third_output.contains("first paragraph"));
+        assert!(third_output.contains("changed paragraph"));
+    }
+
+    #[tokio::test]
+    async fn json_only_document_build_writes_json() {
+
+        let dir = std::env::temp_dir().join(format!("nmd-test-json-document-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(dir.join("note.nmd"), "# Note\n\nsome text\n").unwrap();
+
+        let mut builder_configuration = BuilderConfiguration::new(dir.join("note.nmd"), PathBuf::from("."));
+        builder_configuration.set_formats(vec![BuildFormat::Json]);
+        builder_configuration.set_output_location(builder_configuration.default_document_output_location());
+
+        Builder::build_document(&builder_configuration).await.unwrap();
+
+        let json_written = dir.join("note.json").is_file();
+        let html_written = dir.join("note.html").exists();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(json_written);
+        assert!(!html_written);
+    }
+
+    #[tokio::test]
+    async fn skipped_documents_are_kept_in_split_output() {
+
+        let dossier_path = std::env::temp_dir().join(format!("nmd-test-split-fast-draft-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dossier_path);
+
+        fs::create_dir_all(&dossier_path).unwrap();
+
+        fs::write(dossier_path.join("nmd.yml"), r#"
+name: Split dossier
+documents:
+- ./first.nmd
+- ./second.nmd
+"#).unwrap();
+
+        fs::write(dossier_path.join("first.nmd"), "# First\n\nfirst paragraph\n").unwrap();
+        fs::write(dossier_path.join("second.nmd"), "# Second\n\nsecond paragraph\n").unwrap();
+
+        let output_dir = dossier_path.join("site");
+
+        let mut builder_configuration = BuilderConfiguration::new(dossier_path.clone(), output_dir.clone());
+        builder_configuration.set_split(Some(true));
+        builder_configuration.set_force_output(Some(true));
+
+        let mut dossier = Builder::load_dossier(&builder_configuration).await.unwrap();
+
+        Builder::build_dossier(&mut dossier, &builder_configuration).await.unwrap();
+
+        let first_page = fs::read_to_string(output_dir.join("first.html")).unwrap();
+
+        fs::write(dossier_path.join("second.nmd"), "# Second\n\nchanged paragraph\n").unwrap();
+
+        builder_configuration.set_fast_draft(Some(true));
+
+        let mut dossier = Builder::load_dossier(&builder_configuration).await.unwrap();
+
+        let subset = HashSet::from([normalize_path(&dossier_path.join("second.nmd"))]);
+
+        Builder::build_dossier_compiling_subset(&mut dossier, &builder_configuration, Some(subset)).await.unwrap();
+
+        let kept_first_page = fs::read_to_string(output_dir.join("first.html")).unwrap();
+        let second_page = fs::read_to_string(output_dir.join("second.html")).unwrap();
+        let index = fs::read_to_string(output_dir.join("index.html")).unwrap();
+
+        fs::remove_dir_all(&dossier_path).unwrap();
+
+        assert_eq!(first_page, kept_first_page);
+
+        assert!(second_page.contains("changed paragraph"));
+        assert!(second_page.contains(r#"href="first.html""#));
+
+        assert!(index.contains(r#"href="first.html#"#));
+        assert!(index.contains(r#"href="second.html#"#));
+    }
+}
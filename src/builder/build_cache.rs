@@ -0,0 +1,193 @@
+use std::{fs, io, path::{Path, PathBuf}, time::UNIX_EPOCH};
+use nmd_core::{compilation::compilation_outcome::CompilationOutcome, dossier::{self, dossier_configuration::DossierConfiguration}};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{constants::VERSION, utility::normalize_path};
+use super::builder_configuration::BuilderConfiguration;
+
+
+/// Build cache directory ignores itself, so it is not committed by mistake
+const BUILD_CACHE_GITIGNORE_CONTENT: &str = "# automatically created by NMD build cache\n*\n";
+
+
+#[derive(Error, Debug)]
+pub enum BuildCacheError {
+
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+
+/// Cached compilation outcome of a document
+#[derive(Debug, Serialize, Deserialize)]
+struct BuildCacheEntry {
+    key: String,
+    outcome: CompilationOutcome,
+}
+
+
+/// On-disk cache of compiled documents. Each document (identified by its path relative to dossier directory) has only one entry,
+/// which is valid only if its key (built using document content and configuration used to compile it) matches.
+///
+/// Keys are SHA-256 hashes, so they are stable across NMD builds and machines (e.g. cache can be restored in CI)
+#[derive(Debug)]
+pub struct BuildCache {
+    cache_dir: PathBuf,
+    dossier_dir: PathBuf,
+    configuration_key: String,
+}
+
+impl BuildCache {
+
+    pub fn new(cache_dir: PathBuf, dossier_dir: &Path, configuration_key: String) -> Self {
+        Self {
+            cache_dir,
+            dossier_dir: normalize_path(dossier_dir),
+            configuration_key,
+        }
+    }
+
+    /// Build the key of all options which have an effect on documents compilation output
+    pub fn configuration_key(builder_configuration: &BuilderConfiguration, dossier_configuration: &DossierConfiguration) -> Result<String, BuildCacheError> {
+
+        let mut hasher = Sha256::new();
+
+        hash_field(&mut hasher, VERSION.unwrap_or_default());
+        hash_field(&mut hasher, builder_configuration.format().get_extension());
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.fast_draft()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.embed_local_image()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.embed_remote_image()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.compress_embed_image()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.strict_image_src_check()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.nuid()));
+        hash_field(&mut hasher, format!("{:?}", builder_configuration.theme()));
+
+        // JSON objects are sorted, so dossier configuration maps are hashed in a stable order
+        hash_field(&mut hasher, serde_json::to_value(dossier_configuration)?.to_string());
+
+        // embedded images are part of output, so each change to assets must invalidate cache
+        if builder_configuration.embed_local_image().unwrap_or(false) {
+
+            let assets_dir = builder_configuration.input_location().join(dossier::ASSETS_DIR);
+
+            if assets_dir.is_dir() {
+                Self::hash_dir_metadata(&assets_dir, &mut hasher)?;
+            }
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn hash_dir_metadata(dir: &Path, hasher: &mut Sha256) -> Result<(), io::Error> {
+
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+                                            .filter_map(|entry| entry.ok())
+                                            .map(|entry| entry.path())
+                                            .collect();
+
+        entries.sort();
+
+        for entry in entries {
+
+            if entry.is_dir() {
+
+                Self::hash_dir_metadata(&entry, hasher)?;
+
+            } else {
+
+                let metadata = fs::metadata(&entry)?;
+
+                hash_field(hasher, entry.to_string_lossy().as_bytes());
+                hash_field(hasher, metadata.len().to_le_bytes());
+
+                if let Ok(modified) = metadata.modified() {
+                    hash_field(hasher, modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().to_le_bytes());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Identifier of document in `document_path`: its normalized path relative to dossier directory (absolute if it is outside)
+    fn document_id(&self, document_path: &Path) -> String {
+
+        let document_path = normalize_path(document_path);
+
+        let relative_path = document_path.strip_prefix(&self.dossier_dir).unwrap_or(&document_path);
+
+        relative_path.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+    }
+
+    /// Build the key of a document
+    pub fn document_key(&self, document_path: &Path, document_content: &str) -> String {
+
+        let mut hasher = Sha256::new();
+
+        hash_field(&mut hasher, &self.configuration_key);
+        hash_field(&mut hasher, self.document_id(document_path));
+        hash_field(&mut hasher, document_content);
+
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn entry_path(&self, document_path: &Path) -> PathBuf {
+        self.cache_dir.join(format!("{:x}.json", Sha256::digest(self.document_id(document_path))))
+    }
+
+    /// Load cached compilation outcome of document, if it is present and it is valid for passed key
+    pub fn load(&self, document_path: &Path, key: &str) -> Option<CompilationOutcome> {
+
+        let entry = fs::read_to_string(self.entry_path(document_path)).ok()?;
+
+        match serde_json::from_str::<BuildCacheEntry>(&entry) {
+            Ok(entry) => {
+
+                if entry.key.eq(key) {
+                    return Some(entry.outcome)
+                }
+
+                None
+            },
+            Err(err) => {
+                log::warn!("invalid build cache entry of document {:?} will be ignored: {}", document_path, err);
+
+                None
+            }
+        }
+    }
+
+    /// Store compilation outcome of document, replacing previous one
+    pub fn store(&self, document_path: &Path, key: &str, outcome: &CompilationOutcome) -> Result<(), BuildCacheError> {
+
+        if !self.cache_dir.is_dir() {
+
+            fs::create_dir_all(&self.cache_dir)?;
+
+            fs::write(self.cache_dir.join(".gitignore"), BUILD_CACHE_GITIGNORE_CONTENT)?;
+        }
+
+        let entry = BuildCacheEntry {
+            key: key.to_string(),
+            outcome: CompilationOutcome::from(outcome.content().as_str()),
+        };
+
+        fs::write(self.entry_path(document_path), serde_json::to_string(&entry)?)?;
+
+        Ok(())
+    }
+}
+
+/// Hash a field prefixed by its length, so adjacent fields can not be confused
+fn hash_field(hasher: &mut Sha256, field: impl AsRef<[u8]>) {
+
+    let field = field.as_ref();
+
+    hasher.update((field.len() as u64).to_le_bytes());
+    hasher.update(field);
+}
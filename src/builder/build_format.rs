@@ -0,0 +1,56 @@
+use std::{fmt::Display, str::FromStr};
+use nmd_core::output_format::{OutputFormat, OutputFormatError};
+
+
+/// Format of a build artifact. Compiled formats are produced by NMD compiler, while others are produced
+/// directly from loaded dossier or document (e.g. JSON is the same output of `analyze` command)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BuildFormat {
+    Html,
+    Json,
+}
+
+impl BuildFormat {
+
+    /// Output format used to compile, `None` if format doesn't need compilation
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        match self {
+            BuildFormat::Html => Some(OutputFormat::Html),
+            BuildFormat::Json => None,
+        }
+    }
+
+    pub fn get_extension(&self) -> String {
+        match self {
+            BuildFormat::Html => OutputFormat::Html.get_extension(),
+            BuildFormat::Json => String::from("json"),
+        }
+    }
+}
+
+impl From<&OutputFormat> for BuildFormat {
+    fn from(output_format: &OutputFormat) -> Self {
+        match output_format {
+            OutputFormat::Html => BuildFormat::Html,
+        }
+    }
+}
+
+impl FromStr for BuildFormat {
+
+    type Err = OutputFormatError;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+
+            _ => Ok(Self::from(&OutputFormat::from_str(format)?)),
+        }
+    }
+}
+
+impl Display for BuildFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_extension())
+    }
+}
@@ -0,0 +1,321 @@
+use std::{collections::HashSet, fmt::Display, fs, io, path::{Path, PathBuf}, sync::Mutex, time::Instant};
+use nmd_core::{bibliography::Bibliography, compilation::compilation_configuration::CompilableResourceType, resource::text_reference::TextReferenceMap, theme::Theme};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::constants::VERSION;
+use super::{build_format::BuildFormat, builder_configuration::BuilderConfiguration};
+
+
+pub const DEFAULT_BUILD_REPORT_FILE_NAME: &str = "nmd-build-report.json";
+
+
+/// Warnings emitted during a reported build. `None` if no build is collecting warnings
+static COLLECTED_WARNINGS: Lazy<Mutex<Option<Vec<String>>>> = Lazy::new(|| Mutex::new(None));
+
+
+/// Collect a warning emitted during build. It is ignored if no build report is collecting warnings
+pub fn collect_warning(warning: String) {
+
+    if let Ok(mut collected_warnings) = COLLECTED_WARNINGS.lock() {
+        if let Some(collected_warnings) = collected_warnings.as_mut() {
+            collected_warnings.push(warning);
+        }
+    }
+}
+
+
+#[derive(Error, Debug)]
+pub enum BuildReportError {
+
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildStage {
+    Load,
+    Compile,
+    Dump,
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildStageReport {
+    stage: BuildStage,
+    success: bool,
+    time_ms: u128,
+    error: Option<String>,
+}
+
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DocumentBuildReport {
+    name: String,
+    path: Option<PathBuf>,
+    load_time_ms: Option<u128>,
+    compile_time_ms: Option<u128>,
+    compiled_size: Option<usize>,
+    from_build_cache: bool,
+}
+
+
+/// Load time of a document, if it is known (documents of a dossier are loaded together by nmd-core)
+#[derive(Debug, Clone)]
+pub struct DocumentLoadMeasure {
+    pub name: String,
+    pub path: PathBuf,
+    pub time_ms: Option<u128>,
+}
+
+
+/// Compilation time and compiled size of a document
+#[derive(Debug, Clone)]
+pub struct DocumentCompilationMeasure {
+    pub name: String,
+    pub path: PathBuf,
+    pub time_ms: u128,
+    pub compiled_size: usize,
+    pub from_build_cache: bool,
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildOutputReport {
+    format: String,
+    path: PathBuf,
+    size: u64,
+}
+
+
+/// Configuration actually used to build, i.e. `BuilderConfiguration` after dossier configuration merging
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReportConfiguration {
+    format: String,
+    formats: Vec<String>,
+    input_location: PathBuf,
+    output_location: PathBuf,
+    force_output: Option<bool>,
+    fast_draft: Option<bool>,
+    embed_local_image: Option<bool>,
+    embed_remote_image: Option<bool>,
+    compress_embed_image: Option<bool>,
+    strict_image_src_check: Option<bool>,
+    parallelization: Option<bool>,
+    use_remote_addons: Option<bool>,
+    references: Option<TextReferenceMap>,
+    documents_subset_to_compile: Option<HashSet<String>>,
+    bibliography: Option<Bibliography>,
+    theme: Option<Theme>,
+    styles_raw_path: Vec<String>,
+    nuid: Option<bool>,
+    build_cache: Option<bool>,
+    split: Option<bool>,
+}
+
+impl From<&BuilderConfiguration> for BuildReportConfiguration {
+    fn from(builder_configuration: &BuilderConfiguration) -> Self {
+        Self {
+            format: builder_configuration.format().get_extension(),
+            formats: builder_configuration.formats().iter().map(|format| format.get_extension()).collect(),
+            input_location: builder_configuration.input_location().clone(),
+            output_location: builder_configuration.output_location().clone(),
+            force_output: builder_configuration.force_output(),
+            fast_draft: *builder_configuration.fast_draft(),
+            embed_local_image: *builder_configuration.embed_local_image(),
+            embed_remote_image: *builder_configuration.embed_remote_image(),
+            compress_embed_image: *builder_configuration.compress_embed_image(),
+            strict_image_src_check: *builder_configuration.strict_image_src_check(),
+            parallelization: *builder_configuration.parallelization(),
+            use_remote_addons: *builder_configuration.use_remote_addons(),
+            references: builder_configuration.references().clone(),
+            documents_subset_to_compile: builder_configuration.documents_subset_to_compile().clone(),
+            bibliography: builder_configuration.bibliography().clone(),
+            theme: builder_configuration.theme().clone(),
+            styles_raw_path: builder_configuration.styles_raw_path().clone(),
+            nuid: builder_configuration.nuid(),
+            build_cache: builder_configuration.build_cache(),
+            split: builder_configuration.split(),
+        }
+    }
+}
+
+
+/// Machine-readable report of a build: stages outcome, per-document timings, output and warnings
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    nmd_version: String,
+    resource_type: String,
+    input_location: PathBuf,
+    success: bool,
+    total_time_ms: u128,
+    stages: Vec<BuildStageReport>,
+    documents: Vec<DocumentBuildReport>,
+    outputs: Vec<BuildOutputReport>,
+    configuration: Option<BuildReportConfiguration>,
+    warnings: Vec<String>,
+
+    #[serde(skip)]
+    start: Instant,
+}
+
+impl BuildReport {
+
+    /// Create a new report and start to collect warnings
+    pub fn start(builder_configuration: &BuilderConfiguration) -> Self {
+
+        if let Ok(mut collected_warnings) = COLLECTED_WARNINGS.lock() {
+            *collected_warnings = Some(Vec::new());
+        }
+
+        let resource_type = match builder_configuration.resource_type() {
+            CompilableResourceType::Dossier => "dossier",
+            CompilableResourceType::File => "document",
+            CompilableResourceType::Unknown => "unknown",
+        };
+
+        Self {
+            nmd_version: VERSION.unwrap_or("unknown").to_string(),
+            resource_type: resource_type.to_string(),
+            input_location: builder_configuration.input_location().clone(),
+            success: false,
+            total_time_ms: 0,
+            stages: Vec::new(),
+            documents: Vec::new(),
+            outputs: Vec::new(),
+            configuration: None,
+            warnings: Vec::new(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Stop to collect warnings and set overall outcome
+    pub fn finish(&mut self) {
+
+        if let Ok(mut collected_warnings) = COLLECTED_WARNINGS.lock() {
+            self.warnings = collected_warnings.take().unwrap_or_default();
+        }
+
+        self.total_time_ms = self.start.elapsed().as_millis();
+        self.success = !self.stages.is_empty() && self.stages.iter().all(|stage| stage.success);
+    }
+
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    /// Write report as pretty JSON
+    pub fn dump(&self, path: &Path) -> Result<(), BuildReportError> {
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        log::info!("build report written in {:?}", path);
+
+        Ok(())
+    }
+
+    /// Record outcome of a stage started at `start`
+    pub fn record_stage<T, E: Display>(&mut self, stage: BuildStage, start: Instant, result: &Result<T, E>) {
+        self.stages.push(BuildStageReport {
+            stage,
+            success: result.is_ok(),
+            time_ms: start.elapsed().as_millis(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+        });
+    }
+
+    pub fn set_configuration(&mut self, builder_configuration: &BuilderConfiguration) {
+        self.configuration = Some(BuildReportConfiguration::from(builder_configuration));
+    }
+
+    pub fn add_output(&mut self, format: &BuildFormat, path: PathBuf, size: u64) {
+        self.outputs.push(BuildOutputReport {
+            format: format.get_extension(),
+            path,
+            size
+        });
+    }
+
+    /// Report of document, identified by its path (different documents can have the same name, e.g. `part-1/intro.nmd` and `part-2/intro.nmd`)
+    fn document_mut(&mut self, name: &str, path: &Path) -> &mut DocumentBuildReport {
+
+        if let Some(index) = self.documents.iter().position(|document| document.path.as_deref() == Some(path)) {
+            return &mut self.documents[index]
+        }
+
+        self.documents.push(DocumentBuildReport {
+            name: name.to_string(),
+            path: Some(path.to_path_buf()),
+            ..Default::default()
+        });
+
+        self.documents.last_mut().unwrap()
+    }
+
+    pub fn record_document_load(&mut self, measure: DocumentLoadMeasure) {
+
+        let document = self.document_mut(&measure.name, &measure.path);
+
+        document.load_time_ms = measure.time_ms;
+    }
+
+    pub fn record_document_compilation(&mut self, measure: DocumentCompilationMeasure) {
+
+        let document = self.document_mut(&measure.name, &measure.path);
+
+        document.compile_time_ms = Some(measure.time_ms);
+        document.compiled_size = Some(measure.compiled_size);
+        document.from_build_cache = measure.from_build_cache;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{BuildReport, DocumentCompilationMeasure, DocumentLoadMeasure};
+    use crate::builder::builder_configuration::BuilderConfiguration;
+
+
+    #[test]
+    fn documents_with_the_same_name_are_reported_separately() {
+
+        let mut report = BuildReport::start(&BuilderConfiguration::new(PathBuf::from("dossier"), PathBuf::from(".")));
+
+        for part in ["part-1", "part-2"] {
+
+            let path = PathBuf::from("dossier").join(part).join("intro.nmd");
+
+            report.record_document_load(DocumentLoadMeasure {
+                name: String::from("intro.nmd"),
+                path: path.clone(),
+                time_ms: None,
+            });
+
+            report.record_document_compilation(DocumentCompilationMeasure {
+                name: String::from("intro.nmd"),
+                path,
+                time_ms: 1,
+                compiled_size: part.len(),
+                from_build_cache: part == "part-2",
+            });
+        }
+
+        report.finish();
+
+        assert_eq!(report.documents.len(), 2);
+
+        assert_eq!(report.documents[0].path, Some(PathBuf::from("dossier/part-1/intro.nmd")));
+        assert!(!report.documents[0].from_build_cache);
+
+        assert_eq!(report.documents[1].path, Some(PathBuf::from("dossier/part-2/intro.nmd")));
+        assert!(report.documents[1].from_build_cache);
+    }
+}
@@ -1,15 +1,22 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, path::{Path, PathBuf}};
 use getset::{CopyGetters, Getters, MutGetters, Setters};
-use nmd_core::{bibliography::Bibliography, codex::Codex, compilation::compilation_configuration::{CompilableResourceType, CompilationConfiguration}, dossier::dossier_configuration::DossierConfiguration, output_format::OutputFormat, resource::text_reference::TextReferenceMap, theme::Theme};
+use tokio_util::sync::CancellationToken;
+use super::build_format::BuildFormat;
+use nmd_core::{bibliography::Bibliography, codex::Codex, compilation::compilation_configuration::{CompilableResourceType, CompilationConfiguration}, dossier::dossier_configuration::DossierConfiguration, output_format::OutputFormat, resource::text_reference::TextReferenceMap, theme::Theme, utility::file_utility};
 
 
 /// Struct which contains all information about possible compilation options. It is used to wrap specific user requests for compilation 
 #[derive(Debug, Getters, CopyGetters, MutGetters, Setters, Clone)]
 pub struct BuilderConfiguration {
 
+    /// Format used to compile
     #[getset(get = "pub", set = "pub")]
     format: OutputFormat,
 
+    /// Formats of artifacts which will be produced from the same loaded dossier or document
+    #[getset(get = "pub", set = "pub")]
+    formats: Vec<BuildFormat>,
+
     #[getset(get = "pub")]
     input_location: PathBuf,
 
@@ -61,11 +68,24 @@ pub struct BuilderConfiguration {
     #[getset(get_copy = "pub", set = "pub")]
     preview: Option<bool>,
 
+    #[getset(get = "pub", set = "pub")]
+    preview_url: Option<String>,
+
     #[getset(get_copy = "pub", set = "pub")]
     watching: Option<bool>,
 
     #[getset(get_copy = "pub", set = "pub")]
     nuid: Option<bool>,
+
+    #[getset(get_copy = "pub", set = "pub")]
+    build_cache: Option<bool>,
+
+    #[getset(get_copy = "pub", set = "pub")]
+    split: Option<bool>,
+
+    /// Build is stopped between its stages (load, compile and dump) as soon as this token is cancelled
+    #[getset(get = "pub", set = "pub")]
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl BuilderConfiguration {
@@ -96,6 +116,35 @@ impl BuilderConfiguration {
         self.input_location = input_location;
     }
 
+    /// Extension of output file: the one of compiled format if it is requested, otherwise the one of first requested format
+    pub fn output_extension(&self) -> String {
+        self.formats.iter()
+            .find(|format| format.output_format().is_some())
+            .or(self.formats.first())
+            .map(|format| format.get_extension())
+            .unwrap_or_else(|| self.format.get_extension())
+    }
+
+    /// Default output location of a document build: a file named after input document, in its directory
+    pub fn default_document_output_location(&self) -> PathBuf {
+
+        let file_name = file_utility::build_output_file_name(
+            self.input_location.file_stem().unwrap_or_default().to_string_lossy().to_string().as_str(),
+            Some(&self.output_extension())
+        );
+
+        if self.input_location.is_dir() {
+            return self.input_location.join(file_name)
+        }
+
+        self.input_location.parent().unwrap_or(Path::new(".")).join(file_name)
+    }
+
+    /// `true` if at least one of requested formats needs compilation
+    pub fn needs_compilation(&self) -> bool {
+        self.formats.iter().any(|format| format.output_format().is_some())
+    }
+
     pub fn codex(&self) -> Codex {
 
         Codex::from(&self.format)
@@ -249,6 +298,7 @@ impl Default for BuilderConfiguration {
     fn default() -> Self {
         Self {
             format: Default::default(),
+            formats: vec![BuildFormat::Html],
             input_location: PathBuf::from("."),
             output_location: PathBuf::from("."),
             fast_draft: Some(false),
@@ -266,8 +316,12 @@ impl Default for BuilderConfiguration {
             styles_raw_path: Vec::new(),
             resource_type: CompilableResourceType::default(),
             preview: Some(false),
+            preview_url: None,
             watching: Some(false),
             nuid: Some(false),
+            build_cache: Some(false),
+            split: Some(false),
+            cancellation_token: None,
         }
     }
 }
\ No newline at end of file
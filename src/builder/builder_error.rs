@@ -1,9 +1,12 @@
 use nmd_core::{assembler::AssemblerError, compilation::compilation_error::CompilationError, dumpable::DumpError, load::LoadError};
+use std::{io, path::PathBuf};
 use thiserror::Error;
 use tokio::task::JoinError;
 
 use crate::{preview::PreviewError, watcher::WatcherError};
 
+use super::{build_cache::BuildCacheError, build_report::BuildReportError};
+
 #[derive(Error, Debug)]
 pub enum BuilderError {
 
@@ -13,9 +16,15 @@ pub enum BuilderError {
     #[error(transparent)]
     LoadError(#[from] LoadError),
 
+    #[error("document '{0}' can not be loaded: {1}")]
+    DocumentLoadError(String, LoadError),
+
     #[error(transparent)]
     CompilationError(#[from] CompilationError),
 
+    #[error("document '{0}' can not be compiled: {1}")]
+    DocumentCompilationError(String, CompilationError),
+
     #[error(transparent)]
     AssemblerError(#[from] AssemblerError),
 
@@ -30,4 +39,42 @@ pub enum BuilderError {
 
     #[error(transparent)]
     JoinError(#[from] JoinError),
-}
\ No newline at end of file
+
+    #[error(transparent)]
+    BuildCacheError(#[from] BuildCacheError),
+
+    #[error(transparent)]
+    BuildReportError(#[from] BuildReportError),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("split output needs a directory as output location, but {0:?} is not a directory")]
+    InvalidSplitOutputLocation(PathBuf),
+
+    #[error("input location {0:?} can not be resolved: {1}")]
+    InvalidInputLocation(PathBuf, io::Error),
+
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error("build cancelled")]
+    Cancelled,
+
+    /// Errors of all documents which can not be compiled
+    #[error("{} documents can not be compiled:\n{}", .0.len(), .0.iter().map(|err| format!("- {}", err)).collect::<Vec<String>>().join("\n"))]
+    BucketOfErrors(Vec<BuilderError>),
+}
+
+impl BuilderError {
+
+    /// Name of document which has caused the error, if known
+    pub fn failing_document(&self) -> Option<&String> {
+        match self {
+            Self::DocumentLoadError(document, _) => Some(document),
+            Self::DocumentCompilationError(document_name, _) => Some(document_name),
+            Self::BucketOfErrors(errors) => errors.iter().find_map(|err| err.failing_document()),
+            _ => None,
+        }
+    }
+}
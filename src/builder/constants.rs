@@ -0,0 +1,2 @@
+/// Build cache directory, relative to dossier directory
+pub const BUILD_CACHE_DIR: &str = ".nmd-cache";
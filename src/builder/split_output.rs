@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+use nmd_core::utility::file_utility;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+
+/// File name of split output index page
+pub const SPLIT_INDEX_FILE_NAME: &str = "index.html";
+
+const SPLIT_PAGE_EXTENSION: &str = "html";
+
+/// Style of navigation bar added to each page of split output
+pub const SPLIT_NAVIGATION_STYLE: &str = r#"
+.split-navigation { display: flex; justify-content: space-between; margin: 1em 0; padding: 0.5em 0; }
+.split-navigation a { text-decoration: none; }
+.split-navigation .split-navigation-placeholder { visibility: hidden; }
+"#;
+
+static ID_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sid="([^"]+)""#).unwrap());
+
+static LOCAL_HREF_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r##"href="#([^"]+)""##).unwrap());
+
+
+/// A page of split output, i.e. a compiled document dumped in its own file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPage {
+    pub document_name: String,
+    pub title: String,
+    pub file_name: String,
+}
+
+/// Build pages of documents preserving their order. File names are unique and they never collide with index page
+pub fn split_pages(documents_names: &[String]) -> Vec<SplitPage> {
+
+    let mut used_file_names: HashSet<String> = HashSet::from([SPLIT_INDEX_FILE_NAME.to_string()]);
+
+    documents_names.iter().map(|document_name| {
+
+        let title = match document_name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+            _ => document_name.clone(),
+        };
+
+        let base = file_utility::build_output_file_name(&title, None);
+
+        let mut file_name = format!("{}.{}", base, SPLIT_PAGE_EXTENSION);
+        let mut counter = 1;
+
+        while used_file_names.contains(&file_name) {
+
+            file_name = format!("{}-{}.{}", base, counter, SPLIT_PAGE_EXTENSION);
+
+            counter += 1;
+        }
+
+        used_file_names.insert(file_name.clone());
+
+        SplitPage {
+            document_name: document_name.clone(),
+            title,
+            file_name,
+        }
+
+    }).collect()
+}
+
+/// Navigation bar with links to previous page, index and next page
+pub fn navigation(previous: Option<&SplitPage>, next: Option<&SplitPage>) -> String {
+
+    let link = |page: Option<&SplitPage>, label: &str, class: &str| -> String {
+        match page {
+            Some(page) => format!(r#"<a href="{}" class="{}">{}</a>"#, page.file_name, class, label.replace("{}", &escape_html(&page.title))),
+            None => format!(r#"<span class="{} split-navigation-placeholder"></span>"#, class),
+        }
+    };
+
+    format!(
+        r#"<nav class="split-navigation">{}<a href="{}" class="split-navigation-index">Index</a>{}</nav>"#,
+        link(previous, "&larr; {}", "split-navigation-previous"),
+        SPLIT_INDEX_FILE_NAME,
+        link(next, "{} &rarr;", "split-navigation-next"),
+    )
+}
+
+/// List of pages, used as index if table of contents is not included in output
+pub fn pages_list(pages: &[SplitPage]) -> String {
+
+    let items: String = pages.iter()
+                            .map(|page| format!(r#"<li class="split-index-item"><a href="{}" class="link">{}</a></li>"#, page.file_name, escape_html(&page.title)))
+                            .collect();
+
+    format!(r#"<ul class="split-index">{}</ul>"#, items)
+}
+
+/// Escape `text` to be placed in HTML (e.g. a page title, which comes from a document file name)
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Collect all ids defined in `html`
+pub fn collect_ids(html: &str) -> HashSet<String> {
+    ID_REGEX.captures_iter(html)
+            .map(|captures| captures[1].to_string())
+            .collect()
+}
+
+/// Rewrite local links (e.g. `href="#id"`) whose target is in another page, using `ids_pages` (id -> page file name)
+pub fn rewrite_local_links(html: &str, page_file_name: &str, ids_pages: &HashMap<String, String>) -> String {
+
+    LOCAL_HREF_REGEX.replace_all(html, |captures: &Captures| {
+
+        let id = &captures[1];
+
+        match ids_pages.get(id) {
+            Some(target_page) if target_page != page_file_name => format!(r##"href="{}#{}""##, target_page, id),
+            _ => captures[0].to_string(),
+        }
+
+    }).to_string()
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{navigation, pages_list, rewrite_local_links, split_pages};
+
+
+    #[test]
+    fn pages_file_names_are_unique() {
+
+        let pages = split_pages(&[
+            String::from("Chapter 1.nmd"),
+            String::from("index.nmd"),
+            String::from("chapter 1.nmd"),
+        ]);
+
+        let file_names: Vec<&str> = pages.iter().map(|page| page.file_name.as_str()).collect();
+
+        assert_eq!(file_names, vec!["chapter-1.html", "index-1.html", "chapter-1-1.html"]);
+    }
+
+    #[test]
+    fn links_to_other_pages_are_rewritten() {
+
+        let ids_pages = HashMap::from([
+            (String::from("intro"), String::from("a.html")),
+            (String::from("results"), String::from("b.html")),
+        ]);
+
+        let html = r##"<a href="#intro">x</a><a href="#results">y</a><a href="#unknown">z</a>"##;
+
+        assert_eq!(
+            rewrite_local_links(html, "a.html", &ids_pages),
+            r##"<a href="#intro">x</a><a href="b.html#results">y</a><a href="#unknown">z</a>"##
+        );
+    }
+
+    #[test]
+    fn pages_titles_are_escaped() {
+
+        let pages = split_pages(&[
+            String::from("Q&A <draft>.nmd"),
+            String::from("\"Results\".nmd"),
+        ]);
+
+        assert_eq!(
+            navigation(Some(&pages[0]), Some(&pages[1])),
+            concat!(
+                r#"<nav class="split-navigation"><a href="qa-draft.html" class="split-navigation-previous">&larr; Q&amp;A &lt;draft&gt;</a>"#,
+                r#"<a href="index.html" class="split-navigation-index">Index</a>"#,
+                r#"<a href="results.html" class="split-navigation-next">&quot;Results&quot; &rarr;</a></nav>"#,
+            )
+        );
+
+        assert!(pages_list(&pages).contains(r#"<a href="qa-draft.html" class="link">Q&amp;A &lt;draft&gt;</a>"#));
+    }
+}
@@ -0,0 +1,384 @@
+use std::{collections::{HashMap, HashSet}, fs, mem, path::{Path, PathBuf}};
+use nmd_core::{constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME}, dossier::{self, document::Document, dossier_configuration::DossierConfiguration, Dossier}, load::{LoadConfiguration, LoadConfigurationOverLay, LoadError}, utility::nmd_unique_identifier::assign_nuid_to_document_paragraphs};
+use once_cell::sync::Lazy;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::Regex;
+
+use crate::utility::normalize_path;
+
+use super::{builder_configuration::BuilderConfiguration, builder_error::BuilderError};
+
+
+/// Image sources of image (`![caption](src)`, also in multi-image) and abridged image (`![(src)]`) paragraphs
+static IMAGE_SRC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[(?:\(([^)\s]+)\)\]|[^\]]*\](?:#[\w-]+)?\(\s*([^)\s]+)[^)]*\))").unwrap());
+
+const REMOTE_SRC_PREFIXES: [&str; 3] = ["http://", "https://", "data:"];
+
+
+/// Changes applied to a `WatchedDossier` by a sync
+#[derive(Debug, Default)]
+pub struct DossierSync {
+
+    /// Dossier configuration file is changed, so whole dossier must be compiled
+    pub configuration_changed: bool,
+
+    /// Normalized paths of (re)loaded documents
+    pub loaded_documents: HashSet<PathBuf>,
+
+    /// Names of documents dropped from dossier (removed from dossier configuration or from filesystem)
+    pub removed_documents: Vec<String>,
+
+    /// Normalized paths of documents which reference a changed asset (e.g. an image), so they must be compiled again
+    pub affected_documents: HashSet<PathBuf>,
+
+    /// A style file is changed, so output must be assembled again
+    pub styles_changed: bool,
+}
+
+
+/// Dossier loaded in watch mode. Path of each document is tracked, so dossier can be kept in sync with filesystem
+/// (modified, created, removed and renamed documents) without reloading it from scratch
+#[derive(Debug)]
+pub struct WatchedDossier {
+
+    dossier: Dossier,
+
+    /// Normalized path of each document of dossier (same order of documents)
+    documents_paths: Vec<PathBuf>,
+
+    /// Normalized paths of local assets (i.e. images) referenced by each document (by document path)
+    documents_assets: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl WatchedDossier {
+
+    pub fn new(dossier: Dossier, builder_configuration: &BuilderConfiguration) -> Self {
+
+        let listed_paths = listed_documents_paths(dossier.configuration(), builder_configuration);
+
+        // documents which are not found are not loaded, so paths are realigned with loaded documents
+        let documents_paths: Vec<PathBuf> = align_documents_paths(&listed_paths, dossier.documents()).into_iter()
+                                                .zip(dossier.documents())
+                                                .map(|(path, document)| path.unwrap_or_else(|| {
+                                                    log::warn!("path of document '{}' not found, changes to it could not be detected", document.name());
+
+                                                    PathBuf::from(document.name())
+                                                }))
+                                                .collect();
+
+        let documents_assets = documents_paths.iter()
+                                    .map(|path| (path.clone(), referenced_local_assets(path, builder_configuration.input_location())))
+                                    .collect();
+
+        Self {
+            dossier,
+            documents_paths,
+            documents_assets,
+        }
+    }
+
+    pub fn dossier(&self) -> &Dossier {
+        &self.dossier
+    }
+
+    pub fn dossier_mut(&mut self) -> &mut Dossier {
+        &mut self.dossier
+    }
+
+    /// Normalized paths of style files used by dossier, both passed by user and listed in dossier configuration
+    pub fn styles_paths(&self, builder_configuration: &BuilderConfiguration) -> HashSet<PathBuf> {
+        builder_configuration.styles_raw_path().iter()
+            .map(PathBuf::from)
+            .chain(self.dossier.configuration().style().styles_references().into_iter().map(PathBuf::from))
+            .map(|path| normalize_path(&path))
+            .collect()
+    }
+
+    /// Normalized paths of local assets referenced by at least one document
+    pub fn assets_paths(&self) -> HashSet<PathBuf> {
+        self.documents_assets.values().flatten().cloned().collect()
+    }
+
+    /// Paths whose changes are relevant for dossier besides its documents (i.e. styles and referenced assets),
+    /// they can be outside of dossier directory
+    pub fn dependencies_paths(&self, builder_configuration: &BuilderConfiguration) -> HashSet<PathBuf> {
+
+        let mut paths = self.styles_paths(builder_configuration);
+
+        paths.extend(self.assets_paths());
+
+        paths
+    }
+
+    /// Normalized paths of documents which reference at least one of `assets_paths` (normalized)
+    pub fn documents_using(&self, assets_paths: &HashSet<PathBuf>) -> HashSet<PathBuf> {
+        self.documents_paths.iter()
+            .filter(|path| {
+                self.documents_assets.get(*path).is_some_and(|assets| !assets.is_disjoint(assets_paths))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Sync dossier with `changed_paths` (normalized): if dossier configuration is changed, documents list is reloaded;
+    /// changed and new documents are (re)loaded, while removed documents are dropped. Unchanged documents are kept as they are.
+    ///
+    /// In case of error dossier is not modified
+    pub fn sync(&mut self, changed_paths: &HashSet<PathBuf>, builder_configuration: &BuilderConfiguration) -> Result<DossierSync, BuilderError> {
+
+        let configuration_root = normalize_path(builder_configuration.input_location());
+
+        let configuration_changed = configuration_paths(&configuration_root).iter().any(|path| changed_paths.contains(path));
+
+        let configuration = if configuration_changed {
+
+            log::info!("dossier configuration is changed, documents list is reloaded");
+
+            Some(DossierConfiguration::try_from(builder_configuration.input_location()).map_err(LoadError::from)?)
+
+        } else {
+
+            None
+        };
+
+        let listed_paths = listed_documents_paths(configuration.as_ref().unwrap_or(self.dossier.configuration()), builder_configuration);
+
+        let loaded_paths: HashSet<&PathBuf> = self.documents_paths.iter().collect();
+
+        let paths_to_load: HashSet<PathBuf> = listed_paths.iter()
+                                                .filter(|path| changed_paths.contains(*path) || !loaded_paths.contains(path))
+                                                .filter(|path| {
+
+                                                    if path.is_file() {
+                                                        return true
+                                                    }
+
+                                                    log::warn!("document {:?} not found, it is skipped until it is created", path);
+
+                                                    false
+                                                })
+                                                .cloned()
+                                                .collect();
+
+        let mut loaded_documents = load_documents(paths_to_load, builder_configuration)?;
+
+        // from here dossier is modified, so nothing can fail
+
+        let mut sync = DossierSync {
+            configuration_changed,
+            ..Default::default()
+        };
+
+        let mut current_documents: HashMap<PathBuf, Document> = mem::take(&mut self.documents_paths).into_iter()
+                                                                    .zip(mem::take(self.dossier.documents_mut()))
+                                                                    .collect();
+
+        let mut documents: Vec<Document> = Vec::new();
+
+        for path in listed_paths {
+
+            let document = match loaded_documents.remove(&path) {
+                Some(document) => {
+
+                    // previous version of reloaded document is replaced, not dropped
+                    current_documents.remove(&path);
+
+                    sync.loaded_documents.insert(path.clone());
+
+                    Some(document)
+                },
+                None if path.is_file() => current_documents.remove(&path),
+                None => None,
+            };
+
+            if let Some(document) = document {
+                documents.push(document);
+                self.documents_paths.push(path);
+            }
+        }
+
+        for (path, document) in current_documents {
+
+            log::info!("document {:?} is dropped from dossier", path);
+
+            self.documents_assets.remove(&path);
+
+            sync.removed_documents.push(document.name().clone());
+        }
+
+        if let Some(configuration) = configuration {
+            self.dossier.set_configuration(configuration);
+        }
+
+        self.dossier.set_documents(documents);
+
+        for path in &self.documents_paths {
+            if changed_paths.contains(path) || !self.documents_assets.contains_key(path) {
+                self.documents_assets.insert(path.clone(), referenced_local_assets(path, builder_configuration.input_location()));
+            }
+        }
+
+        sync.styles_changed = !self.styles_paths(builder_configuration).is_disjoint(changed_paths);
+
+        sync.affected_documents = self.documents_using(changed_paths).difference(&sync.loaded_documents).cloned().collect();
+
+        log::info!("dossier synced: {} document(s) loaded, {} document(s) dropped, {} document(s) affected by changed assets", sync.loaded_documents.len(), sync.removed_documents.len(), sync.affected_documents.len());
+
+        Ok(sync)
+    }
+}
+
+
+/// Normalized paths of dossier configuration files (i.e. `nmd.yml` and `nmd.json`) in dossier directory `root`
+pub fn configuration_paths(root: &Path) -> [PathBuf; 2] {
+    [
+        root.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME),
+        root.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME),
+    ]
+}
+
+/// Normalized paths of documents listed in dossier configuration, considering documents subset of builder configuration (if any)
+pub fn listed_documents_paths(dossier_configuration: &DossierConfiguration, builder_configuration: &BuilderConfiguration) -> Vec<PathBuf> {
+
+    let documents_subset = builder_configuration.documents_subset_to_compile();
+
+    dossier_configuration.documents_paths().into_iter()
+        .map(|path| normalize_path(Path::new(&path)))
+        .filter(|path| {
+            match (documents_subset.as_ref(), path.file_name()) {
+                (Some(subset), Some(file_name)) => subset.contains(file_name.to_string_lossy().as_ref()),
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+/// Path of each document (`None` if it is not found), given `paths` from which documents have been loaded in the same order.
+/// Some paths can have no document (e.g. they are not found), so each document is matched with the next path with its file name:
+/// documents with the same name in different directories are not mixed up
+pub fn align_documents_paths(paths: &[PathBuf], documents: &[Document]) -> Vec<Option<PathBuf>> {
+
+    let mut paths = paths.iter();
+
+    documents.iter()
+        .map(|document| {
+            paths.find(|path| path.file_name().is_some_and(|file_name| file_name.to_string_lossy().eq(document.name()))).cloned()
+        })
+        .collect()
+}
+
+/// Normalized paths of local images referenced by document in `document_path`. Like compilation, relative sources are resolved
+/// against `input_location` (i.e. dossier directory or document parent directory) and, as fallback, against dossier images assets directory: both are returned,
+/// so creation of a missing image is captured too
+pub fn referenced_local_assets(document_path: &Path, input_location: &Path) -> HashSet<PathBuf> {
+
+    let content = match fs::read_to_string(document_path) {
+        Ok(content) => content,
+        Err(err) => {
+            log::warn!("assets referenced by document {:?} can not be tracked: {}", document_path, err);
+
+            return HashSet::new()
+        }
+    };
+
+    let base_location = if input_location.is_dir() {
+        input_location
+    } else {
+        input_location.parent().unwrap_or(input_location)
+    };
+
+    let mut assets: HashSet<PathBuf> = HashSet::new();
+
+    for src in image_sources(&content) {
+
+        if REMOTE_SRC_PREFIXES.iter().any(|prefix| src.starts_with(prefix)) {
+            continue
+        }
+
+        let src = Path::new(src);
+
+        if src.is_absolute() {
+            assets.insert(normalize_path(src));
+
+            continue
+        }
+
+        assets.insert(normalize_path(&base_location.join(src)));
+
+        if let Some(file_name) = src.file_name() {
+            assets.insert(normalize_path(&base_location.join(dossier::ASSETS_DIR).join(dossier::IMAGES_DIR).join(file_name)));
+        }
+    }
+
+    assets
+}
+
+fn image_sources(content: &str) -> Vec<&str> {
+    IMAGE_SRC_REGEX.captures_iter(content)
+        .filter_map(|captures| captures.get(1).or(captures.get(2)))
+        .map(|src| src.as_str())
+        .collect()
+}
+
+fn load_documents(paths: HashSet<PathBuf>, builder_configuration: &BuilderConfiguration) -> Result<HashMap<PathBuf, Document>, BuilderError> {
+
+    let mut load_configuration = LoadConfiguration::default();
+    load_configuration.set_input_location(builder_configuration.input_location().clone());
+
+    if let Some(p) = builder_configuration.parallelization() {
+        load_configuration.set_parallelization(*p);
+    }
+
+    let codex = builder_configuration.codex();
+
+    let with_nuid = builder_configuration.nuid().unwrap_or(false);
+
+    paths.into_par_iter().map(|path| {
+
+        log::info!("load document {:?}", path);
+
+        let mut document = Document::load_document_from_path(&path, &codex, &load_configuration, LoadConfigurationOverLay::default())
+                                    .map_err(|err| BuilderError::DocumentLoadError(path.to_string_lossy().to_string(), err))?;
+
+        if with_nuid {
+            assign_nuid_to_document_paragraphs(&mut document);
+        }
+
+        Ok((path, document))
+
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use nmd_core::{content_bundle::ContentBundle, dossier::document::Document};
+
+    use super::{align_documents_paths, image_sources};
+
+
+    #[test]
+    fn image_sources_are_found() {
+
+        let content = "# Title\n\n![caption](./assets/images/a.png)\n\n![(b.jpg)]\n\n!![[\n![x](c.png)\n![y]#id(https://example.com/d.png)\n]]\n\nnot an image [link](e.png)\n";
+
+        assert_eq!(image_sources(content), vec!["./assets/images/a.png", "b.jpg", "c.png", "https://example.com/d.png"]);
+    }
+
+    #[test]
+    fn documents_with_same_name_are_aligned_with_their_paths() {
+
+        let paths = vec![
+            PathBuf::from("/dossier/part-1/intro.nmd"),
+            PathBuf::from("/dossier/missing.nmd"),
+            PathBuf::from("/dossier/part-2/intro.nmd"),
+        ];
+
+        let documents: Vec<Document> = ["intro.nmd", "intro.nmd"].into_iter()
+                                            .map(|name| Document::new(name.to_string(), ContentBundle::new(Vec::new(), Vec::new())))
+                                            .collect();
+
+        assert_eq!(align_documents_paths(&paths, &documents), vec![Some(paths[0].clone()), Some(paths[2].clone())]);
+    }
+}
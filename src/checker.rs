@@ -0,0 +1,276 @@
+pub mod diagnostic;
+
+
+use std::{collections::{BTreeMap, HashSet}, path::{Path, PathBuf}};
+use diagnostic::{Diagnostic, DiagnosticSeverity};
+use nmd_core::{bibliography::bibliography_record::BibliographyRecord, codex::modifier::standard_text_modifier::StandardTextModifier, constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME, NMD_EXTENSION}, dossier::{self, dossier_configuration::DossierConfiguration}, resource::{text_reference::TextReferenceMap, ResourceError}, utility::file_utility};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use thiserror::Error;
+use url::Url;
+
+use crate::{builder::{builder_configuration::BuilderConfiguration, Builder}, dossier_manager::DossierManager};
+
+
+static IMAGE_SRC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[[^\]\(]*\](?:#[\w-]+)?\(([^)]+)\)").unwrap());
+static ABRIDGED_IMAGE_SRC_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[\(([^)]+)\)\]").unwrap());
+
+const CODE_BLOCK_DELIMITER: &str = "```";
+
+
+#[derive(Error, Debug)]
+pub enum CheckerError {
+
+    #[error(transparent)]
+    ResourceError(#[from] ResourceError),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+
+/// Checker, it can be used to find problems in a dossier or document without build it
+pub struct Checker {
+}
+
+impl Checker {
+
+    /// Check dossier based on `BuilderConfiguration`. Dossier is loaded, but nothing is dumped
+    pub async fn check_dossier(builder_configuration: &BuilderConfiguration) -> Result<Vec<Diagnostic>, CheckerError> {
+
+        let dossier_path = builder_configuration.input_location().clone();
+
+        let configuration_path = Self::dossier_configuration_path(&dossier_path);
+
+        let dossier_configuration = match DossierConfiguration::try_from(&dossier_path) {
+            Ok(dc) => dc,
+            Err(err) => return Ok(vec![Diagnostic::error(configuration_path, None, None, err.to_string())]),
+        };
+
+        let configuration_content = file_utility::read_file_content(&configuration_path).unwrap_or_default();
+
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        let mut listed_documents: HashSet<PathBuf> = HashSet::new();
+        let mut existing_documents: Vec<PathBuf> = Vec::new();
+
+        for raw_path in dossier_configuration.raw_documents_paths() {
+
+            let path = DossierManager::resolve_raw_document_path(&dossier_path, raw_path);
+
+            if path.is_file() {
+
+                listed_documents.insert(path.canonicalize()?);
+                existing_documents.push(path);
+
+            } else {
+
+                let (line, column) = Self::find_position(&configuration_content, raw_path).unzip();
+
+                diagnostics.push(Diagnostic::error(configuration_path.clone(), line, column, format!("document '{}' not found", raw_path)));
+            }
+        }
+
+        let mut files_in_dir = file_utility::all_files_in_dir(&dossier_path, &vec![NMD_EXTENSION.to_string()])?;
+        files_in_dir.sort();
+
+        for file in files_in_dir {
+            if !listed_documents.contains(&file.canonicalize()?) {
+                diagnostics.push(Diagnostic::warning(file, None, None, String::from("document is not listed in dossier configuration")));
+            }
+        }
+
+        for document_path in existing_documents {
+
+            let content = file_utility::read_file_content(&document_path)?;
+
+            diagnostics.append(&mut Self::check_document_content(
+                &document_path,
+                &content,
+                &dossier_path,
+                Some(dossier_configuration.references()),
+                Some(dossier_configuration.bibliography().records())
+            ));
+        }
+
+        if !diagnostics.iter().any(|d| d.severity() == DiagnosticSeverity::Error) {
+
+            if let Err(err) = Builder::load_dossier(builder_configuration).await {
+                diagnostics.push(Diagnostic::error(configuration_path, None, None, format!("dossier loading failed: {}", err)));
+            }
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Check document based on `BuilderConfiguration`. Document is loaded, but nothing is dumped
+    pub async fn check_document(builder_configuration: &BuilderConfiguration) -> Result<Vec<Diagnostic>, CheckerError> {
+
+        let document_path = builder_configuration.input_location().clone();
+
+        let content = file_utility::read_file_content(&document_path)?;
+
+        let base_location = document_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+
+        let mut diagnostics = Self::check_document_content(&document_path, &content, &base_location, None, None);
+
+        if !diagnostics.iter().any(|d| d.severity() == DiagnosticSeverity::Error) {
+
+            if let Err(err) = Builder::load_document(builder_configuration).await {
+                diagnostics.push(Diagnostic::error(document_path, None, None, format!("document loading failed: {}", err)));
+            }
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Check raw content of a document. References and cites are checked only if `references` and `bibliography` are provided
+    pub fn check_document_content(document_path: &Path, content: &str, base_location: &Path, references: Option<&TextReferenceMap>, bibliography: Option<&BTreeMap<String, BibliographyRecord>>) -> Vec<Diagnostic> {
+
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        let mut open_code_block_line: Option<usize> = None;
+
+        for (index, line) in content.lines().enumerate() {
+
+            let line_number = index + 1;
+
+            if line.trim_start().starts_with(CODE_BLOCK_DELIMITER) {
+
+                if open_code_block_line.is_some() {
+                    open_code_block_line = None;
+                } else {
+                    open_code_block_line = Some(line_number);
+                }
+
+                continue;
+            }
+
+            if open_code_block_line.is_some() {
+                continue;
+            }
+
+            if let Some(references) = references {
+                for captures in StandardTextModifier::Reference.modifier_pattern_regex().captures_iter(line) {
+
+                    let key = captures.get(1).unwrap();
+
+                    if !references.contains_key(key.as_str()) {
+                        diagnostics.push(Diagnostic::error(document_path.to_path_buf(), Some(line_number), Some(Self::column(line, captures.get(0).unwrap().start())), format!("unresolved reference '{}'", key.as_str())));
+                    }
+                }
+            }
+
+            if let Some(bibliography) = bibliography {
+                for captures in StandardTextModifier::Cite.modifier_pattern_regex().captures_iter(line) {
+
+                    let key = captures.get(1).unwrap();
+
+                    if !bibliography.contains_key(key.as_str()) {
+                        diagnostics.push(Diagnostic::error(document_path.to_path_buf(), Some(line_number), Some(Self::column(line, captures.get(0).unwrap().start())), format!("unknown bibliography key '{}'", key.as_str())));
+                    }
+                }
+            }
+
+            for regex in [&IMAGE_SRC_REGEX, &ABRIDGED_IMAGE_SRC_REGEX] {
+                for captures in regex.captures_iter(line) {
+
+                    let src = captures.get(1).unwrap();
+
+                    if !Self::image_exists(src.as_str(), base_location) {
+                        diagnostics.push(Diagnostic::error(document_path.to_path_buf(), Some(line_number), Some(Self::column(line, src.start())), format!("image '{}' not found", src.as_str())));
+                    }
+                }
+            }
+        }
+
+        if let Some(line_number) = open_code_block_line {
+            diagnostics.push(Diagnostic::error(document_path.to_path_buf(), Some(line_number), Some(1), String::from("code block is not closed")));
+        }
+
+        diagnostics
+    }
+
+    /// Image source is resolved as compiler does: relative to base location or, as fallback, in dossier images assets directory
+    fn image_exists(src: &str, base_location: &Path) -> bool {
+
+        if Url::parse(src).is_ok() {
+            return true     // remote images are not checked
+        }
+
+        let path = PathBuf::from(src);
+
+        if path.is_absolute() {
+            return path.exists()
+        }
+
+        if base_location.join(&path).exists() {
+            return true
+        }
+
+        if let Some(file_name) = path.file_name() {
+            return base_location.join(dossier::ASSETS_DIR).join(dossier::IMAGES_DIR).join(file_name).exists()
+        }
+
+        false
+    }
+
+    fn dossier_configuration_path(dossier_path: &Path) -> PathBuf {
+
+        let yaml_path = dossier_path.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME);
+
+        if !yaml_path.exists() && dossier_path.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME).exists() {
+            return dossier_path.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME)
+        }
+
+        yaml_path
+    }
+
+    /// Return (line, column) of first occurrence of `pattern` in `content`
+    fn find_position(content: &str, pattern: &str) -> Option<(usize, usize)> {
+
+        content.lines().enumerate().find_map(|(index, line)| {
+            line.find(pattern).map(|start| (index + 1, Self::column(line, start)))
+        })
+    }
+
+    /// Column (starting from 1, in characters) of a byte offset of line
+    fn column(line: &str, byte_offset: usize) -> usize {
+        line[..byte_offset].chars().count() + 1
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{collections::{BTreeMap, HashMap}, path::PathBuf};
+
+    use super::Checker;
+
+
+    #[test]
+    fn document_content_diagnostics() {
+
+        let content = concat!(
+            "# Title\n",
+            "\n",
+            "Hello &name& and &unknown&\n",
+            "\n",
+            "```\n",
+            "&not-a-reference&\n",
+        );
+
+        let references = HashMap::from([(String::from("name"), String::from("NMD"))]);
+
+        let diagnostics = Checker::check_document_content(&PathBuf::from("test.nmd"), content, &PathBuf::from("."), Some(&references), Some(&BTreeMap::new()));
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].line(), Some(3));
+        assert_eq!(diagnostics[0].column(), Some(18));
+        assert!(diagnostics[0].message().contains("unknown"));
+
+        assert_eq!(diagnostics[1].line(), Some(5));
+        assert!(diagnostics[1].message().contains("code block"));
+    }
+}
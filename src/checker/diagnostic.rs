@@ -0,0 +1,78 @@
+use std::{fmt::Display, path::PathBuf};
+use getset::{CopyGetters, Getters};
+use serde::Serialize;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+impl Display for DiagnosticSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticSeverity::Error => write!(f, "error"),
+            DiagnosticSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+
+/// Problem found checking a dossier or a document. `line` and `column` start from 1
+#[derive(Debug, Clone, Getters, CopyGetters, Serialize)]
+pub struct Diagnostic {
+
+    #[getset(get_copy = "pub")]
+    severity: DiagnosticSeverity,
+
+    #[getset(get = "pub")]
+    file: PathBuf,
+
+    #[getset(get_copy = "pub")]
+    line: Option<usize>,
+
+    #[getset(get_copy = "pub")]
+    column: Option<usize>,
+
+    #[getset(get = "pub")]
+    message: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: DiagnosticSeverity, file: PathBuf, line: Option<usize>, column: Option<usize>, message: String) -> Self {
+        Self {
+            severity,
+            file,
+            line,
+            column,
+            message,
+        }
+    }
+
+    pub fn error(file: PathBuf, line: Option<usize>, column: Option<usize>, message: String) -> Self {
+        Self::new(DiagnosticSeverity::Error, file, line, column, message)
+    }
+
+    pub fn warning(file: PathBuf, line: Option<usize>, column: Option<usize>, message: String) -> Self {
+        Self::new(DiagnosticSeverity::Warning, file, line, column, message)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        write!(f, "{}", self.file.to_string_lossy())?;
+
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
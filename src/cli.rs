@@ -1,27 +1,41 @@
+pub mod cli_configuration;
+pub mod dossier_wizard;
+
 use std::collections::HashSet;
-use std::io::{stdout, Write};
+use std::io::{self, stdout, IsTerminal, Read, Write};
+use std::net::{AddrParseError, IpAddr};
 use std::num::ParseIntError;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 use nmd_core::compilation::compilation_configuration::CompilableResourceType;
-use nmd_core::output_format::{OutputFormat, OutputFormatError};
+use nmd_core::output_format::OutputFormatError;
 use nmd_core::resource::ResourceError;
 use nmd_core::theme::{Theme, ThemeError};
 use nmd_core::utility::file_utility;
+use tokio::runtime::Handle;
 use tokio::sync::RwLock as TokioRwLock;
-use std::{path::PathBuf, str::FromStr};
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{env, fs, path::PathBuf, str::FromStr};
+use clap::{parser::ValueSource, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use cli_configuration::{BuildProfile, CliConfiguration, CliConfigurationError, ENVIRONMENT_VARIABLES_PREFIX};
+use dossier_wizard::{DossierWizard, DossierWizardError};
 use tokio::task::{JoinError, JoinHandle};
+use crate::builder::build_format::BuildFormat;
+use crate::builder::build_report::{self, BuildReport, DEFAULT_BUILD_REPORT_FILE_NAME};
 use crate::builder::builder_configuration::BuilderConfiguration;
 use crate::builder::builder_error::BuilderError;
 use crate::builder::Builder;
-use crate::constants::{MINIMUM_WATCHER_TIME, VERSION};
-use crate::dossier_manager::{dossier_manager_configuration::DossierManagerConfiguration, DossierManager, DossierManagerError};
-use crate::generator::{generator_configuration::GeneratorConfiguration, Generator};
+use crate::checker::{Checker, CheckerError};
+use crate::checker::diagnostic::DiagnosticSeverity;
+use crate::constants::{VERSION, WATCHER_DEBOUNCE_TIME};
+use crate::dossier_manager::{document_template::{DocumentTemplate, TemplateContext}, dossier_manager_configuration::DossierManagerConfiguration, DocumentPosition, DossierManager, DossierManagerError};
+use crate::generator::{document_slug::SlugStrategy, generator_configuration::GeneratorConfiguration, Generator};
 use crate::preview::html_preview::HtmlPreview;
 use crate::preview::PreviewError;
 use crate::preview::Preview;
-use log::{LevelFilter, ParseLevelError};
+use crate::server::{NmdServer, ServerError};
+use crate::shutdown;
+use log::{Level, LevelFilter, Log, Metadata, ParseLevelError, Record};
 use thiserror::Error;
 use simple_logger::SimpleLogger;
 
@@ -68,11 +82,35 @@ pub enum NmdCliError {
     #[error(transparent)]
     ParseIntError(#[from] ParseIntError),
 
+    #[error(transparent)]
+    AddrParseError(#[from] AddrParseError),
+
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
 
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    CheckerError(#[from] CheckerError),
+
+    #[error("check failed: {0} error(s) found")]
+    CheckFailed(usize),
+
+    #[error(transparent)]
+    ServerError(#[from] ServerError),
+
+    #[error(transparent)]
+    CliConfigurationError(#[from] CliConfigurationError),
+
+    #[error("invalid watcher time: '{0}' (use e.g. 500ms or 2s)")]
+    InvalidWatcherTime(String),
+
+    #[error(transparent)]
+    DossierWizardError(#[from] DossierWizardError),
+
+    #[error("preview needs HTML among build formats (e.g. '-f html')")]
+    PreviewWithoutHtml,
 }
 
 
@@ -107,9 +145,8 @@ impl NmdCli {
                                     Arg::new("format")
                                     .short('f')
                                     .long("format")
-                                    .help("output format")
-                                    .action(ArgAction::Set)
-                                    .num_args(1)
+                                    .help("output format, it can be repeated to build more formats from one load (e.g. -f html -f json)")
+                                    .action(ArgAction::Append)
                                     .default_value("html")
                                 )
                                 .arg(
@@ -136,7 +173,7 @@ impl NmdCli {
                                 .arg(
                                     Arg::new("watcher-time")
                                         .long("watcher-time")
-                                        .help("set minimum watcher time interval")
+                                        .help("set watcher debounce time, i.e. quiet time after last change before compilation (e.g. 500ms, 2s; default unit is seconds)")
                                         .action(ArgAction::Set)
                                 )
                                 .arg(
@@ -146,11 +183,26 @@ impl NmdCli {
                                         .help("show preview")
                                         .action(ArgAction::SetTrue)
                                 )
+                                .arg(
+                                    Arg::new("preview-host")
+                                        .long("preview-host")
+                                        .help("set preview server host (e.g. 0.0.0.0)")
+                                        .required(false)
+                                        .action(ArgAction::Set)
+                                )
+                                .arg(
+                                    Arg::new("preview-port")
+                                        .long("preview-port")
+                                        .help("set preview server port, next free port is used if it is already taken")
+                                        .required(false)
+                                        .action(ArgAction::Set)
+                                )
                                 .arg(
                                     Arg::new("preview-scraping-interval")
                                         .long("preview-scraping-interval")
-                                        .help("set preview scraping interval")
+                                        .help("deprecated, preview is updated by server as soon as output changes")
                                         .required(false)
+                                        .hide(true)
                                         .action(ArgAction::Set)
                                 )
                                 .arg(
@@ -226,6 +278,40 @@ impl NmdCli {
                                     .help("set nuid")
                                     .action(ArgAction::SetTrue)
                                 )
+                                .arg(
+                                    Arg::new("build-cache")
+                                    .long("cache")
+                                    .help("reuse compiled documents which are not changed from last build (stored in dossier .nmd-cache directory)")
+                                    .action(ArgAction::SetTrue)
+                                )
+                                .arg(
+                                    Arg::new("profile")
+                                    .long("profile")
+                                    .help("build profile of CLI configuration file (nmd.toml or .nmdrc) to use")
+                                    .action(ArgAction::Set)
+                                    .num_args(1)
+                                )
+                                .arg(
+                                    Arg::new("split")
+                                    .long("split")
+                                    .help("dump each document of dossier in its own HTML page, plus an index.html with table of contents (output path must be a directory)")
+                                    .action(ArgAction::SetTrue)
+                                    .conflicts_with("preview")
+                                )
+                                .arg(
+                                    Arg::new("report")
+                                    .long("report")
+                                    .help("write a build report in passed format")
+                                    .action(ArgAction::Set)
+                                    .value_parser(["json"])
+                                )
+                                .arg(
+                                    Arg::new("report-output")
+                                    .long("report-output")
+                                    .help("set build report path (default: nmd-build-report.json in output directory)")
+                                    .action(ArgAction::Set)
+                                    .requires("report")
+                                )
                 )
                 .subcommand(
                     Command::new("generate")
@@ -241,10 +327,18 @@ impl NmdCli {
                                             Arg::new("path")
                                             .short('p')
                                             .long("path")
-                                            .help("destination path")
+                                            .help("destination path (if it is not set, a wizard asks dossier settings)")
                                             .action(ArgAction::Set)
                                             .num_args(1)
-                                            .required(true)
+
+                                        )
+                                        .arg(
+                                            Arg::new("yes")
+                                            .short('y')
+                                            .long("yes")
+                                            .help("accept default answers of wizard, without asking them")
+                                            .action(ArgAction::SetTrue)
+                                            .conflicts_with("path")
 
                                         )
                                         .arg(
@@ -277,6 +371,54 @@ impl NmdCli {
                                                 .help("generate NMD dossier from Markdown file")
                                                 .action(ArgAction::Set)
                                         )
+                                        .arg(
+                                            Arg::new("from-md-dir")
+                                                .long("from-md-dir")
+                                                .help("generate NMD dossier from a directory of Markdown pages (e.g. mdBook or MkDocs documentation)")
+                                                .action(ArgAction::Set)
+                                                .conflicts_with("from-md")
+                                        )
+                                        .arg(
+                                            Arg::new("split-level")
+                                                .long("split-level")
+                                                .help("split imported Markdown file in a document for each heading up to this level (default: 1)")
+                                                .action(ArgAction::Set)
+                                                .value_parser(clap::value_parser!(u8).range(1..=6))
+                                                .requires("from-md")
+                                        )
+                                        .arg(
+                                            Arg::new("no-split")
+                                                .long("no-split")
+                                                .help("import Markdown file as a single document")
+                                                .action(ArgAction::SetTrue)
+                                                .requires("from-md")
+                                                .conflicts_with("split-level")
+                                        )
+                                        .arg(
+                                            Arg::new("slug")
+                                                .long("slug")
+                                                .help("how file names of imported documents are built (default: kebab)")
+                                                .action(ArgAction::Set)
+                                                .value_parser(["kebab", "numbered", "title"])
+                                        )
+                                        .group(
+                                            ArgGroup::new("markdown-source")
+                                                .args(["from-md", "from-md-dir"])
+                                        )
+                                        .arg(
+                                            Arg::new("template")
+                                                .long("template")
+                                                .short('t')
+                                                .help("generate dossier from a template: built-in template (report, thesis, meeting-notes or book), directory in ~/.config/nmd/templates/ or path of a local directory")
+                                                .action(ArgAction::Set)
+                                        )
+                                        .arg(
+                                            Arg::new("author")
+                                                .long("author")
+                                                .help("insert author used in template (default: NMD_AUTHOR environment variable)")
+                                                .action(ArgAction::Set)
+                                                .requires("template")
+                                        )
                                         .arg(
                                             Arg::new("name")
                                                 .long("name")
@@ -312,6 +454,127 @@ impl NmdCli {
                             .required(true)
                             .action(ArgAction::Append)
                         )
+                        .arg(
+                            Arg::new("template")
+                            .short('t')
+                            .long("template")
+                            .help("use template (built-in or in assets/templates/ or ~/.config/nmd/templates/) as initial content")
+                        )
+                        .arg(
+                            Arg::new("from-file")
+                            .long("from-file")
+                            .help("use content of file as initial content")
+                        )
+                        .arg(
+                            Arg::new("stdin")
+                            .long("stdin")
+                            .help("use standard input as initial content")
+                            .action(ArgAction::SetTrue)
+                        )
+                        .group(
+                            ArgGroup::new("initial-content")
+                            .args(["template", "from-file", "stdin"])
+                        )
+                        .arg(
+                            Arg::new("force")
+                            .long("force")
+                            .help("overwrite documents which already exist")
+                            .action(ArgAction::SetTrue)
+                        )
+                        .arg(
+                            Arg::new("author")
+                            .long("author")
+                            .help("insert author used in templates (default: NMD_AUTHOR environment variable)")
+                            .requires("template")
+                        )
+                        .arg(
+                            Arg::new("position")
+                            .long("position")
+                            .help("insert document in position (starting from 1)")
+                            .value_parser(clap::value_parser!(usize))
+                        )
+                        .arg(
+                            Arg::new("before")
+                            .long("before")
+                            .help("insert document before another document")
+                        )
+                        .arg(
+                            Arg::new("after")
+                            .long("after")
+                            .help("insert document after another document")
+                        )
+                        .group(
+                            ArgGroup::new("insert-position")
+                            .args(["position", "before", "after"])
+                        )
+                    )
+                    .subcommand(
+                        Command::new("remove")
+                        .about("Remove document from a dossier")
+                        .arg(
+                            Arg::new("document-name")
+                            .short('d')
+                            .long("document-name")
+                            .help("insert document (path in dossier configuration or file name)")
+                            .required(true)
+                            .action(ArgAction::Append)
+                        )
+                        .arg(
+                            Arg::new("delete-file")
+                            .long("delete-file")
+                            .help("delete document file too")
+                            .action(ArgAction::SetTrue)
+                        )
+                    )
+                    .subcommand(
+                        Command::new("rename")
+                        .about("Rename document of a dossier")
+                        .arg(
+                            Arg::new("document-name")
+                            .short('d')
+                            .long("document-name")
+                            .help("insert document (path in dossier configuration or file name)")
+                            .required(true)
+                        )
+                        .arg(
+                            Arg::new("new-name")
+                            .short('n')
+                            .long("new-name")
+                            .help("insert new file name of the document")
+                            .required(true)
+                        )
+                    )
+                    .subcommand(
+                        Command::new("move")
+                        .about("Move document of a dossier in documents list")
+                        .arg(
+                            Arg::new("document-name")
+                            .short('d')
+                            .long("document-name")
+                            .help("insert document (path in dossier configuration or file name)")
+                            .required(true)
+                        )
+                        .arg(
+                            Arg::new("position")
+                            .long("position")
+                            .help("move document to position (starting from 1)")
+                            .value_parser(clap::value_parser!(usize))
+                        )
+                        .arg(
+                            Arg::new("before")
+                            .long("before")
+                            .help("move document before another document")
+                        )
+                        .arg(
+                            Arg::new("after")
+                            .long("after")
+                            .help("move document after another document")
+                        )
+                        .group(
+                            ArgGroup::new("target-position")
+                            .args(["position", "before", "after"])
+                            .required(true)
+                        )
                     )
                     .subcommand(
                         Command::new("reset")
@@ -351,6 +614,51 @@ impl NmdCli {
                         .help("pretty json")
                         .action(ArgAction::SetTrue)
                     )
+                )
+                .subcommand(
+                    Command::new("check")
+                    .about("Check NMD dossier or document without build it")
+                    .arg(
+                        Arg::new("input-path")
+                            .short('i')
+                            .long("input")
+                            .help("insert input path")
+                            .action(ArgAction::Set)
+                            .default_value(".")
+                    )
+                    .arg(
+                        Arg::new("format")
+                        .long("format")
+                        .help("set diagnostics output format")
+                        .action(ArgAction::Set)
+                        .value_parser(["text", "json"])
+                        .default_value("text")
+                    )
+                )
+                .subcommand(
+                    Command::new("serve")
+                    .about("Start a long-running HTTP build server")
+                    .arg(
+                        Arg::new("root")
+                            .short('r')
+                            .long("root")
+                            .help("set root directory, only dossiers and documents inside it can be built")
+                            .action(ArgAction::Set)
+                            .default_value(".")
+                    )
+                    .arg(
+                        Arg::new("host")
+                            .long("host")
+                            .help("set server host (default: 127.0.0.1)")
+                            .action(ArgAction::Set)
+                    )
+                    .arg(
+                        Arg::new("port")
+                            .short('p')
+                            .long("port")
+                            .help("set server port (default: 8080)")
+                            .action(ArgAction::Set)
+                    )
                 );
         Self {
             cli
@@ -378,6 +686,10 @@ impl NmdCli {
 
             Some(("analyze", analyze_matches)) => Self::handle_analyze_command(&analyze_matches).await,
 
+            Some(("check", check_matches)) => Self::handle_check_command(check_matches).await,
+
+            Some(("serve", serve_matches)) => Self::handle_serve_command(serve_matches).await,
+
             _ => {
                 log::error!("bad command");
 
@@ -395,32 +707,61 @@ impl NmdCli {
 
     fn set_logger(log_level: LevelFilter) {
 
-        SimpleLogger::new()
-            .without_timestamps()
-            .with_level(log_level)
-            .init()
-            .unwrap();
+        let logger = SimpleLogger::new()
+                        .without_timestamps()
+                        .with_level(log_level);
+
+        // warnings always reach logger, so they are collected for build report also if they are not printed
+        log::set_max_level(logger.max_level().max(LevelFilter::Warn));
+        log::set_boxed_logger(Box::new(NmdCliLogger::new(logger))).unwrap();
     }
 
     async fn handle_build_command(matches: &ArgMatches) -> Result<(), NmdCliError> {
 
         let mut builder_configuration = BuilderConfiguration::default();
 
+        // PROFILE & ENVIRONMENT VARIABLES (precedence: CLI > environment variables > profile > dossier configuration > defaults)
+        let cli_configuration = CliConfiguration::discover()?;
+
+        let profile = cli_configuration.profile(matches.get_one::<String>("profile").map(|name| name.as_str()))?
+                                        .cloned()
+                                        .unwrap_or_default();
+
+        let profile = BuildProfile::from_env()?.or(profile);
+
+        profile.apply(&mut builder_configuration)?;
+
+        let profile_has_output_path = profile.output_path.is_some();
+
         // FORMAT
-        if let Some(format) = matches.get_one::<String>("format") {
-                    
-            let format = OutputFormat::from_str(format)?;
+        if let (Some(formats), Some(ValueSource::CommandLine)) = (matches.get_many::<String>("format"), matches.value_source("format")) {
+
+            let mut build_formats: Vec<BuildFormat> = Vec::new();
+
+            for format in formats {
+
+                let format = BuildFormat::from_str(format)?;
+
+                if !build_formats.contains(&format) {
+                    build_formats.push(format);
+                }
+            }
+
+            if let Some(output_format) = build_formats.iter().find_map(|format| format.output_format()) {
+                builder_configuration.set_format(output_format);
+            }
 
-            builder_configuration.set_format(format);
+            builder_configuration.set_formats(build_formats);
         }
 
         let there_is_preview = matches.get_flag("preview");
 
         builder_configuration.set_preview(Some(there_is_preview));
 
-        if there_is_preview {
+        // there is only HtmlPreview
+        if there_is_preview && !builder_configuration.formats().contains(&BuildFormat::Html) {
 
-            assert!(builder_configuration.format().eq(&OutputFormat::Html));        // there is only HtmlPreview
+            return Err(NmdCliError::PreviewWithoutHtml)
         }
 
         // INPUT & OUTPUT PATHs
@@ -437,7 +778,7 @@ impl NmdCli {
 
             builder_configuration.set_output_location(output_path);
 
-        } else {
+        } else if !profile_has_output_path {
             
             match builder_configuration.resource_type() {
                 CompilableResourceType::Dossier => {
@@ -454,46 +795,56 @@ impl NmdCli {
                 },
                 CompilableResourceType::File => {
 
-                    let mut output_path = builder_configuration.input_location().clone();
-
-                    if output_path.is_dir() {
-                        
-                        output_path = output_path.join(file_utility::build_output_file_name(
-                            output_path.file_stem().unwrap().to_string_lossy().to_string().as_str(),
-                        Some(&builder_configuration.format().get_extension())
-                        ));
-
-                    } else {
-
-                        output_path = output_path.parent().unwrap().join(file_utility::build_output_file_name(
-                            output_path.file_stem().unwrap().to_string_lossy().to_string().as_str(),
-                        Some(&builder_configuration.format().get_extension())
-                        ));
-                    }
-
-                    builder_configuration.set_output_location(output_path);
+                    builder_configuration.set_output_location(builder_configuration.default_document_output_location());
                 },
                 CompilableResourceType::Unknown => (),
             }
         }
 
+        // SHUTDOWN (SIGINT or SIGTERM stop watcher and preview after in-flight build, and one-shot builds between their stages)
+        let shutdown_token = shutdown::shutdown_token();
+
+        if matches.contains_id("preview-scraping-interval") {
+            log::warn!("--preview-scraping-interval is deprecated and it has no effect, because preview is updated as soon as output changes");
+        }
+
         // PREVIEW
         let preview: Option<Arc<TokioRwLock<HtmlPreview>>>;
         let preview_start_handle: Option<JoinHandle<Result<(), PreviewError>>>;
 
         if there_is_preview {
 
-            let scraping_interval: Option<u32>; 
-            
-            if let Some(interval) = matches.get_one::<String>("preview-scraping-interval") {
+            let host: Option<IpAddr>;
 
-                scraping_interval = Some(interval.clone().parse::<u32>()?);
+            if let Some(h) = matches.get_one::<String>("preview-host") {
+
+                host = Some(h.parse::<IpAddr>()?);
 
             } else {
-                scraping_interval = None;
+                host = None;
             }
 
-            let p = HtmlPreview::new(builder_configuration.output_location().clone(), scraping_interval);
+            let port: Option<u16>;
+
+            if let Some(p) = matches.get_one::<String>("preview-port") {
+
+                port = Some(p.parse::<u16>()?);
+
+            } else {
+                port = None;
+            }
+
+            let root = match builder_configuration.resource_type() {
+                CompilableResourceType::File => builder_configuration.input_location().parent()
+                                                        .filter(|p| !p.as_os_str().is_empty())
+                                                        .map(|p| p.to_path_buf())
+                                                        .unwrap_or(PathBuf::from(".")),
+                _ => builder_configuration.input_location().clone(),
+            };
+
+            let mut p = HtmlPreview::new(builder_configuration.output_location().clone(), root, host, port);
+
+            p.set_shutdown_token(shutdown_token.child_token());
 
             let p = Arc::new(TokioRwLock::new(p));
 
@@ -523,27 +874,29 @@ impl NmdCli {
         }
 
         // WATCHER
-        let watcher_time: u64;
-
-        if let Some(wt) = matches.get_one::<String>("watcher-time") {
-                                
-            watcher_time = wt.parse::<u64>().unwrap();
-
-        } else {
-            watcher_time = MINIMUM_WATCHER_TIME;
-        }
+        let watcher_time = match matches.get_one::<String>("watcher-time") {
+            Some(wt) => Self::parse_watcher_time(wt)?,
+            None => WATCHER_DEBOUNCE_TIME,
+        };
 
         let watch: bool = matches.get_flag("watch");
 
         builder_configuration.set_watching(Some(watch));
 
+        // in watch mode in-flight build is completed, while a one-shot build is stopped as soon as possible
+        if !watch {
+            builder_configuration.set_cancellation_token(Some(shutdown_token.child_token()));
+        }
+
         
         // FAST DRAFT, FORCE, STYLEs
-        let fast_draft: bool = matches.get_flag("fast-draft");
-
-        builder_configuration.set_fast_draft(Some(fast_draft));
+        if matches.get_flag("fast-draft") {
+            builder_configuration.set_fast_draft(Some(true));
+        }
 
-        builder_configuration.set_force_output(Some(matches.get_flag("force-output")));
+        if matches.get_flag("force-output") {
+            builder_configuration.set_force_output(Some(true));
+        }
 
         if let Some(styles) = matches.get_many::<String>("style-file") {
             builder_configuration.set_styles_raw_path(styles.map(|s| s.clone()).collect());
@@ -559,6 +912,36 @@ impl NmdCli {
             builder_configuration.set_nuid(Some(true));
         }
 
+        // BUILD CACHE
+        if matches.get_flag("build-cache") {
+            builder_configuration.set_build_cache(Some(true));
+        }
+
+        // SPLIT OUTPUT
+        if matches.get_flag("split") {
+            builder_configuration.set_split(Some(true));
+        }
+
+        // BUILD REPORT
+        let report_output: Option<PathBuf> = matches.get_one::<String>("report").map(|_| {
+
+            if let Some(report_output) = matches.get_one::<String>("report-output") {
+                return PathBuf::from(report_output)
+            }
+
+            let output_location = builder_configuration.output_location();
+
+            if output_location.is_dir() {
+                return output_location.join(DEFAULT_BUILD_REPORT_FILE_NAME)
+            }
+
+            output_location.parent().map(|p| p.join(DEFAULT_BUILD_REPORT_FILE_NAME)).unwrap_or(PathBuf::from(DEFAULT_BUILD_REPORT_FILE_NAME))
+        });
+
+        if report_output.is_some() && watch {
+            log::warn!("build report is not supported in watch mode, it will not be written");
+        }
+
         // IMAGEs
         if matches.get_flag("embed-local-image") {
             builder_configuration.set_embed_local_image(Some(true));
@@ -596,6 +979,10 @@ impl NmdCli {
             handle.await??;
         }
 
+        if let Some(p) = preview.as_ref() {
+            builder_configuration.set_preview_url(Some(p.read().await.url()));
+        }
+
         let builder_configuration = Arc::new(TokioRwLock::new(builder_configuration));
 
         let build_handle: JoinHandle<Result<(), BuilderError>>;
@@ -608,26 +995,44 @@ impl NmdCli {
 
                         let preview = preview.clone();
                         let builder_configuration = builder_configuration.clone();
+                        let shutdown_token = shutdown_token.clone();
 
                         async move {
-                            Builder::watch_compile_dossier(builder_configuration.read().await.deref().clone(), watcher_time, preview).await
+                            Builder::watch_compile_dossier(builder_configuration.read().await.deref().clone(), watcher_time, preview, shutdown_token).await
                         }
                     });
 
                 } else {
                     
-                    build_handle = tokio::spawn({
+                    // one-shot build is CPU bound, so it runs in a blocking thread: runtime is kept free to handle shutdown signals
+                    build_handle = tokio::task::spawn_blocking({
 
                         let builder_configuration = builder_configuration.clone();
 
-                        async move {
+                        let runtime = Handle::current();
+
+                        move || runtime.block_on(async move {
+
+                            if let Some(report_output) = report_output {
+
+                                let mut builder_configuration = builder_configuration.write().await;
+
+                                let mut report = BuildReport::start(&builder_configuration);
+
+                                let build_result = Builder::load_and_build_dossier_with_report(&mut builder_configuration, &mut report).await;
+
+                                report.finish();
+                                report.dump(&report_output)?;
+
+                                return build_result
+                            }
 
                             let mut dossier = Builder::load_dossier(builder_configuration.read().await.deref()).await?;
 
                             builder_configuration.write().await.merge_dossier_configuration(dossier.configuration());
     
                             Builder::build_dossier(&mut dossier, builder_configuration.read().await.deref()).await
-                        }
+                        })
                     });
 
                     if let Some(p) = preview.clone() {
@@ -644,21 +1049,43 @@ impl NmdCli {
                 if watch {
 
                     build_handle = tokio::spawn({
+
+                        let preview = preview.clone();
+                        let builder_configuration = builder_configuration.clone();
+                        let shutdown_token = shutdown_token.clone();
+
                         async move {
-                            unimplemented!("watch compile file will be added in a next version")
+                            Builder::watch_compile_document(builder_configuration.read().await.deref().clone(), watcher_time, preview, shutdown_token).await
                         }
                     });
 
                 } else {
 
-                    build_handle = tokio::spawn({
+                    // one-shot build is CPU bound, so it runs in a blocking thread: runtime is kept free to handle shutdown signals
+                    build_handle = tokio::task::spawn_blocking({
 
                         let builder_configuration = builder_configuration.clone();
 
-                        async move {
+                        let runtime = Handle::current();
+
+                        move || runtime.block_on(async move {
+
+                            if let Some(report_output) = report_output {
+
+                                let builder_configuration = builder_configuration.read().await;
+
+                                let mut report = BuildReport::start(&builder_configuration);
+
+                                let build_result = Builder::build_document_with_report(&builder_configuration, &mut report).await;
+
+                                report.finish();
+                                report.dump(&report_output)?;
+
+                                return build_result
+                            }
 
                             Builder::build_document(builder_configuration.read().await.deref()).await
-                        }
+                        })
                     });
 
                     if let Some(p) = preview.clone() {
@@ -673,15 +1100,46 @@ impl NmdCli {
             CompilableResourceType::Unknown => return Err(NmdCliError::ResourceError(ResourceError::InvalidResourceVerbose("resource is a dossier nor file".to_string()))),
         }
 
-        build_handle.await??;
+        let build_result = build_handle.await?;
+
+        match build_result.as_ref() {
+            Ok(_) if watch => log::info!("watch mode stopped"),
+            Ok(_) => log::info!("build completed successfully"),
+            Err(err) => log::error!("build failed: {}", err),
+        }
 
         if let Some(preview) = preview {
-            preview.write().await.stop().await?;       // need Ctrl + C to terminate
+
+            if build_result.is_ok() && !shutdown_token.is_cancelled() {
+
+                log::info!("preview is running, press CTRL + C to terminate");
+
+                shutdown_token.cancelled().await;
+            }
+
+            preview.write().await.stop().await?;
         }
 
+        build_result?;
+
         Ok(())
     }
 
+    /// Parse watcher time, which is in milliseconds if it ends with `ms`, in seconds otherwise (e.g. `500ms`, `2s`, `1.5`)
+    fn parse_watcher_time(watcher_time: &str) -> Result<Duration, NmdCliError> {
+
+        let watcher_time = watcher_time.trim();
+
+        let parsed = match watcher_time.strip_suffix("ms") {
+            Some(millis) => millis.trim().parse::<u64>().ok().map(Duration::from_millis),
+            None => watcher_time.strip_suffix('s').unwrap_or(watcher_time).trim()
+                                .parse::<f64>().ok()
+                                .and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+        };
+
+        parsed.ok_or(NmdCliError::InvalidWatcherTime(watcher_time.to_string()))
+    }
+
     async fn handle_generate_command(matches: &ArgMatches) -> Result<(), NmdCliError> {
         match matches.subcommand() {
             Some(("dossier", generate_dossier_matches)) => {
@@ -712,11 +1170,61 @@ impl NmdCli {
                 generator_configuration.set_force_generation(generate_dossier_matches.get_flag("force"));
                 generator_configuration.set_gitkeep(generate_dossier_matches.get_flag("gitkeep"));
                 generator_configuration.set_welcome(generate_dossier_matches.get_flag("welcome"));
+
+                if let Some(split_level) = generate_dossier_matches.get_one::<u8>("split-level") {
+                    generator_configuration.set_split_level(Some(*split_level as usize));
+                }
+
+                if generate_dossier_matches.get_flag("no-split") {
+                    generator_configuration.set_split_level(None);
+                }
+
+                if let Some(slug) = generate_dossier_matches.get_one::<String>("slug") {
+                    generator_configuration.set_slug_strategy(SlugStrategy::from_str(slug)?);
+                }
+
+                if let Some(template) = generate_dossier_matches.get_one::<String>("template") {
+
+                    generator_configuration.set_template(Some(template.clone()));
+
+                    generator_configuration.set_author(generate_dossier_matches.get_one::<String>("author").cloned().or_else(|| env::var(format!("{}AUTHOR", ENVIRONMENT_VARIABLES_PREFIX)).ok()));
+                }
                 
+                if generate_dossier_matches.get_one::<String>("path").is_none() {
+
+                    let accept_defaults = generate_dossier_matches.get_flag("yes");
+
+                    if !accept_defaults && !io::stdin().is_terminal() {
+                        return Err(NmdCliError::TooFewArguments(String::from("--path (or --yes to use default settings)")))
+                    }
+
+                    // dossier settings are given by template or Markdown source, if any
+                    let ask_dossier_configuration = generator_configuration.template().is_none() && md_file_path.is_none() && !generate_dossier_matches.contains_id("from-md-dir");
+
+                    let mut wizard = DossierWizard::new(io::stdin().lock(), stdout(), accept_defaults);
+
+                    generator_configuration = wizard.ask_generator_configuration(generator_configuration, ask_dossier_configuration && !generate_dossier_matches.get_flag("welcome"))?;
+
+                    if ask_dossier_configuration {
+
+                        let name = generator_configuration.name().clone().unwrap_or_default();
+
+                        let dossier_configuration = wizard.ask_dossier_configuration(&name, generator_configuration.path())?;
+
+                        Generator::generate_dossier_with_configuration(generator_configuration, dossier_configuration)?;
+
+                        return Ok(())
+                    }
+                }
+
                 if let Some(md_file_path) = md_file_path {
 
                     Generator::generate_dossier_from_markdown_file(&md_file_path, generator_configuration)?;
                     
+                } else if let Some(md_dir_path) = generate_dossier_matches.get_one::<String>("from-md-dir") {
+
+                    Generator::generate_dossier_from_markdown_dir(&PathBuf::from(md_dir_path), generator_configuration)?;
+
                 } else {
 
                     Generator::generate_dossier(generator_configuration)?;
@@ -736,13 +1244,67 @@ impl NmdCli {
             Some(("add", add_dossier_matches)) => {
 
                 if let Some(document_names) = add_dossier_matches.get_many::<String>("document-name") {
-                    
-                    let dossier_manager_configuration = DossierManagerConfiguration::new(dossier_path);
+
+                    let template = match add_dossier_matches.get_one::<String>("template") {
+                        Some(template) => Some(DocumentTemplate::load(template, &dossier_path)?),
+                        None => None,
+                    };
+
+                    let author = add_dossier_matches.get_one::<String>("author").cloned().or_else(|| env::var(format!("{}AUTHOR", ENVIRONMENT_VARIABLES_PREFIX)).ok());
+
+                    // the same content is used for all documents
+                    let content = if let Some(file_path) = add_dossier_matches.get_one::<String>("from-file") {
+
+                        Some(fs::read_to_string(file_path)?)
+
+                    } else if add_dossier_matches.get_flag("stdin") {
+
+                        let mut content = String::new();
+
+                        io::stdin().read_to_string(&mut content)?;
+
+                        Some(content)
+
+                    } else {
+
+                        None
+                    };
+
+                    let mut position = if let Some(position) = add_dossier_matches.get_one::<usize>("position") {
+                        Some(DocumentPosition::Index(*position))
+                    } else if let Some(before) = add_dossier_matches.get_one::<String>("before") {
+                        Some(DocumentPosition::Before(before.clone()))
+                    } else {
+                        add_dossier_matches.get_one::<String>("after").map(|after| DocumentPosition::After(after.clone()))
+                    };
+
+                    let mut dossier_manager_configuration = DossierManagerConfiguration::new(dossier_path);
+                    dossier_manager_configuration.set_force(add_dossier_matches.get_flag("force"));
 
                     let dossier_manager = DossierManager::new(dossier_manager_configuration);
 
                     for file_name in document_names {
-                        dossier_manager.add_empty_document(&file_name)?;
+
+                        let content = match (&template, &content) {
+                            (Some(template), _) => {
+
+                                let mut context = TemplateContext::new(DossierManager::document_title(file_name));
+                                context.set_author(author.clone());
+
+                                template.render(&context)
+                            },
+                            (None, Some(content)) => content.clone(),
+                            (None, None) => String::new(),
+                        };
+
+                        let raw_path = dossier_manager.add_document_at(file_name, &content, position.as_ref())?;
+
+                        // next documents follow added one, keeping their order
+                        position = match position {
+                            Some(DocumentPosition::Index(index)) => Some(DocumentPosition::Index(index + 1)),
+                            Some(DocumentPosition::After(_)) => Some(DocumentPosition::After(raw_path)),
+                            position => position,
+                        };
                     }
 
                     return Ok(())
@@ -751,6 +1313,48 @@ impl NmdCli {
                 Err(NmdCliError::TooFewArguments("dossier path".to_string()))
             },
 
+            Some(("remove", remove_dossier_matches)) => {
+
+                let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path));
+
+                let delete_file = remove_dossier_matches.get_flag("delete-file");
+
+                for document in remove_dossier_matches.get_many::<String>("document-name").unwrap() {
+                    dossier_manager.remove_document(document, delete_file)?;
+                }
+
+                Ok(())
+            },
+
+            Some(("rename", rename_dossier_matches)) => {
+
+                let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path));
+
+                dossier_manager.rename_document(
+                    rename_dossier_matches.get_one::<String>("document-name").unwrap(),
+                    rename_dossier_matches.get_one::<String>("new-name").unwrap()
+                )?;
+
+                Ok(())
+            },
+
+            Some(("move", move_dossier_matches)) => {
+
+                let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path));
+
+                let position = if let Some(position) = move_dossier_matches.get_one::<usize>("position") {
+                    DocumentPosition::Index(*position)
+                } else if let Some(before) = move_dossier_matches.get_one::<String>("before") {
+                    DocumentPosition::Before(before.clone())
+                } else {
+                    DocumentPosition::After(move_dossier_matches.get_one::<String>("after").unwrap().clone())
+                };
+
+                dossier_manager.move_document(move_dossier_matches.get_one::<String>("document-name").unwrap(), &position)?;
+
+                Ok(())
+            },
+
             Some(("reset", reset_dossier_matches)) => {
                 
                 let dossier_manager_configuration = DossierManagerConfiguration::new(dossier_path.clone());
@@ -832,4 +1436,105 @@ impl NmdCli {
         Ok(())
     }
 
-}
\ No newline at end of file
+    async fn handle_check_command(matches: &ArgMatches) -> Result<(), NmdCliError> {
+
+        let mut builder_configuration = BuilderConfiguration::default();
+
+        builder_configuration.set_input_location(PathBuf::from(matches.get_one::<String>("input-path").unwrap()));
+
+        let diagnostics = match builder_configuration.resource_type() {
+            CompilableResourceType::Dossier => Checker::check_dossier(&builder_configuration).await?,
+            CompilableResourceType::File => Checker::check_document(&builder_configuration).await?,
+            CompilableResourceType::Unknown => {
+                log::error!("unknown resource");
+
+                return Err(NmdCliError::UnknownResource)
+            },
+        };
+
+        let errors = diagnostics.iter().filter(|d| d.severity() == DiagnosticSeverity::Error).count();
+        let warnings = diagnostics.len() - errors;
+
+        if matches.get_one::<String>("format").unwrap().eq("json") {
+
+            let output = serde_json::json!({
+                "diagnostics": diagnostics,
+                "errors": errors,
+                "warnings": warnings,
+            });
+
+            stdout().write_all(serde_json::to_string_pretty(&output)?.as_bytes())?;
+            stdout().write_all(b"\n")?;
+
+        } else {
+
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+
+            println!("{} error(s), {} warning(s)", errors, warnings);
+        }
+
+        if errors > 0 {
+            return Err(NmdCliError::CheckFailed(errors))
+        }
+
+        Ok(())
+    }
+
+    async fn handle_serve_command(matches: &ArgMatches) -> Result<(), NmdCliError> {
+
+        let root = PathBuf::from(matches.get_one::<String>("root").unwrap());
+
+        let host = match matches.get_one::<String>("host") {
+            Some(host) => Some(host.parse::<IpAddr>()?),
+            None => None,
+        };
+
+        let port = match matches.get_one::<String>("port") {
+            Some(port) => Some(port.parse::<u16>()?),
+            None => None,
+        };
+
+        let mut server = NmdServer::new(root, host, port);
+
+        server.set_shutdown_token(shutdown::shutdown_token());
+
+        server.serve().await?;
+
+        Ok(())
+    }
+}
+
+
+/// Logger used by NMD CLI. It wraps `SimpleLogger` and collects warnings for build report, before `SimpleLogger` filters records by level
+struct NmdCliLogger {
+    logger: SimpleLogger
+}
+
+impl NmdCliLogger {
+    fn new(logger: SimpleLogger) -> Self {
+        Self {
+            logger
+        }
+    }
+}
+
+impl Log for NmdCliLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+
+        if record.level() == Level::Warn {
+            build_report::collect_warning(format!("[{}] {}", record.target(), record.args()));
+        }
+
+        self.logger.log(record)
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+}
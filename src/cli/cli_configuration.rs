@@ -0,0 +1,415 @@
+use std::{collections::HashMap, env, fs, io, path::{Path, PathBuf}, str::FromStr};
+use nmd_core::{output_format::OutputFormatError, theme::{Theme, ThemeError}};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{builder::{build_format::BuildFormat, builder_configuration::BuilderConfiguration}, utility::user_configuration_dir};
+
+
+/// File names of project-level CLI configuration file, searched from current directory up to root
+pub const PROJECT_CLI_CONFIGURATION_FILE_NAMES: [&str; 2] = ["nmd.toml", ".nmdrc"];
+
+/// File name of user-level CLI configuration file, placed in `$XDG_CONFIG_HOME/nmd` or `~/.config/nmd`
+pub const USER_CLI_CONFIGURATION_FILE_NAME: &str = "nmd.toml";
+
+/// Profile used if no one is selected
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// Prefix of environment variables which override build options (e.g. `NMD_THEME`)
+pub const ENVIRONMENT_VARIABLES_PREFIX: &str = "NMD_";
+
+/// Separator of list values in environment variables (e.g. `NMD_FORMAT=html,json`)
+const ENVIRONMENT_VARIABLES_LIST_SEPARATOR: char = ',';
+
+
+
+#[derive(Error, Debug)]
+pub enum CliConfigurationError {
+
+    #[error("CLI configuration file {0:?} can not be read: {1}")]
+    IoError(PathBuf, io::Error),
+
+    #[error("CLI configuration file {0:?} is invalid: {1}")]
+    TomlError(PathBuf, toml::de::Error),
+
+    #[error("profile '{0}' not found (available profiles: {1})")]
+    ProfileNotFound(String, String),
+
+    #[error("environment variable {0} has an invalid value: '{1}'")]
+    InvalidEnvironmentVariable(String, String),
+
+    #[error(transparent)]
+    OutputFormatError(#[from] OutputFormatError),
+
+    #[error(transparent)]
+    ThemeError(#[from] ThemeError),
+}
+
+
+/// Named set of `build` options. Missing options are not changed
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildProfile {
+    pub format: Option<Vec<String>>,
+    pub theme: Option<String>,
+    pub output_path: Option<PathBuf>,
+    pub style_files: Option<Vec<String>>,
+    pub fast_draft: Option<bool>,
+    pub force: Option<bool>,
+    pub parallelization: Option<bool>,
+    pub embed_local_image: Option<bool>,
+    pub embed_remote_image: Option<bool>,
+    pub compress_embed_image: Option<bool>,
+    pub strict_image_src_check: Option<bool>,
+    pub use_remote_addons: Option<bool>,
+    pub nuid: Option<bool>,
+    pub cache: Option<bool>,
+    pub split: Option<bool>,
+    pub documents_subset: Option<Vec<String>>,
+}
+
+impl BuildProfile {
+
+    /// Build profile from `NMD_*` environment variables (e.g. `NMD_THEME`, `NMD_FORMAT`, `NMD_PARALLELIZATION`, `NMD_OUTPUT_PATH`).
+    /// Empty variables are ignored
+    pub fn from_env() -> Result<Self, CliConfigurationError> {
+        Self::from_variables(|name| env::var(name).ok())
+    }
+
+    fn from_variables(get_variable: impl Fn(&str) -> Option<String>) -> Result<Self, CliConfigurationError> {
+
+        let variable = |option: &str| -> Option<(String, String)> {
+
+            let name = format!("{}{}", ENVIRONMENT_VARIABLES_PREFIX, option);
+
+            get_variable(&name).map(|value| value.trim().to_string())
+                                .filter(|value| !value.is_empty())
+                                .map(|value| (name, value))
+        };
+
+        let string = |option: &str| variable(option).map(|(_, value)| value);
+
+        let list = |option: &str| variable(option).map(|(_, value)| {
+            value.split(ENVIRONMENT_VARIABLES_LIST_SEPARATOR)
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<String>>()
+        });
+
+        let flag = |option: &str| -> Result<Option<bool>, CliConfigurationError> {
+
+            match variable(option) {
+                Some((name, value)) => match value.to_lowercase().as_str() {
+                    "1" | "true" | "yes" | "on" => Ok(Some(true)),
+                    "0" | "false" | "no" | "off" => Ok(Some(false)),
+                    _ => Err(CliConfigurationError::InvalidEnvironmentVariable(name, value)),
+                },
+                None => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            format: list("FORMAT"),
+            theme: string("THEME"),
+            output_path: string("OUTPUT_PATH").map(PathBuf::from),
+            style_files: list("STYLE_FILES"),
+            fast_draft: flag("FAST_DRAFT")?,
+            force: flag("FORCE")?,
+            parallelization: flag("PARALLELIZATION")?,
+            embed_local_image: flag("EMBED_LOCAL_IMAGE")?,
+            embed_remote_image: flag("EMBED_REMOTE_IMAGE")?,
+            compress_embed_image: flag("COMPRESS_EMBED_IMAGE")?,
+            strict_image_src_check: flag("STRICT_IMAGE_SRC_CHECK")?,
+            use_remote_addons: flag("USE_REMOTE_ADDONS")?,
+            nuid: flag("NUID")?,
+            cache: flag("CACHE")?,
+            split: flag("SPLIT")?,
+            documents_subset: list("DOCUMENTS_SUBSET"),
+        })
+    }
+
+    /// Fill options which are not set in `self` using `other`
+    pub fn or(self, other: BuildProfile) -> Self {
+        Self {
+            format: self.format.or(other.format),
+            theme: self.theme.or(other.theme),
+            output_path: self.output_path.or(other.output_path),
+            style_files: self.style_files.or(other.style_files),
+            fast_draft: self.fast_draft.or(other.fast_draft),
+            force: self.force.or(other.force),
+            parallelization: self.parallelization.or(other.parallelization),
+            embed_local_image: self.embed_local_image.or(other.embed_local_image),
+            embed_remote_image: self.embed_remote_image.or(other.embed_remote_image),
+            compress_embed_image: self.compress_embed_image.or(other.compress_embed_image),
+            strict_image_src_check: self.strict_image_src_check.or(other.strict_image_src_check),
+            use_remote_addons: self.use_remote_addons.or(other.use_remote_addons),
+            nuid: self.nuid.or(other.nuid),
+            cache: self.cache.or(other.cache),
+            split: self.split.or(other.split),
+            documents_subset: self.documents_subset.or(other.documents_subset),
+        }
+    }
+
+    /// Paths are relative to directory of configuration file in which they are written
+    fn resolve_paths(&mut self, base_dir: &Path) {
+
+        if let Some(output_path) = self.output_path.as_mut() {
+            if output_path.is_relative() {
+                *output_path = base_dir.join(&output_path);
+            }
+        }
+
+        if let Some(style_files) = self.style_files.as_mut() {
+            for style_file in style_files.iter_mut() {
+                if Path::new(style_file).is_relative() {
+                    *style_file = base_dir.join(&style_file).to_string_lossy().to_string();
+                }
+            }
+        }
+    }
+
+    /// Set options of profile in `builder_configuration`. It must be applied before CLI options and dossier configuration merging
+    pub fn apply(&self, builder_configuration: &mut BuilderConfiguration) -> Result<(), CliConfigurationError> {
+
+        if let Some(formats) = self.format.as_ref() {
+
+            let mut build_formats: Vec<BuildFormat> = Vec::new();
+
+            for format in formats {
+
+                let format = BuildFormat::from_str(format)?;
+
+                if !build_formats.contains(&format) {
+                    build_formats.push(format);
+                }
+            }
+
+            if let Some(output_format) = build_formats.iter().find_map(|format| format.output_format()) {
+                builder_configuration.set_format(output_format);
+            }
+
+            builder_configuration.set_formats(build_formats);
+        }
+
+        if let Some(theme) = self.theme.as_ref() {
+            builder_configuration.set_theme(Some(Theme::from_str(theme)?));
+        }
+
+        if let Some(output_path) = self.output_path.as_ref() {
+            builder_configuration.set_output_location(output_path.clone());
+        }
+
+        if let Some(style_files) = self.style_files.as_ref() {
+            builder_configuration.set_styles_raw_path(style_files.clone());
+        }
+
+        if self.fast_draft.is_some() {
+            builder_configuration.set_fast_draft(self.fast_draft);
+        }
+
+        if self.force.is_some() {
+            builder_configuration.set_force_output(self.force);
+        }
+
+        if self.parallelization.is_some() {
+            builder_configuration.set_parallelization(self.parallelization);
+        }
+
+        if self.embed_local_image.is_some() {
+            builder_configuration.set_embed_local_image(self.embed_local_image);
+        }
+
+        if self.embed_remote_image.is_some() {
+            builder_configuration.set_embed_remote_image(self.embed_remote_image);
+        }
+
+        if self.compress_embed_image.is_some() {
+            builder_configuration.set_compress_embed_image(self.compress_embed_image);
+        }
+
+        if self.strict_image_src_check.is_some() {
+            builder_configuration.set_strict_image_src_check(self.strict_image_src_check);
+        }
+
+        if self.use_remote_addons.is_some() {
+            builder_configuration.set_use_remote_addons(self.use_remote_addons);
+        }
+
+        if self.nuid.is_some() {
+            builder_configuration.set_nuid(self.nuid);
+        }
+
+        if self.cache.is_some() {
+            builder_configuration.set_build_cache(self.cache);
+        }
+
+        if self.split.is_some() {
+            builder_configuration.set_split(self.split);
+        }
+
+        if let Some(documents_subset) = self.documents_subset.as_ref() {
+            builder_configuration.set_documents_subset_to_compile(Some(documents_subset.iter().cloned().collect()));
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Content of a CLI configuration file, e.g.:
+///
+/// ```toml
+/// [profile.draft]
+/// fast_draft = true
+///
+/// [profile.release]
+/// embed_local_image = true
+/// theme = "scientific"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CliConfiguration {
+
+    #[serde(rename = "profile")]
+    pub profiles: HashMap<String, BuildProfile>,
+}
+
+impl CliConfiguration {
+
+    /// Load user-level and project-level configuration files (if they exist). Project-level profiles override user-level ones option by option
+    pub fn discover() -> Result<Self, CliConfigurationError> {
+
+        let mut cli_configuration = Self::default();
+
+        if let Some(user_file) = Self::user_configuration_file().filter(|file| file.is_file()) {
+
+            log::info!("load user CLI configuration from {:?}", user_file);
+
+            cli_configuration = cli_configuration.overridden_by(Self::load(&user_file)?);
+        }
+
+        let current_dir = env::current_dir().map_err(|err| CliConfigurationError::IoError(PathBuf::from("."), err))?;
+
+        if let Some(project_file) = Self::project_configuration_file(&current_dir) {
+
+            log::info!("load project CLI configuration from {:?}", project_file);
+
+            cli_configuration = cli_configuration.overridden_by(Self::load(&project_file)?);
+        }
+
+        Ok(cli_configuration)
+    }
+
+    pub fn load(file: &Path) -> Result<Self, CliConfigurationError> {
+
+        let content = fs::read_to_string(file).map_err(|err| CliConfigurationError::IoError(file.to_path_buf(), err))?;
+
+        let mut cli_configuration: Self = toml::from_str(&content).map_err(|err| CliConfigurationError::TomlError(file.to_path_buf(), err))?;
+
+        if let Some(base_dir) = file.parent() {
+            cli_configuration.profiles.values_mut().for_each(|profile| profile.resolve_paths(base_dir));
+        }
+
+        Ok(cli_configuration)
+    }
+
+    fn overridden_by(mut self, other: Self) -> Self {
+
+        for (name, profile) in other.profiles {
+
+            let profile = match self.profiles.remove(&name) {
+                Some(base) => profile.or(base),
+                None => profile,
+            };
+
+            self.profiles.insert(name, profile);
+        }
+
+        self
+    }
+
+    /// Profile named `name` or, if `name` is `None`, default profile (if it exists)
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<&BuildProfile>, CliConfigurationError> {
+
+        match name {
+            Some(name) => {
+
+                let profile = self.profiles.get(name).ok_or_else(|| {
+
+                    let mut available: Vec<&str> = self.profiles.keys().map(|name| name.as_str()).collect();
+                    available.sort();
+
+                    CliConfigurationError::ProfileNotFound(name.to_string(), available.join(", "))
+                })?;
+
+                Ok(Some(profile))
+            },
+            None => Ok(self.profiles.get(DEFAULT_PROFILE_NAME)),
+        }
+    }
+
+    fn user_configuration_file() -> Option<PathBuf> {
+        Some(user_configuration_dir()?.join(USER_CLI_CONFIGURATION_FILE_NAME))
+    }
+
+    fn project_configuration_file(from_dir: &Path) -> Option<PathBuf> {
+
+        from_dir.ancestors()
+                .flat_map(|dir| PROJECT_CLI_CONFIGURATION_FILE_NAMES.iter().map(move |file_name| dir.join(file_name)))
+                .find(|file| file.is_file())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{BuildProfile, CliConfiguration};
+
+
+    #[test]
+    fn project_profile_overrides_user_profile() {
+
+        let user: CliConfiguration = toml::from_str(r#"
+            [profile.release]
+            theme = "dark"
+            parallelization = true
+        "#).unwrap();
+
+        let project: CliConfiguration = toml::from_str(r#"
+            [profile.release]
+            theme = "scientific"
+        "#).unwrap();
+
+        let cli_configuration = user.overridden_by(project);
+
+        let release = cli_configuration.profile(Some("release")).unwrap().unwrap();
+
+        assert_eq!(release.theme.as_deref(), Some("scientific"));
+        assert_eq!(release.parallelization, Some(true));
+
+        assert!(cli_configuration.profile(Some("ci")).is_err());
+        assert!(cli_configuration.profile(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn profile_from_environment_variables() {
+
+        let variables = HashMap::from([
+            ("NMD_FORMAT", "html, json"),
+            ("NMD_PARALLELIZATION", "Yes"),
+            ("NMD_EMBED_LOCAL_IMAGE", "0"),
+            ("NMD_THEME", ""),
+        ]);
+
+        let profile = BuildProfile::from_variables(|name| variables.get(name).map(|value| value.to_string())).unwrap();
+
+        assert_eq!(profile.format, Some(vec![String::from("html"), String::from("json")]));
+        assert_eq!(profile.parallelization, Some(true));
+        assert_eq!(profile.embed_local_image, Some(false));
+        assert_eq!(profile.theme, None);
+
+        assert!(BuildProfile::from_variables(|name| (name == "NMD_NUID").then(|| String::from("maybe"))).is_err());
+    }
+}
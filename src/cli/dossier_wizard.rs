@@ -0,0 +1,252 @@
+use std::{fs, io::{self, BufRead, Write}, path::{Path, PathBuf}, str::FromStr};
+use nmd_core::{dossier::dossier_configuration::DossierConfiguration, theme::Theme, utility::file_utility};
+use thiserror::Error;
+
+use crate::{dossier_manager::DossierManager, generator::generator_configuration::GeneratorConfiguration};
+
+
+const DEFAULT_DOSSIER_NAME: &str = "New Dossier";
+
+const THEMES: [&str; 6] = ["light", "dark", "scientific", "vintage", "high-contrast", "none"];
+
+
+#[derive(Error, Debug)]
+pub enum DossierWizardError {
+
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error("directory {0:?} is not empty, choose another path or use force option")]
+    DirectoryNotEmpty(PathBuf),
+}
+
+
+/// Interactive questions used to generate a new dossier. If `accept_defaults`, nothing is asked and default answers are used
+pub struct DossierWizard<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    accept_defaults: bool,
+}
+
+impl<R: BufRead, W: Write> DossierWizard<R, W> {
+
+    pub fn new(input: R, output: W, accept_defaults: bool) -> Self {
+        Self {
+            input,
+            output,
+            accept_defaults,
+        }
+    }
+
+    /// Ask name, path and (if `ask_welcome`) welcome page of new dossier. Values already in `configuration` (e.g. name) are not asked.
+    ///
+    /// Dossier directory is created if it doesn't exist, while a not empty directory is overwritten only if user confirms it
+    pub fn ask_generator_configuration(&mut self, mut configuration: GeneratorConfiguration, ask_welcome: bool) -> Result<GeneratorConfiguration, DossierWizardError> {
+
+        if configuration.name().is_none() {
+
+            let name = self.ask("Dossier name", DEFAULT_DOSSIER_NAME)?;
+
+            configuration.set_name(Some(name));
+        }
+
+        let default_path = file_utility::build_output_file_name(configuration.name().as_deref().unwrap_or(DEFAULT_DOSSIER_NAME), None);
+
+        let path = PathBuf::from(self.ask("Dossier path", &default_path)?);
+
+        if !path.exists() {
+
+            // a new directory is always created, force option is not needed
+            configuration.set_force_generation(true);
+
+        } else if !configuration.force_generation() && fs::read_dir(&path).map(|mut entries| entries.next().is_some()).unwrap_or(false) {
+
+            if !self.confirm(&format!("Directory {:?} is not empty, overwrite it?", path), false)? {
+                return Err(DossierWizardError::DirectoryNotEmpty(path))
+            }
+
+            configuration.set_force_generation(true);
+        }
+
+        configuration.set_path(path);
+
+        if ask_welcome {
+
+            let welcome = self.confirm("Add welcome page?", true)?;
+
+            configuration.set_welcome(welcome);
+        }
+
+        Ok(configuration)
+    }
+
+    /// Ask theme, initial documents, table of contents and bibliography settings of dossier `name` in `path`
+    pub fn ask_dossier_configuration(&mut self, name: &str, path: &Path) -> Result<DossierConfiguration, DossierWizardError> {
+
+        let default_configuration = DossierConfiguration::default();
+
+        let mut style = default_configuration.style().clone();
+
+        let theme = self.choose("Theme", &THEMES, &style.theme().to_string())?;
+        style.set_theme(Theme::from_str(&theme).unwrap_or_default());
+
+        let documents: Vec<String> = self.ask("Initial documents (comma separated, e.g. introduction, conclusion)", "")?
+                                        .split(',')
+                                        .map(str::trim)
+                                        .filter(|document| !document.is_empty())
+                                        .map(|document| format!("./{}", DossierManager::document_file_name(document)))
+                                        .collect();
+
+        let mut toc = default_configuration.table_of_contents_configuration().clone();
+
+        let include_toc = self.confirm("Include table of contents?", toc.include_in_output())?;
+        toc.set_include_in_output(include_toc);
+
+        if include_toc {
+
+            let maximum_heading_level = self.ask_number("Maximum heading level in table of contents (1-6)", toc.maximum_heading_level(), 1..=6)?;
+
+            toc.set_maximum_heading_level(maximum_heading_level);
+        }
+
+        let mut bibliography = default_configuration.bibliography().clone();
+
+        let include_bibliography = self.confirm("Include bibliography?", bibliography.include_in_output())?;
+        bibliography.set_include_in_output(include_bibliography);
+
+        if include_bibliography {
+
+            let title = self.ask("Bibliography title", bibliography.title())?;
+
+            bibliography.set_title(title);
+        }
+
+        Ok(DossierConfiguration::new(
+            path.to_path_buf(),
+            name.to_string(),
+            toc,
+            documents,
+            style,
+            default_configuration.references().clone(),
+            default_configuration.compilation().clone(),
+            bibliography,
+        ))
+    }
+
+    /// Answer of user, `None` if default answer must be used
+    fn read_answer(&mut self, question: &str) -> Result<Option<String>, DossierWizardError> {
+
+        if self.accept_defaults {
+            return Ok(None)
+        }
+
+        write!(self.output, "{}: ", question)?;
+        self.output.flush()?;
+
+        let mut answer = String::new();
+
+        // end of input means default answer
+        if self.input.read_line(&mut answer)? == 0 {
+
+            writeln!(self.output)?;
+
+            return Ok(None)
+        }
+
+        let answer = answer.trim();
+
+        if answer.is_empty() {
+            return Ok(None)
+        }
+
+        Ok(Some(answer.to_string()))
+    }
+
+    fn ask(&mut self, question: &str, default: &str) -> Result<String, DossierWizardError> {
+
+        let question = if default.is_empty() { question.to_string() } else { format!("{} [{}]", question, default) };
+
+        Ok(self.read_answer(&question)?.unwrap_or(default.to_string()))
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool, DossierWizardError> {
+
+        let question = format!("{} [{}]", question, if default { "Y/n" } else { "y/N" });
+
+        loop {
+            match self.read_answer(&question)?.map(|answer| answer.to_lowercase()).as_deref() {
+                None => return Ok(default),
+                Some("y") | Some("yes") => return Ok(true),
+                Some("n") | Some("no") => return Ok(false),
+                Some(_) => writeln!(self.output, "Please answer y or n")?,
+            }
+        }
+    }
+
+    fn choose(&mut self, question: &str, options: &[&str], default: &str) -> Result<String, DossierWizardError> {
+
+        let question = format!("{} ({}) [{}]", question, options.join(", "), default);
+
+        loop {
+            match self.read_answer(&question)? {
+                None => return Ok(default.to_string()),
+                Some(answer) if options.contains(&answer.to_lowercase().as_str()) => return Ok(answer.to_lowercase()),
+                Some(_) => writeln!(self.output, "Please choose one of: {}", options.join(", "))?,
+            }
+        }
+    }
+
+    fn ask_number(&mut self, question: &str, default: usize, range: std::ops::RangeInclusive<usize>) -> Result<usize, DossierWizardError> {
+
+        let question = format!("{} [{}]", question, default);
+
+        loop {
+            match self.read_answer(&question)? {
+                None => return Ok(default),
+                Some(answer) => match answer.parse::<usize>() {
+                    Ok(n) if range.contains(&n) => return Ok(n),
+                    _ => writeln!(self.output, "Please insert a number from {} to {}", range.start(), range.end())?,
+                },
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{io::Cursor, path::PathBuf};
+
+    use crate::generator::generator_configuration::GeneratorConfiguration;
+
+    use super::DossierWizard;
+
+
+    #[test]
+    fn answers_are_used() {
+
+        let answers = "My Thesis\n/tmp/nmd-wizard-test-not-existing\nn\nvintage\nIntro, State of the art,\n\nwrong\n3\ny\nReferences\n";
+
+        let mut output: Vec<u8> = Vec::new();
+
+        let mut wizard = DossierWizard::new(Cursor::new(answers), &mut output, false);
+
+        let configuration = wizard.ask_generator_configuration(GeneratorConfiguration::default(), true).unwrap();
+
+        assert_eq!(configuration.name().as_deref(), Some("My Thesis"));
+        assert_eq!(configuration.path(), &PathBuf::from("/tmp/nmd-wizard-test-not-existing"));
+        assert!(configuration.force_generation());
+        assert!(!configuration.welcome());
+
+        let dossier_configuration = wizard.ask_dossier_configuration("My Thesis", configuration.path()).unwrap();
+
+        assert_eq!(dossier_configuration.style().theme().to_string(), "vintage");
+        assert_eq!(dossier_configuration.raw_documents_paths(), &vec![String::from("./intro.nmd"), String::from("./state-of-the-art.nmd")]);
+        assert!(dossier_configuration.table_of_contents_configuration().include_in_output());
+        assert_eq!(dossier_configuration.table_of_contents_configuration().maximum_heading_level(), 3);
+        assert!(dossier_configuration.bibliography().include_in_output());
+        assert_eq!(dossier_configuration.bibliography().title(), "References");
+
+        assert!(String::from_utf8(output).unwrap().contains("Please insert a number from 1 to 6"));
+    }
+}
@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 
 /// NMD CLI version
 pub const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
-pub const MINIMUM_WATCHER_TIME: u64 = 5;
\ No newline at end of file
+/// Default quiet time of watch mode before compilation
+pub const WATCHER_DEBOUNCE_TIME: Duration = Duration::from_millis(300);
@@ -1,10 +1,13 @@
 pub mod dossier_manager_configuration;
+pub mod documents_list_editor;
+pub mod document_template;
 
 
-use std::{io, path::PathBuf};
-use nmd_core::{constants::{DOSSIER_CONFIGURATION_YAML_FILE_NAME, NMD_EXTENSION}, dossier::dossier_configuration::DossierConfiguration, resource::ResourceError, utility::file_utility};
+use std::{fs, io, path::{Path, PathBuf}};
+use nmd_core::{constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME, NMD_EXTENSION}, dossier::dossier_configuration::DossierConfiguration, resource::ResourceError, utility::file_utility};
 use thiserror::Error;
-use self::dossier_manager_configuration::DossierManagerConfiguration;
+use crate::utility::normalize_path;
+use self::{documents_list_editor::{copy_yaml_documents, set_json_documents, set_yaml_documents}, dossier_manager_configuration::DossierManagerConfiguration};
 
 #[derive(Error, Debug)]
 pub enum DossierManagerError {
@@ -15,17 +18,67 @@ pub enum DossierManagerError {
     IoError(#[from] io::Error),
 
     #[error(transparent)]
-    SerdeYamlError(#[from] serde_yaml::Error)
+    SerdeYamlError(#[from] serde_yaml::Error),
 
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("document '{0}' not found in dossier")]
+    DocumentNotFound(String),
+
+    #[error("document '{0}' is ambiguous, it can be one of: {1:?}")]
+    AmbiguousDocument(String, Vec<String>),
+
+    #[error("document {0:?} already exists")]
+    DocumentAlreadyExists(PathBuf),
+
+    #[error("invalid position {0}, it must be between 1 and {1}")]
+    InvalidPosition(usize, usize),
+
+    #[error("documents list of {0:?} can not be edited preserving its formatting")]
+    ConfigurationEditError(PathBuf),
+
+    #[error("template '{0}' not found, available templates: {1}")]
+    TemplateNotFound(String, String),
 }
 
-/// Dossier manager, it can be used to add documents to a dossier
+/// Where a document is moved in documents list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentPosition {
+
+    /// Position in documents list (starting from 1)
+    Index(usize),
+
+    /// Before another document
+    Before(String),
+
+    /// After another document
+    After(String),
+}
+
+/// Dossier manager, it can be used to add, remove, rename and move documents of a dossier
 #[derive(Debug)]
 pub struct DossierManager {
     configuration: DossierManagerConfiguration
 }
 
 impl DossierManager {
+
+    /// Resolve a raw document path of dossier configuration (e.g. `./document.nmd`) using dossier path.
+    ///
+    /// Dossier configuration resolves documents paths using last loaded dossier, so this should be used
+    /// if more dossiers are handled at the same time
+    pub fn resolve_raw_document_path(dossier_path: &Path, raw_path: &str) -> PathBuf {
+
+        let path = PathBuf::from(raw_path.strip_prefix("./").unwrap_or(raw_path));
+
+        if path.is_absolute() {
+            return path
+        }
+
+        dossier_path.join(path)
+    }
+
     pub fn new(configuration: DossierManagerConfiguration) -> Self {
 
         log::debug!("new DossierManager using configuration: \n{:#?}", configuration);
@@ -35,40 +88,185 @@ impl DossierManager {
         }
     }
 
-    /// Add document to dossier. Create file if it doesn't exist.
+    /// Add document to dossier creating its file
     pub fn add_document(&self, filename: &str, content: &str) -> Result<(), DossierManagerError> {
+        self.add_document_at(filename, content, None)?;
+
+        Ok(())
+    }
+
+    /// Add document to dossier in `position` of documents list (at the end if `None`) creating its file.
+    ///
+    /// Raw path of added document in dossier configuration is returned
+    pub fn add_document_at(&self, filename: &str, content: &str, position: Option<&DocumentPosition>) -> Result<String, DossierManagerError> {
+        self.add_document_file_at(&Self::document_file_name(filename), content, position)
+    }
+
+    /// Like `add_document_at`, but `filename` is used as it is, without normalization (e.g. it can contain uppercase letters and spaces).
+    ///
+    /// Document is not added if it is already in documents list or if its file already exists, unless `force` is set in configuration:
+    /// in this case its file is overwritten and it is listed only once
+    pub fn add_document_file_at(&self, filename: &str, content: &str, position: Option<&DocumentPosition>) -> Result<String, DossierManagerError> {
 
-        let mut filename = String::from(filename);
+        let abs_file_path = self.configuration.dossier_path().clone().join(filename);
+        let mut rel_file_path = format!(r"./{}", filename);
+
+        let listed_raw_path = self.listed_raw_path(&abs_file_path)?;
+
+        if !self.configuration.force() && (listed_raw_path.is_some() || abs_file_path.exists()) {
+            return Err(DossierManagerError::DocumentAlreadyExists(abs_file_path))
+        }
 
-        if filename.ends_with(NMD_EXTENSION) {
-            for _ in 0..NMD_EXTENSION.len() + 1  {
+        let already_listed = listed_raw_path.is_some();
 
-                filename.remove(filename.len() - 1);
+        if let Some(listed_raw_path) = listed_raw_path {
+            rel_file_path = listed_raw_path;
+        }
+
+        self.update_documents_list(|documents| {
+
+            if !already_listed {
+                documents.push(rel_file_path.clone());
             }
+
+            if let Some(position) = position {
+                move_document(documents, &rel_file_path, position)?;
+            }
+
+            Ok(())
+        })?;
+
+        if abs_file_path.exists() {
+            log::warn!("document file {:?} will be overwritten", abs_file_path);
         }
 
-        let filename = file_utility::build_output_file_name(&filename, Some(NMD_EXTENSION));
+        // file is created only if documents list is updated, otherwise document is removed from list again
+        if let Err(err) = file_utility::create_file_with_content(&abs_file_path, content) {
 
-        let mut dossier_configuration = DossierConfiguration::try_from(self.configuration.dossier_path())?;
+            if already_listed {
+                return Err(DossierManagerError::from(err))
+            }
+
+            if let Err(revert_err) = self.update_documents_list(|documents| {
+                documents.retain(|document| document.ne(&rel_file_path));
 
-        let abs_file_path = self.configuration.dossier_path().clone().join(&filename);
-        let rel_file_path = format!(r"./{}", filename);
+                Ok(())
+            }) {
+                log::error!("'{}' can not be removed from documents list after a failed creation: {}", rel_file_path, revert_err);
+            }
 
-        file_utility::create_file_with_content(&abs_file_path, content)?;
+            return Err(DossierManagerError::from(err))
+        }
 
         log::info!("created document: '{}'", filename);
 
-        dossier_configuration.append_raw_document_path(rel_file_path);
+        Ok(rel_file_path)
+    }
 
-        dossier_configuration.dump_as_yaml(self.configuration.dossier_path().clone().join(DOSSIER_CONFIGURATION_YAML_FILE_NAME))?;
+    /// Check if document in `document_path` is in documents list
+    pub fn is_listed(&self, document_path: &Path) -> Result<bool, DossierManagerError> {
+        Ok(self.listed_raw_path(document_path)?.is_some())
+    }
 
-        Ok(())
+    /// Raw path of documents list which refers to document in `document_path`, if any
+    fn listed_raw_path(&self, document_path: &Path) -> Result<Option<String>, DossierManagerError> {
+
+        let dossier_configuration = self.load_dossier_configuration()?;
+
+        let document_path = normalize_path(document_path);
+
+        Ok(dossier_configuration.raw_documents_paths().iter()
+            .find(|raw_path| normalize_path(&Self::resolve_raw_document_path(self.configuration.dossier_path(), raw_path)).eq(&document_path))
+            .cloned())
     }
 
     pub fn add_empty_document(&self, filename: &String) -> Result<(), DossierManagerError> {
         self.add_document(filename, "")
     }
 
+    /// Remove document from dossier. Its file is deleted only if `delete_file`
+    pub fn remove_document(&self, document: &str, delete_file: bool) -> Result<(), DossierManagerError> {
+
+        let mut removed_raw_path = String::new();
+
+        self.update_documents_list(|documents| {
+
+            let index = find_document(documents, document)?;
+
+            removed_raw_path = documents.remove(index);
+
+            Ok(())
+        })?;
+
+        log::info!("removed document '{}' from dossier", removed_raw_path);
+
+        if delete_file {
+
+            let file_path = Self::resolve_raw_document_path(self.configuration.dossier_path(), &removed_raw_path);
+
+            fs::remove_file(&file_path)?;
+
+            log::info!("deleted file {:?}", file_path);
+        }
+
+        Ok(())
+    }
+
+    /// Rename document file, keeping its directory and its position in documents list
+    pub fn rename_document(&self, document: &str, new_name: &str) -> Result<(), DossierManagerError> {
+
+        let dossier_configuration = self.load_dossier_configuration()?;
+
+        let old_raw_path = dossier_configuration.raw_documents_paths()[find_document(dossier_configuration.raw_documents_paths(), document)?].clone();
+
+        let new_file_name = Self::document_file_name(new_name);
+
+        let new_raw_path = match old_raw_path.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/{}", dir, new_file_name),
+            None => new_file_name,
+        };
+
+        let old_file_path = Self::resolve_raw_document_path(self.configuration.dossier_path(), &old_raw_path);
+        let new_file_path = Self::resolve_raw_document_path(self.configuration.dossier_path(), &new_raw_path);
+
+        if new_file_path.exists() {
+            return Err(DossierManagerError::DocumentAlreadyExists(new_file_path))
+        }
+
+        fs::rename(&old_file_path, &new_file_path)?;
+
+        let update_result = self.update_documents_list(|documents| {
+
+            if let Some(raw_path) = documents.iter_mut().find(|raw_path| raw_path.eq(&&old_raw_path)) {
+                *raw_path = new_raw_path.clone();
+            }
+
+            Ok(())
+        });
+
+        // file and documents list must be consistent
+        if let Err(err) = update_result {
+
+            fs::rename(&new_file_path, &old_file_path)?;
+
+            return Err(err)
+        }
+
+        log::info!("renamed document '{}' to '{}'", old_raw_path, new_raw_path);
+
+        Ok(())
+    }
+
+    /// Move document to `position` in documents list
+    pub fn move_document(&self, document: &str, position: &DocumentPosition) -> Result<(), DossierManagerError> {
+
+        self.update_documents_list(|documents| move_document(documents, document, position))?;
+
+        log::info!("moved document '{}'", document);
+
+        Ok(())
+    }
+
     pub fn reset_dossier_configuration(&self, dossier_path: PathBuf, preserve_documents_list: bool) -> Result<(), DossierManagerError> {
 
         log::info!("resetting dossier configuration...");
@@ -83,13 +281,327 @@ impl DossierManager {
             log::info!("documents list will be preserved")
         }
 
-        file_utility::create_file_with_content(
-            &dossier_path.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME),
-            &serde_yaml::to_string(&dc)?
-        )?;
+        // existing configuration file format is kept
+        let configuration_file_path = Self::configuration_file_path(&dossier_path);
+
+        let content = if Self::is_json(&configuration_file_path) {
+
+            serde_json::to_string_pretty(&dc)?
+
+        } else {
+
+            let content = serde_yaml::to_string(&dc)?;
+
+            // documents list is kept as it is written (e.g. with its comments)
+            match preserve_documents_list.then(|| fs::read_to_string(&configuration_file_path)) {
+                Some(Ok(ex_content)) => copy_yaml_documents(&ex_content, &content),
+                _ => content,
+            }
+        };
+
+        write_atomically(&configuration_file_path, &content)?;
 
         log::info!("reset done");
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// File name of a new document, e.g. `My Chapter` -> `my-chapter.nmd`
+    pub fn document_file_name(name: &str) -> String {
+
+        let name = name.strip_suffix(&format!(".{}", NMD_EXTENSION)).unwrap_or(name);
+
+        file_utility::build_output_file_name(name, Some(NMD_EXTENSION))
+    }
+
+    /// Title of a new document, e.g. `my-chapter` -> `My Chapter`. Names which are already a title are kept as they are
+    pub fn document_title(name: &str) -> String {
+
+        let name = name.strip_suffix(&format!(".{}", NMD_EXTENSION)).unwrap_or(name);
+
+        if name.contains(' ') {
+            return name.to_string()
+        }
+
+        name.split(['-', '_'])
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+
+                let mut chars = word.chars();
+
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Path of dossier configuration file which is used (`nmd.yml` is preferred, like in dossier loading)
+    fn configuration_file_path(dossier_path: &Path) -> PathBuf {
+
+        let yaml_path = dossier_path.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME);
+        let json_path = dossier_path.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME);
+
+        if !yaml_path.exists() && json_path.exists() {
+            return json_path
+        }
+
+        yaml_path
+    }
+
+    fn is_json(configuration_file_path: &Path) -> bool {
+        configuration_file_path.ends_with(DOSSIER_CONFIGURATION_JSON_FILE_NAME)
+    }
+
+    fn load_dossier_configuration(&self) -> Result<DossierConfiguration, DossierManagerError> {
+        Ok(DossierConfiguration::try_from(self.configuration.dossier_path())?)
+    }
+
+    /// Apply `update` to documents list of dossier configuration. Only documents list is changed in configuration file,
+    /// the rest (comments and formatting too) is preserved. Configuration file is replaced atomically, so it is never
+    /// left half-written; nothing is written if `update` fails
+    fn update_documents_list<F>(&self, update: F) -> Result<(), DossierManagerError>
+        where F: FnOnce(&mut Vec<String>) -> Result<(), DossierManagerError> {
+
+        let dossier_configuration = self.load_dossier_configuration()?;
+
+        let mut documents = dossier_configuration.raw_documents_paths().clone();
+
+        update(&mut documents)?;
+
+        let configuration_file_path = Self::configuration_file_path(self.configuration.dossier_path());
+
+        let content = fs::read_to_string(&configuration_file_path)?;
+
+        let (content, edited_dossier_configuration) = if Self::is_json(&configuration_file_path) {
+
+            let content = set_json_documents(&content, &documents).ok_or(DossierManagerError::ConfigurationEditError(configuration_file_path.clone()))?;
+
+            let edited_dossier_configuration: DossierConfiguration = serde_json::from_str(&content)?;
+
+            (content, edited_dossier_configuration)
+
+        } else {
+
+            let content = set_yaml_documents(&content, &documents);
+
+            let edited_dossier_configuration: DossierConfiguration = serde_yaml::from_str(&content)?;
+
+            (content, edited_dossier_configuration)
+        };
+
+        // edited file must be a valid dossier configuration with exactly the new documents list
+        if edited_dossier_configuration.raw_documents_paths().ne(&documents) {
+            return Err(DossierManagerError::ConfigurationEditError(configuration_file_path))
+        }
+
+        write_atomically(&configuration_file_path, &content)?;
+
+        log::debug!("dossier configuration updated: {:?}", configuration_file_path);
+
+        Ok(())
+    }
+}
+
+/// Write `content` in a temporary file next to `path`, then replace `path` with it
+fn write_atomically(path: &Path, content: &str) -> Result<(), io::Error> {
+
+    let file_name = path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    fs::write(&tmp_path, content)?;
+
+    if let Err(err) = fs::rename(&tmp_path, path) {
+
+        let _ = fs::remove_file(&tmp_path);
+
+        return Err(err)
+    }
+
+    Ok(())
+}
+
+/// Index of `document` in `raw_documents_paths`. Document can be referenced by raw path (with or without `./`),
+/// by file name or by file name without extension
+fn find_document(raw_documents_paths: &[String], document: &str) -> Result<usize, DossierManagerError> {
+
+    let strip = |path: &str| path.strip_prefix("./").unwrap_or(path).to_string();
+
+    let query = strip(document);
+
+    if let Some(index) = raw_documents_paths.iter().position(|raw_path| strip(raw_path).eq(&query)) {
+        return Ok(index)
+    }
+
+    let matches: Vec<usize> = raw_documents_paths.iter().enumerate()
+                                .filter(|(_, raw_path)| {
+
+                                    let file_name = raw_path.rsplit('/').next().unwrap_or(raw_path);
+
+                                    file_name.eq(&query) || file_name.strip_suffix(&format!(".{}", NMD_EXTENSION)).is_some_and(|stem| stem.eq(&query))
+                                })
+                                .map(|(index, _)| index)
+                                .collect();
+
+    match matches.len() {
+        0 => Err(DossierManagerError::DocumentNotFound(document.to_string())),
+        1 => Ok(matches[0]),
+        _ => Err(DossierManagerError::AmbiguousDocument(document.to_string(), matches.into_iter().map(|index| raw_documents_paths[index].clone()).collect())),
+    }
+}
+
+fn move_document(raw_documents_paths: &mut Vec<String>, document: &str, position: &DocumentPosition) -> Result<(), DossierManagerError> {
+
+    let index = find_document(raw_documents_paths, document)?;
+
+    let target_index = match position {
+        DocumentPosition::Index(position) => {
+
+            if *position < 1 || *position > raw_documents_paths.len() {
+                return Err(DossierManagerError::InvalidPosition(*position, raw_documents_paths.len()))
+            }
+
+            position - 1
+        },
+        DocumentPosition::Before(other) | DocumentPosition::After(other) => {
+
+            let mut other_index = find_document(raw_documents_paths, other)?;
+
+            // other document shifts back when moved document is taken out before it
+            if other_index > index {
+                other_index -= 1;
+            }
+
+            if matches!(position, DocumentPosition::After(_)) {
+                other_index += 1;
+            }
+
+            other_index
+        },
+    };
+
+    let raw_path = raw_documents_paths.remove(index);
+
+    raw_documents_paths.insert(target_index.min(raw_documents_paths.len()), raw_path);
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{dossier_manager_configuration::DossierManagerConfiguration, find_document, move_document, DocumentPosition, DossierManager, DossierManagerError};
+
+
+    fn documents() -> Vec<String> {
+        vec![
+            String::from("./intro.nmd"),
+            String::from("./part-1/chapter.nmd"),
+            String::from("./part-2/chapter.nmd"),
+            String::from("./end.nmd"),
+        ]
+    }
+
+    #[test]
+    fn documents_titles() {
+        assert_eq!(DossierManager::document_title("intro"), "Intro");
+        assert_eq!(DossierManager::document_title("state_of-the-art.nmd"), "State Of The Art");
+        assert_eq!(DossierManager::document_title("My chapter"), "My chapter");
+    }
+
+    #[test]
+    fn documents_are_found_by_path_or_name() {
+
+        let documents = documents();
+
+        assert_eq!(find_document(&documents, "intro").unwrap(), 0);
+        assert_eq!(find_document(&documents, "end.nmd").unwrap(), 3);
+        assert_eq!(find_document(&documents, "part-2/chapter.nmd").unwrap(), 2);
+        assert_eq!(find_document(&documents, "./part-1/chapter.nmd").unwrap(), 1);
+
+        assert!(matches!(find_document(&documents, "chapter"), Err(DossierManagerError::AmbiguousDocument(_, _))));
+        assert!(matches!(find_document(&documents, "missing"), Err(DossierManagerError::DocumentNotFound(_))));
+    }
+
+    #[test]
+    fn documents_are_moved() {
+
+        let mut documents = documents();
+
+        move_document(&mut documents, "end", &DocumentPosition::Index(1)).unwrap();
+        assert_eq!(documents[0], "./end.nmd");
+
+        move_document(&mut documents, "end", &DocumentPosition::After(String::from("part-1/chapter.nmd"))).unwrap();
+        assert_eq!(documents, vec!["./intro.nmd", "./part-1/chapter.nmd", "./end.nmd", "./part-2/chapter.nmd"]);
+
+        move_document(&mut documents, "intro", &DocumentPosition::Before(String::from("part-2/chapter.nmd"))).unwrap();
+        assert_eq!(documents, vec!["./part-1/chapter.nmd", "./end.nmd", "./intro.nmd", "./part-2/chapter.nmd"]);
+
+        assert!(matches!(move_document(&mut documents, "intro", &DocumentPosition::Index(5)), Err(DossierManagerError::InvalidPosition(5, 4))));
+    }
+
+    #[test]
+    fn existing_documents_are_not_added_again() {
+
+        let dossier_path = std::env::temp_dir().join(format!("nmd-test-add-document-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dossier_path);
+
+        fs::create_dir_all(&dossier_path).unwrap();
+
+        fs::write(dossier_path.join("nmd.yml"), "name: Dossier\ndocuments:\n- ./intro.nmd\n").unwrap();
+        fs::write(dossier_path.join("intro.nmd"), "# Intro\n").unwrap();
+        fs::write(dossier_path.join("notes.nmd"), "# Notes\n").unwrap();
+
+        let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path.clone()));
+
+        let listed = dossier_manager.add_document_at("intro", "", Some(&DocumentPosition::Index(1)));
+        let not_listed = dossier_manager.add_document_at("notes", "", None);
+
+        let documents = dossier_manager.load_dossier_configuration().unwrap().raw_documents_paths().clone();
+        let intro = fs::read_to_string(dossier_path.join("intro.nmd")).unwrap();
+        let notes = fs::read_to_string(dossier_path.join("notes.nmd")).unwrap();
+
+        fs::remove_dir_all(&dossier_path).unwrap();
+
+        assert!(matches!(listed, Err(DossierManagerError::DocumentAlreadyExists(_))));
+        assert!(matches!(not_listed, Err(DossierManagerError::DocumentAlreadyExists(_))));
+        assert_eq!(documents, vec!["./intro.nmd"]);
+        assert_eq!(intro, "# Intro\n");
+        assert_eq!(notes, "# Notes\n");
+    }
+
+    #[test]
+    fn existing_documents_are_overwritten_if_forced() {
+
+        let dossier_path = std::env::temp_dir().join(format!("nmd-test-add-document-force-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dossier_path);
+
+        fs::create_dir_all(&dossier_path).unwrap();
+
+        fs::write(dossier_path.join("nmd.yml"), "name: Dossier\ndocuments:\n- ./intro.nmd\n- ./end.nmd\n").unwrap();
+        fs::write(dossier_path.join("intro.nmd"), "# Intro\n").unwrap();
+        fs::write(dossier_path.join("end.nmd"), "# End\n").unwrap();
+
+        let mut dossier_manager_configuration = DossierManagerConfiguration::new(dossier_path.clone());
+        dossier_manager_configuration.set_force(true);
+
+        let dossier_manager = DossierManager::new(dossier_manager_configuration);
+
+        dossier_manager.add_document_at("intro", "# New intro\n", Some(&DocumentPosition::Index(2))).unwrap();
+
+        let documents = dossier_manager.load_dossier_configuration().unwrap().raw_documents_paths().clone();
+        let intro = fs::read_to_string(dossier_path.join("intro.nmd")).unwrap();
+
+        fs::remove_dir_all(&dossier_path).unwrap();
+
+        assert_eq!(documents, vec!["./end.nmd", "./intro.nmd"]);
+        assert_eq!(intro, "# New intro\n");
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeSet, fs, path::{Path, PathBuf}};
+use getset::{Getters, Setters};
+use nmd_core::{constants::NMD_EXTENSION, dossier};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::utility::user_configuration_dir;
+
+use super::DossierManagerError;
+
+
+/// Directory of user-defined templates, both in dossier assets directory and in user configuration directory
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Templates shipped with NMD, they can be overridden by user-defined templates with the same name
+const BUILTIN_TEMPLATES: [(&str, &str); 3] = [
+    ("chapter", "# {{title}}\n\n## Introduction\n\n\n\n## Conclusion\n\n"),
+    ("appendix", "# Appendix: {{title}}\n\n"),
+    ("notes", "# {{title}}\n\nDate: {{date}}\n\n## Notes\n\n- \n"),
+];
+
+/// Placeholders like `{{title}}` or `{{ date }}`
+static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap());
+
+
+/// Values of template placeholders
+#[derive(Debug, Clone, Getters, Setters)]
+pub struct TemplateContext {
+
+    /// `{{title}}`
+    #[getset(get = "pub", set = "pub")]
+    title: String,
+
+    /// `{{date}}`
+    #[getset(get = "pub", set = "pub")]
+    date: String,
+
+    /// `{{author}}`, empty if it is not set
+    #[getset(get = "pub", set = "pub")]
+    author: Option<String>,
+}
+
+impl TemplateContext {
+
+    /// Context of a new document: date is today, author is not set
+    pub fn new(title: String) -> Self {
+        Self {
+            title,
+            date: chrono::offset::Local::now().format("%Y-%m-%d").to_string(),
+            author: None,
+        }
+    }
+
+    fn value(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "title" => Some(&self.title),
+            "date" => Some(&self.date),
+            "author" => Some(self.author.as_deref().unwrap_or("")),
+            _ => None,
+        }
+    }
+}
+
+
+/// Template of a new document
+#[derive(Debug, Clone, Getters)]
+pub struct DocumentTemplate {
+
+    #[getset(get = "pub")]
+    name: String,
+
+    #[getset(get = "pub")]
+    content: String,
+}
+
+impl DocumentTemplate {
+
+    /// Load template `name` searching it (as `<name>.nmd`) in dossier `assets/templates/`, then in `~/.config/nmd/templates/`
+    /// and finally in built-in templates
+    pub fn load(name: &str, dossier_path: &Path) -> Result<Self, DossierManagerError> {
+
+        for dir in templates_dirs(dossier_path) {
+
+            let path = dir.join(format!("{}.{}", name, NMD_EXTENSION));
+
+            if path.is_file() {
+
+                log::info!("use template {:?}", path);
+
+                return Ok(Self {
+                    name: name.to_string(),
+                    content: fs::read_to_string(path)?,
+                })
+            }
+        }
+
+        match BUILTIN_TEMPLATES.iter().find(|(builtin_name, _)| builtin_name.eq(&name)) {
+            Some((_, content)) => Ok(Self {
+                name: name.to_string(),
+                content: content.to_string(),
+            }),
+            None => Err(DossierManagerError::TemplateNotFound(name.to_string(), Self::available_templates(dossier_path).join(", "))),
+        }
+    }
+
+    /// Names of all templates which can be used in dossier
+    pub fn available_templates(dossier_path: &Path) -> Vec<String> {
+
+        let mut names: BTreeSet<String> = BUILTIN_TEMPLATES.iter().map(|(name, _)| name.to_string()).collect();
+
+        for dir in templates_dirs(dossier_path) {
+            if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.flatten() {
+
+                    let path = entry.path();
+
+                    if path.extension().is_some_and(|extension| extension == NMD_EXTENSION) {
+                        if let Some(stem) = path.file_stem() {
+                            names.insert(stem.to_string_lossy().to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        names.into_iter().collect()
+    }
+
+    /// Replace placeholders with values of `context`. Unknown placeholders are left as they are
+    pub fn render(&self, context: &TemplateContext) -> String {
+        render_placeholders(&self.content, |placeholder| context.value(placeholder).map(String::from))
+    }
+}
+
+/// Replace placeholders of `content` (e.g. `{{title}}`) with their values. Placeholders without value are left as they are
+pub fn render_placeholders(content: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    PLACEHOLDER_REGEX.replace_all(content, |captures: &Captures| {
+        value(&captures[1]).unwrap_or(captures[0].to_string())
+    }).to_string()
+}
+
+/// Directories of user-defined templates, by priority
+fn templates_dirs(dossier_path: &Path) -> Vec<PathBuf> {
+
+    let mut dirs = vec![dossier_path.join(dossier::ASSETS_DIR).join(TEMPLATES_DIR)];
+
+    if let Some(dir) = user_configuration_dir() {
+        dirs.push(dir.join(TEMPLATES_DIR));
+    }
+
+    dirs
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{DocumentTemplate, TemplateContext};
+
+
+    #[test]
+    fn placeholders_are_rendered() {
+
+        let template = DocumentTemplate {
+            name: String::from("test"),
+            content: String::from("# {{title}}\n\n{{ author }} - {{date}} {{unknown}}\n"),
+        };
+
+        let mut context = TemplateContext::new(String::from("Intro"));
+        context.set_date(String::from("2024-01-31"));
+
+        assert_eq!(template.render(&context), "# Intro\n\n - 2024-01-31 {{unknown}}\n");
+
+        context.set_author(Some(String::from("Ada")));
+
+        assert_eq!(template.render(&context), "# Intro\n\nAda - 2024-01-31 {{unknown}}\n");
+    }
+}
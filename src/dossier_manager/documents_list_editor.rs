@@ -0,0 +1,529 @@
+
+const DOCUMENTS_KEY: &str = "documents";
+
+
+/// Set documents list in YAML dossier configuration `content`, leaving untouched everything else (comments, keys order, formatting
+/// and omitted default values). Comments above an item are kept with it, also if it is moved.
+/// Block and flow styles are preserved (an empty flow list becomes a block list)
+pub fn set_yaml_documents(content: &str, documents: &[String]) -> String {
+
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    let key_index = lines.iter().position(|line| yaml_key_value(line).is_some());
+
+    let mut new_lines: Vec<String> = Vec::new();
+
+    match key_index {
+        None => {
+
+            new_lines.extend(lines.iter().map(|line| line.to_string()));
+            new_lines.extend(yaml_block(documents, ""));
+        },
+        Some(key_index) => {
+
+            new_lines.extend(lines[..key_index].iter().map(|line| line.to_string()));
+
+            let (value, comment) = yaml_key_value(lines[key_index]).unwrap();
+
+            let block_end = yaml_block_end(&lines, key_index + 1);
+
+            if value.starts_with('[') && value != "[]" {
+
+                // flow style is preserved (single line)
+                new_lines.push(format!("{}: [{}]{}", DOCUMENTS_KEY, documents.iter().map(|document| yaml_scalar(document)).collect::<Vec<String>>().join(", "), comment));
+
+            } else if documents.is_empty() {
+
+                new_lines.push(format!("{}: []{}", DOCUMENTS_KEY, comment));
+
+            } else {
+
+                new_lines.push(format!("{}:{}", DOCUMENTS_KEY, comment));
+                new_lines.extend(rebuild_yaml_items(&lines[key_index + 1..block_end], documents));
+            }
+
+            new_lines.extend(lines[block_end..].iter().map(|line| line.to_string()));
+        },
+    }
+
+    let mut new_content = new_lines.join(newline);
+
+    if content.is_empty() || content.ends_with('\n') {
+        new_content.push_str(newline);
+    }
+
+    new_content
+}
+
+/// Copy documents list of YAML dossier configuration `source` (as it is written, comments included) in `target`,
+/// replacing documents list of `target`. `target` is returned as it is if `source` has no documents list
+pub fn copy_yaml_documents(source: &str, target: &str) -> String {
+
+    let source_lines: Vec<&str> = source.lines().collect();
+
+    let Some(source_key_index) = source_lines.iter().position(|line| yaml_key_value(line).is_some()) else {
+        return target.to_string()
+    };
+
+    let source_block = &source_lines[source_key_index..yaml_block_end(&source_lines, source_key_index + 1)];
+
+    let newline = if target.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let target_lines: Vec<&str> = target.lines().collect();
+
+    let mut new_lines: Vec<&str> = Vec::new();
+
+    match target_lines.iter().position(|line| yaml_key_value(line).is_some()) {
+        Some(target_key_index) => {
+
+            new_lines.extend(&target_lines[..target_key_index]);
+            new_lines.extend(source_block);
+            new_lines.extend(&target_lines[yaml_block_end(&target_lines, target_key_index + 1)..]);
+        },
+        None => {
+
+            new_lines.extend(&target_lines);
+            new_lines.extend(source_block);
+        },
+    }
+
+    let mut new_content = new_lines.join(newline);
+
+    if target.is_empty() || target.ends_with('\n') {
+        new_content.push_str(newline);
+    }
+
+    new_content
+}
+
+/// Set documents list in JSON dossier configuration `content`. Formatting of the rest of content is preserved
+pub fn set_json_documents(content: &str, documents: &[String]) -> Option<String> {
+
+    let items: Vec<String> = documents.iter().map(|document| serde_json::to_string(document).unwrap()).collect();
+
+    match json_top_level_value_span(content, DOCUMENTS_KEY) {
+        Some((start, end)) => {
+
+            let old_value = &content[start..end];
+
+            let key_indent = line_indentation(content, start);
+
+            let new_value = if items.is_empty() {
+
+                String::from("[]")
+
+            } else if old_value.contains('\n') || old_value == "[]" && content.contains('\n') {
+
+                let item_indent = format!("{}{}", key_indent, json_indent_unit(content));
+
+                format!("[\n{}\n{}]", items.iter().map(|item| format!("{}{}", item_indent, item)).collect::<Vec<String>>().join(",\n"), key_indent)
+
+            } else {
+
+                format!("[{}]", items.join(", "))
+            };
+
+            Some(format!("{}{}{}", &content[..start], new_value, &content[end..]))
+        },
+        None => {
+
+            // key is added as last one
+            let object_end = content.rfind('}')?;
+
+            let before = content[..object_end].trim_end();
+
+            let separator = if before.ends_with('{') { "" } else { "," };
+
+            Some(format!("{}{}\n{}\"{}\": [{}]\n{}", before, separator, json_indent_unit(content), DOCUMENTS_KEY, items.join(", "), &content[object_end..]))
+        },
+    }
+}
+
+/// New documents key with its block list
+fn yaml_block(documents: &[String], item_indent: &str) -> Vec<String> {
+
+    if documents.is_empty() {
+        return vec![format!("{}: []", DOCUMENTS_KEY)]
+    }
+
+    let mut lines = vec![format!("{}:", DOCUMENTS_KEY)];
+
+    lines.extend(documents.iter().map(|document| format!("{}- {}", item_indent, yaml_scalar(document))));
+
+    lines
+}
+
+/// Value (without comment) and comment (with its leading spaces) of top-level documents key line
+fn yaml_key_value(line: &str) -> Option<(String, String)> {
+
+    let rest = line.strip_prefix(DOCUMENTS_KEY)?.trim_start_matches(' ').strip_prefix(':')?;
+
+    let (value, comment) = split_yaml_comment(rest);
+
+    Some((value.trim().to_string(), comment.to_string()))
+}
+
+/// Split line in content and comment (comment starts with ` #` outside of quotes)
+fn split_yaml_comment(line: &str) -> (&str, &str) {
+
+    let mut quote: Option<char> = None;
+    let mut previous = ' ';
+
+    for (index, c) in line.char_indices() {
+
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => {
+
+                let content = &line[..index];
+                let content_end = content.trim_end().len();
+
+                return (&line[..content_end], &line[content_end..])
+            },
+            None => (),
+        }
+
+        previous = c;
+    }
+
+    (line, "")
+}
+
+/// Index of first line after block of documents list which starts from `start`. Trailing blank and comment lines
+/// are not part of block, because they precede next key
+fn yaml_block_end(lines: &[&str], start: usize) -> usize {
+
+    let mut end = start;
+
+    while end < lines.len() {
+
+        let line = lines[end];
+
+        if line.trim().is_empty() || line.starts_with(' ') || line.starts_with('\t') || line.starts_with('-') || line.starts_with('#') {
+            end += 1;
+        } else {
+            break
+        }
+    }
+
+    while end > start && (lines[end - 1].trim().is_empty() || lines[end - 1].starts_with('#')) {
+        end -= 1;
+    }
+
+    end
+}
+
+/// Item of a block list with the lines which belong to it (comments above it and continuation lines)
+struct YamlItem {
+    value: String,
+    lines: Vec<String>,
+    item_line: usize,
+}
+
+/// Rebuild items of a block list: existing items are reused (with their comments), an item replaced in the same position
+/// (e.g. renamed document) keeps its comments, and new ones use indentation of first item
+fn rebuild_yaml_items(block: &[&str], documents: &[String]) -> Vec<String> {
+
+    let mut header: Vec<String> = Vec::new();
+    let mut pending_comments: Vec<String> = Vec::new();
+    let mut items: Vec<YamlItem> = Vec::new();
+    let mut item_indent: Option<String> = None;
+
+    for line in block {
+
+        let trimmed = line.trim_start();
+
+        if let Some(item) = trimmed.strip_prefix("- ").or(if trimmed == "-" { Some("") } else { None }) {
+
+            let indent = line[..line.len() - trimmed.len()].to_string();
+
+            // comments above first item are a header of the list if they are separated from item by a blank line
+            if item_indent.is_none() {
+
+                item_indent = Some(indent);
+
+                if let Some(last_blank) = pending_comments.iter().rposition(|line| line.trim().is_empty()) {
+                    header.extend(pending_comments.drain(..=last_blank));
+                }
+            }
+
+            let (value, _) = split_yaml_comment(item);
+
+            let mut lines = std::mem::take(&mut pending_comments);
+            lines.push(line.to_string());
+
+            items.push(YamlItem {
+                value: parse_yaml_scalar(value.trim()),
+                item_line: lines.len() - 1,
+                lines,
+            });
+
+        } else if trimmed.starts_with('#') || trimmed.is_empty() {
+
+            pending_comments.push(line.to_string());
+
+        } else if let Some(last_item) = items.last_mut() {
+
+            // continuation of last item (e.g. multi-line scalar)
+            last_item.lines.append(&mut pending_comments);
+            last_item.lines.push(line.to_string());
+        }
+    }
+
+    let mut used = vec![false; items.len()];
+
+    // items are taken in their original order if a document is listed more times
+    let mut matches: Vec<Option<usize>> = documents.iter().map(|document| {
+
+        let index = items.iter().enumerate().position(|(index, item)| !used[index] && item.value.eq(document))?;
+
+        used[index] = true;
+
+        Some(index)
+
+    }).collect();
+
+    for (position, m) in matches.iter_mut().enumerate() {
+        if m.is_none() && position < items.len() && !used[position] {
+
+            used[position] = true;
+
+            *m = Some(position);
+        }
+    }
+
+    let item_indent = item_indent.unwrap_or_default();
+
+    let mut lines = header;
+
+    for (document, m) in documents.iter().zip(matches) {
+        match m.map(|index| &items[index]) {
+            Some(item) if item.value.eq(document) => lines.extend(item.lines.iter().cloned()),
+            Some(item) => {
+
+                let item_line = &item.lines[item.item_line];
+
+                let (_, comment) = split_yaml_comment(item_line);
+
+                let indent = &item_line[..item_line.len() - item_line.trim_start().len()];
+
+                lines.extend(item.lines[..item.item_line].iter().cloned());
+                lines.push(format!("{}- {}{}", indent, yaml_scalar(document), comment));
+            },
+            None => lines.push(format!("{}- {}", item_indent, yaml_scalar(document))),
+        }
+    }
+
+    lines.extend(pending_comments);
+
+    lines
+}
+
+fn parse_yaml_scalar(value: &str) -> String {
+
+    if value.len() >= 2 {
+
+        if value.starts_with('\'') && value.ends_with('\'') {
+            return value[1..value.len() - 1].replace("''", "'")
+        }
+
+        if value.starts_with('"') && value.ends_with('"') {
+            if let Ok(value) = serde_yaml::from_str::<String>(value) {
+                return value
+            }
+        }
+    }
+
+    value.to_string()
+}
+
+fn yaml_scalar(value: &str) -> String {
+
+    let plain = !value.is_empty()
+                    && value.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '/' || c == '_')
+                    && !value.ends_with(' ')
+                    && !value.contains(": ")
+                    && !value.contains(" #")
+                    && !value.contains(['[', ']', '{', '}', ',']);
+
+    if plain {
+        return value.to_string()
+    }
+
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Byte span of value of `key` in top-level JSON object
+fn json_top_level_value_span(content: &str, key: &str) -> Option<(usize, usize)> {
+
+    let bytes = content.as_bytes();
+
+    let mut depth = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+
+        match bytes[index] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth -= 1,
+            b'"' => {
+
+                let string_end = json_string_end(bytes, index)?;
+
+                let is_key = depth == 1 && content[string_end + 1..].trim_start().starts_with(':');
+
+                if is_key && &content[index + 1..string_end] == key {
+
+                    let colon = string_end + 1 + content[string_end + 1..].find(':')?;
+
+                    let value_start = colon + 1 + (content[colon + 1..].len() - content[colon + 1..].trim_start().len());
+
+                    return Some((value_start, json_value_end(bytes, value_start)?))
+                }
+
+                index = string_end;
+            },
+            _ => (),
+        }
+
+        index += 1;
+    }
+
+    None
+}
+
+/// Index of closing quote of string which starts at `start`
+fn json_string_end(bytes: &[u8], start: usize) -> Option<usize> {
+
+    let mut index = start + 1;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return Some(index),
+            _ => index += 1,
+        }
+    }
+
+    None
+}
+
+/// Index after end of value which starts at `start`
+fn json_value_end(bytes: &[u8], start: usize) -> Option<usize> {
+
+    let mut depth = 0;
+    let mut index = start;
+
+    while index < bytes.len() {
+
+        match bytes[index] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+
+                if depth == 0 {
+                    return Some(index)
+                }
+
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(index + 1)
+                }
+            },
+            b',' if depth == 0 => return Some(index),
+            b'"' => {
+
+                index = json_string_end(bytes, index)?;
+
+                if depth == 0 {
+                    return Some(index + 1)
+                }
+            },
+            _ => (),
+        }
+
+        index += 1;
+    }
+
+    Some(index)
+}
+
+fn line_indentation(content: &str, index: usize) -> String {
+
+    let line_start = content[..index].rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    content[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+/// Indentation of first indented line (e.g. indentation of top-level keys), 2 spaces by default
+fn json_indent_unit(content: &str) -> String {
+    content.lines()
+        .map(|line| line.chars().take_while(|c| *c == ' ' || *c == '\t').collect::<String>())
+        .find(|indent| !indent.is_empty())
+        .unwrap_or_else(|| String::from("  "))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{copy_yaml_documents, set_json_documents, set_yaml_documents};
+
+
+    fn documents(documents: &[&str]) -> Vec<String> {
+        documents.iter().map(|document| document.to_string()).collect()
+    }
+
+    #[test]
+    fn yaml_edit_preserves_comments_and_formatting() {
+
+        let content = "# my dossier\nname: Thesis   # title\n\n# chapters in order\ndocuments:\n  # first chapter\n  - ./intro.nmd\n  - './results.nmd'  # draft\n\n# custom style\nstyle:\n  theme: Dark\n";
+
+        let edited = set_yaml_documents(content, &documents(&["./results.nmd", "./intro.nmd", "./conclusion.nmd"]));
+
+        assert_eq!(edited, "# my dossier\nname: Thesis   # title\n\n# chapters in order\ndocuments:\n  - './results.nmd'  # draft\n  # first chapter\n  - ./intro.nmd\n  - ./conclusion.nmd\n\n# custom style\nstyle:\n  theme: Dark\n");
+
+        let renamed = set_yaml_documents(content, &documents(&["./introduction.nmd", "./results.nmd"]));
+
+        assert_eq!(renamed, "# my dossier\nname: Thesis   # title\n\n# chapters in order\ndocuments:\n  # first chapter\n  - ./introduction.nmd\n  - './results.nmd'  # draft\n\n# custom style\nstyle:\n  theme: Dark\n");
+    }
+
+    #[test]
+    fn yaml_edit_of_empty_and_flow_lists() {
+
+        assert_eq!(set_yaml_documents("name: a\ndocuments: []\nstyle: {}\n", &documents(&["./a.nmd"])), "name: a\ndocuments:\n- ./a.nmd\nstyle: {}\n");
+
+        assert_eq!(set_yaml_documents("documents: [./a.nmd, ./b.nmd] # list\n", &documents(&["./b.nmd"])), "documents: [./b.nmd] # list\n");
+
+        assert_eq!(set_yaml_documents("documents:\n- ./a.nmd\n", &documents(&[])), "documents: []\n");
+
+        assert_eq!(set_yaml_documents("name: a\n", &documents(&["./a.nmd"])), "name: a\ndocuments:\n- ./a.nmd\n");
+    }
+
+    #[test]
+    fn yaml_documents_are_copied() {
+
+        let source = "name: Old\ndocuments:\n  # first\n  - ./a.nmd  # draft\n  - ./b.nmd\nstyle:\n  theme: Dark\n";
+
+        assert_eq!(copy_yaml_documents(source, "name: New\ndocuments: []\nstyle:\n  theme: Light\n"), "name: New\ndocuments:\n  # first\n  - ./a.nmd  # draft\n  - ./b.nmd\nstyle:\n  theme: Light\n");
+    }
+
+    #[test]
+    fn json_edit_preserves_formatting() {
+
+        let content = "{\n    \"name\": \"a\",\n    \"documents\": [\n        \"./a.nmd\"\n    ],\n    \"style\": {\"theme\": \"Dark\"}\n}\n";
+
+        assert_eq!(
+            set_json_documents(content, &documents(&["./a.nmd", "./b.nmd"])).unwrap(),
+            "{\n    \"name\": \"a\",\n    \"documents\": [\n        \"./a.nmd\",\n        \"./b.nmd\"\n    ],\n    \"style\": {\"theme\": \"Dark\"}\n}\n"
+        );
+
+        assert_eq!(
+            set_json_documents("{\"name\": \"a\", \"documents\": [\"./a.nmd\"]}", &documents(&[])).unwrap(),
+            "{\"name\": \"a\", \"documents\": []}"
+        );
+    }
+}
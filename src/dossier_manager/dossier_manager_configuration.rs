@@ -6,13 +6,18 @@ use getset::{Getters, Setters};
 pub struct DossierManagerConfiguration {
 
     #[getset(get = "pub", set = "pub")]
-    dossier_path: PathBuf
+    dossier_path: PathBuf,
+
+    /// Overwrite files of added documents if they already exist
+    #[getset(get = "pub", set = "pub")]
+    force: bool,
 }
 
 impl DossierManagerConfiguration {
     pub fn new(dossier_path: PathBuf) -> Self {
         Self {
-            dossier_path
+            dossier_path,
+            force: false,
         }
     }
 }
@@ -20,7 +25,8 @@ impl DossierManagerConfiguration {
 impl Default for DossierManagerConfiguration {
     fn default() -> Self {
         Self {
-            dossier_path: PathBuf::from(".")
+            dossier_path: PathBuf::from("."),
+            force: false,
         }
     }
 }
\ No newline at end of file
@@ -1,18 +1,20 @@
 pub mod generator_configuration;
+pub mod document_slug;
+pub mod dossier_template;
+pub mod markdown_book;
+pub mod markdown_importer;
 
 
 use std::{collections::HashMap, fs, path::PathBuf};
-use nmd_core::{codex::modifier::constants::NEW_LINE_PATTERN, constants::DOSSIER_CONFIGURATION_YAML_FILE_NAME, dossier::{self, dossier_configuration::DossierConfiguration}, resource::{disk_resource::DiskResource, Resource, ResourceError}, utility::file_utility::{self, read_file_content}};
-use once_cell::sync::Lazy;
-use regex::Regex;
-use crate::dossier_manager::{dossier_manager_configuration::DossierManagerConfiguration, DossierManager};
+use nmd_core::{constants::{DOSSIER_CONFIGURATION_YAML_FILE_NAME, NMD_EXTENSION}, dossier::{self, dossier_configuration::DossierConfiguration}, resource::{disk_resource::DiskResource, Resource, ResourceError}, utility::file_utility::{self, read_file_content}};
+use crate::dossier_manager::{dossier_manager_configuration::DossierManagerConfiguration, DocumentPosition, DossierManager};
 
-use self::generator_configuration::GeneratorConfiguration;
+use self::{document_slug::SlugStrategy, dossier_template::DossierTemplate, generator_configuration::GeneratorConfiguration, markdown_book::{rewrite_page_links, MarkdownBook}, markdown_importer::{MarkdownImport, MarkdownImporter}};
 
 
 pub const WELCOME_FILE_NAME: &str = "welcome.nmd";
 
-static SEARCH_HEADING_1_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^#[^#][ ]*(.+))").unwrap());
+const WELCOME_CONTENT: &str = "Welcome in **NMD**!";
 
 
 pub struct Generator {
@@ -37,7 +39,8 @@ impl Generator {
                 return Err(ResourceError::ReadError("check permission".to_string()))
             }
 
-            if configuration.force_generation() {
+            // dossier templates never overwrite existing files, so directory is not cleared
+            if configuration.force_generation() && configuration.template().is_none() {
                 fs::remove_dir_all(configuration.path().clone())?;
                 log::info!("cleared {}", configuration.path().to_string_lossy());
 
@@ -63,29 +66,12 @@ impl Generator {
             log::info!("created dossier directory");
         }
 
-        let assets_path = configuration.path().join(dossier::ASSETS_DIR);
-
-        file_utility::create_directory(&assets_path)?;
-        log::info!("added {}/ directory", dossier::ASSETS_DIR);
-
-        file_utility::create_directory(&assets_path.join(dossier::IMAGES_DIR))?;
-        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::IMAGES_DIR);
-
-        file_utility::create_directory(&assets_path.join(dossier::DOCUMENTS_DIR))?;
-        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::DOCUMENTS_DIR);
-
-        file_utility::create_directory(&assets_path.join(dossier::STYLES_DIR))?;
-        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::STYLES_DIR);
-
-        if configuration.gitkeep() {
-
-            file_utility::create_empty_file(&assets_path.join("images").join(".gitkeep"))?;
-            file_utility::create_empty_file(&assets_path.join("documents").join(".gitkeep"))?;
-            file_utility::create_empty_file(&assets_path.join("styles").join(".gitkeep"))?;
-
-            log::info!("added .gitkeep files");
+        if let Some(template) = configuration.template() {
+            return Self::apply_template(template, &configuration)
         }
 
+        Self::create_assets_directories(&configuration)?;
+
         let mut dossier_configuration: DossierConfiguration;
         
         if !configuration.evaluate_existing_files() {
@@ -105,7 +91,7 @@ impl Generator {
 
             let mut welcome_document = DiskResource::try_from(configuration.path().join(WELCOME_FILE_NAME))?;
 
-            welcome_document.write("Welcome in **NMD**!")?;
+            welcome_document.write(WELCOME_CONTENT)?;
 
             log::info!("added welcome page");
 
@@ -122,64 +108,293 @@ impl Generator {
         Ok(dossier_configuration)
     }
 
+    /// Generate a new dossier using `dossier_configuration` (e.g. built by CLI wizard) instead of default one. Its documents which
+    /// don't exist are created with a heading based on their names
+    pub fn generate_dossier_with_configuration(configuration: GeneratorConfiguration, mut dossier_configuration: DossierConfiguration) -> Result<DossierConfiguration, ResourceError> {
+
+        let dossier_path = configuration.path().clone();
+
+        let generated_dossier_configuration = Self::generate_dossier(configuration)?;
+
+        // generated documents (e.g. welcome page) are placed first
+        let mut documents = generated_dossier_configuration.raw_documents_paths().clone();
+
+        for document in dossier_configuration.raw_documents_paths() {
+
+            if documents.contains(document) {
+                continue
+            }
+
+            let document_path = dossier_path.join(document);
+
+            if !document_path.exists() {
+
+                let title = DossierManager::document_title(&document_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default());
+
+                file_utility::create_file_with_content(&document_path, &format!("# {}\n\n", title))?;
+
+                log::info!("added document {}", document);
+            }
+
+            documents.push(document.clone());
+        }
+
+        dossier_configuration.set_raw_documents_paths(documents);
+        dossier_configuration.set_root_path(dossier_path.clone());
+
+        dossier_configuration.dump_as_yaml(dossier_path.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME))?;
+
+        log::info!("dossier configuration file '{}' updated", DOSSIER_CONFIGURATION_YAML_FILE_NAME);
+
+        Ok(dossier_configuration)
+    }
+
+    /// Create assets directories of new dossier (and their `.gitkeep` files, if requested). Existing directories are kept
+    fn create_assets_directories(configuration: &GeneratorConfiguration) -> Result<(), ResourceError> {
+
+        let assets_path = configuration.path().join(dossier::ASSETS_DIR);
+
+        fs::create_dir_all(&assets_path)?;
+        log::info!("added {}/ directory", dossier::ASSETS_DIR);
+
+        fs::create_dir_all(assets_path.join(dossier::IMAGES_DIR))?;
+        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::IMAGES_DIR);
+
+        fs::create_dir_all(assets_path.join(dossier::DOCUMENTS_DIR))?;
+        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::DOCUMENTS_DIR);
+
+        fs::create_dir_all(assets_path.join(dossier::STYLES_DIR))?;
+        log::info!("added {}/{} directory", dossier::ASSETS_DIR, dossier::STYLES_DIR);
+
+        if configuration.gitkeep() {
+
+            file_utility::create_empty_file(&assets_path.join("images").join(".gitkeep"))?;
+            file_utility::create_empty_file(&assets_path.join("documents").join(".gitkeep"))?;
+            file_utility::create_empty_file(&assets_path.join("styles").join(".gitkeep"))?;
+
+            log::info!("added .gitkeep files");
+        }
+
+        Ok(())
+    }
+
+    /// Write files of dossier `template` in new dossier, filling its placeholders: `{{name}}` (dossier name, by default based on
+    /// dossier directory name), `{{author}}` and `{{date}}` (today)
+    fn apply_template(template: &str, configuration: &GeneratorConfiguration) -> Result<DossierConfiguration, ResourceError> {
+
+        let template = DossierTemplate::load(template)?;
+
+        let name = configuration.name().clone().unwrap_or_else(|| {
+            DossierManager::document_title(&configuration.path().file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default())
+        });
+
+        let author = configuration.author().clone().unwrap_or_default();
+
+        let date = chrono::offset::Local::now().format("%Y-%m-%d").to_string();
+
+        template.apply(configuration.path(), |placeholder| match placeholder {
+            "name" => Some(name.clone()),
+            "author" => Some(author.clone()),
+            "date" => Some(date.clone()),
+            _ => None,
+        })?;
+
+        Self::create_assets_directories(configuration)?;
+
+        if configuration.welcome() {
+
+            let welcome_path = configuration.path().join(WELCOME_FILE_NAME);
+
+            let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(configuration.path().clone()));
+
+            // template can provide its own welcome page
+            if welcome_path.exists() || dossier_manager.is_listed(&welcome_path).map_err(|err| ResourceError::Creation(err.to_string()))? {
+
+                log::info!("welcome page is not added, because dossier template '{}' already provides it", template.name());
+
+            } else {
+
+                dossier_manager.add_document_at(WELCOME_FILE_NAME, WELCOME_CONTENT, Some(&DocumentPosition::Index(1))).map_err(|err| ResourceError::Creation(err.to_string()))?;
+
+                log::info!("added welcome page");
+            }
+        }
+
+        DossierConfiguration::try_from(configuration.path())
+            .map_err(|err| ResourceError::InvalidResourceVerbose(format!("dossier template '{}' has an invalid dossier configuration: {}", template.name(), err)))
+    }
+
+    /// Generate a new dossier importing a Markdown file: it is converted in NMD and split in a document for each heading up to
+    /// split level (level 1 by default), its front matter is applied to dossier configuration and its local images are copied in dossier
     pub fn generate_dossier_from_markdown_file(markdown_source_file_path: &PathBuf, configuration: GeneratorConfiguration) -> Result<DossierConfiguration, ResourceError> {
         let markdown_file_content = read_file_content(markdown_source_file_path)?;
 
+        let source_dir = markdown_source_file_path.parent().map(PathBuf::from).unwrap_or_default();
+
+        let import = MarkdownImporter::new(source_dir).import(&markdown_file_content);
+
         let dossier_path = configuration.path().clone();
-        let dossier_configuration = Self::generate_dossier(configuration)?;
+        let override_name = configuration.name().is_none();
+        let split_level = configuration.split_level();
+        let slug_strategy = configuration.slug_strategy();
+
+        let mut dossier_configuration = Self::generate_dossier(configuration)?;
+
+        if import.front_matter().is_some() {
+
+            dossier_configuration = import.apply_front_matter(dossier_configuration, override_name)?;
+
+            dossier_configuration.dump_as_yaml(dossier_path.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME))?;
+
+            log::info!("front matter applied to dossier configuration");
+        }
+
+        import.copy_images(&dossier_path.join(dossier::ASSETS_DIR).join(dossier::IMAGES_DIR))?;
 
         let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path));
 
-        let mut current_nmd_file_content = String::new();
-        let mut current_nmd_file_name: Option<String> = None;
+        let default_title = markdown_source_file_path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or(String::from("document"));
+
+        let sections: Vec<(String, String)> = match split_level {
+            Some(split_level) => import.sections(split_level).into_iter()
+                                    .map(|section| (section.title().clone().unwrap_or(default_title.clone()), section.content().clone()))
+                                    .collect(),
+            None => vec![(import.headings().first().cloned().unwrap_or(default_title), import.document())],
+        };
 
         let mut document_names: HashMap<String, u32> = HashMap::new();
 
-        let mut in_code_block = false;
+        for (index, (title, content)) in sections.iter().enumerate() {
 
-        for line in markdown_file_content.lines() {
+            log::info!("generating new document...");
 
-            if line.starts_with("```") {
-                in_code_block = !in_code_block;
-            }
+            let file_name = Self::unique_file_name(title, index + 1, sections.len(), slug_strategy, &mut document_names);
+
+            dossier_manager.add_document_file_at(&file_name, content, None).map_err(|err| ResourceError::Creation(err.to_string()))?;
+        }
+
+        Ok(dossier_configuration)
+    }
+
+    /// Generate a new dossier importing a directory of Markdown pages (e.g. mdBook or MkDocs documentation): a document is created
+    /// for each page in reading order, links between pages become NMD references and local images are copied in dossier
+    pub fn generate_dossier_from_markdown_dir(markdown_dir_path: &PathBuf, mut configuration: GeneratorConfiguration) -> Result<DossierConfiguration, ResourceError> {
+
+        let book = MarkdownBook::load(markdown_dir_path)?;
 
-            if !in_code_block && SEARCH_HEADING_1_REGEX.is_match(line) {
+        if book.pages().is_empty() {
+            return Err(ResourceError::InvalidResourceVerbose(format!("no Markdown pages found in {:?}", markdown_dir_path)))
+        }
 
-                log::info!("new header 1 found, generating new document...");
+        log::info!("{} Markdown page(s) found", book.pages().len());
 
-                if let Some(ref file_name) = current_nmd_file_name {
+        if configuration.name().is_none() {
+            configuration.set_name(book.title().clone());
+        }
 
-                    let mut file_name = String::from(file_name);
+        let dossier_path = configuration.path().clone();
+        let slug_strategy = configuration.slug_strategy();
 
-                    let n = *document_names.get(&file_name).unwrap_or(&0) + 1;
-                    document_names.insert(file_name.clone(), n);
+        let dossier_configuration = Self::generate_dossier(configuration)?;
 
-                    if let Some(n) = document_names.get(&file_name) {
+        let images_dir = dossier_path.join(dossier::ASSETS_DIR).join(dossier::IMAGES_DIR);
 
-                        let n = *n;
+        let mut importer = MarkdownImporter::new(markdown_dir_path.clone());
 
-                        if n > 1 {
-                            file_name = format!("{}-{}", file_name, n);
-                        }
+        let mut document_names: HashMap<String, u32> = HashMap::new();
 
-                    } else {
-                        unreachable!();
-                    }
+        let mut documents: HashMap<PathBuf, (String, Vec<String>)> = HashMap::new();
+        let mut imports: Vec<(&PathBuf, String, MarkdownImport)> = Vec::new();
 
-                    dossier_manager.add_document(&file_name, &current_nmd_file_content).unwrap();
-                    current_nmd_file_content.clear();
-                }
+        for (index, page) in book.pages().iter().enumerate() {
 
-                current_nmd_file_name = Some(String::from(SEARCH_HEADING_1_REGEX.captures(line).unwrap().get(1).unwrap().as_str()));
+            log::info!("import page {:?}", page.path());
 
-            }
+            importer.set_source_dir(page.path().parent().map(PathBuf::from).unwrap_or_default());
 
-            current_nmd_file_content.push_str(&format!("{}{}", line, NEW_LINE_PATTERN));
-            
+            let import = importer.import(&read_file_content(page.path())?);
+
+            import.copy_images(&images_dir)?;
+
+            let headings = import.headings();
+
+            let title = page.title().clone()
+                            .or(headings.first().cloned())
+                            .unwrap_or(page.path().file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or(String::from("document")));
+
+            let file_name = Self::unique_file_name(&title, index + 1, book.pages().len(), slug_strategy, &mut document_names);
+
+            documents.insert(page.path().clone(), (file_name.clone(), headings));
+            imports.push((page.path(), file_name, import));
+        }
+
+        let dossier_manager = DossierManager::new(DossierManagerConfiguration::new(dossier_path));
+
+        for (page_path, file_name, import) in imports {
+
+            let content = rewrite_page_links(&import.document(), page_path, &documents);
+
+            dossier_manager.add_document_file_at(&file_name, &content, None).map_err(|err| ResourceError::Creation(err.to_string()))?;
         }
-        
 
         Ok(dossier_configuration)
     }
+
+    /// File name of an imported document built using `slug_strategy`. Names already used (case insensitive, because of
+    /// Windows and macOS file systems) are suffixed with a counter, e.g. `intro-2.nmd`
+    fn unique_file_name(title: &str, index: usize, total: usize, slug_strategy: SlugStrategy, document_names: &mut HashMap<String, u32>) -> String {
+
+        let file_name = slug_strategy.file_name(title, index, total);
+
+        let key = file_name.to_lowercase();
+
+        let n = *document_names.get(&key).unwrap_or(&0) + 1;
+        document_names.insert(key, n);
+
+        if n == 1 {
+            return file_name
+        }
+
+        let stem = file_name.strip_suffix(&format!(".{}", NMD_EXTENSION)).unwrap_or(&file_name);
+
+        format!("{}-{}.{}", stem, n, NMD_EXTENSION)
+    }
 }
 
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{Generator, GeneratorConfiguration, WELCOME_FILE_NAME};
+
+
+    #[test]
+    fn welcome_page_of_dossier_template_is_kept() {
+
+        let dir = std::env::temp_dir().join(format!("nmd-test-template-welcome-{}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        let template_path = dir.join("template");
+        let dossier_path = dir.join("dossier");
+
+        fs::create_dir_all(&template_path).unwrap();
+
+        fs::write(template_path.join("nmd.yml"), "name: \"{{name}}\"\ndocuments:\n- ./welcome.nmd\n").unwrap();
+        fs::write(template_path.join(WELCOME_FILE_NAME), "# Template welcome\n").unwrap();
+
+        let mut configuration = GeneratorConfiguration::new(None, dossier_path.clone(), true, true, false, false);
+        configuration.set_template(Some(template_path.to_string_lossy().to_string()));
+
+        let dossier_configuration = Generator::generate_dossier(configuration);
+
+        let welcome = fs::read_to_string(dossier_path.join(WELCOME_FILE_NAME));
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(dossier_configuration.unwrap().raw_documents_paths(), &vec![String::from("./welcome.nmd")]);
+        assert_eq!(welcome.unwrap(), "# Template welcome\n");
+    }
+}
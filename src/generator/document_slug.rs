@@ -0,0 +1,150 @@
+use std::{fmt::Display, str::FromStr};
+use nmd_core::{constants::NMD_EXTENSION, resource::ResourceError};
+
+
+/// Characters which are not allowed in file names on at least one platform
+const INVALID_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Windows reserved file names (case insensitive, also with an extension)
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+const DEFAULT_SLUG: &str = "document";
+
+
+/// How file names of imported documents are built from their titles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlugStrategy {
+
+    /// `What is X?` -> `what-is-x.nmd`
+    #[default]
+    Kebab,
+
+    /// `What is X?` -> `01-what-is-x.nmd`, documents are numbered in order
+    Numbered,
+
+    /// `What is X?` -> `What is X.nmd`, title is kept removing only characters which are invalid in file names
+    Title,
+}
+
+impl SlugStrategy {
+
+    /// File name (with NMD extension) of document `title`, which is the `index`-th (from 1) of `total` documents
+    pub fn file_name(&self, title: &str, index: usize, total: usize) -> String {
+
+        let slug = match self {
+            Self::Kebab => kebab_case(title),
+            Self::Numbered => {
+
+                let width = total.to_string().len().max(2);
+
+                format!("{:0width$}-{}", index, kebab_case(title), width = width)
+            },
+            Self::Title => sanitize_file_name(title),
+        };
+
+        format!("{}.{}", avoid_reserved_name(slug), NMD_EXTENSION)
+    }
+}
+
+impl FromStr for SlugStrategy {
+
+    type Err = ResourceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kebab" => Ok(Self::Kebab),
+            "numbered" => Ok(Self::Numbered),
+            "title" => Ok(Self::Title),
+
+            _ => Err(ResourceError::InvalidResourceVerbose(format!("unknown slug strategy: {}", s))),
+        }
+    }
+}
+
+impl Display for SlugStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Kebab => write!(f, "kebab"),
+            Self::Numbered => write!(f, "numbered"),
+            Self::Title => write!(f, "title"),
+        }
+    }
+}
+
+/// Lowercase alphanumeric words separated by a dash
+fn kebab_case(title: &str) -> String {
+
+    let slug = title.to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .collect::<Vec<&str>>()
+                    .join("-");
+
+    if slug.is_empty() {
+        return DEFAULT_SLUG.to_string()
+    }
+
+    slug
+}
+
+/// Remove characters which are invalid in file names on Windows, Linux or macOS and trailing dots and spaces
+fn sanitize_file_name(title: &str) -> String {
+
+    let name: String = title.chars()
+                            .filter(|c| !c.is_control() && !INVALID_FILE_NAME_CHARS.contains(c))
+                            .collect();
+
+    let name = name.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let name = name.trim_end_matches(['.', ' ']).trim_start_matches('.').to_string();
+
+    if name.is_empty() {
+        return DEFAULT_SLUG.to_string()
+    }
+
+    name
+}
+
+/// Add an underscore to the part of `name` before first dot if it is a Windows reserved name (Windows ignores what follows it),
+/// e.g. `Con. Intro` -> `Con_. Intro`
+fn avoid_reserved_name(name: String) -> String {
+
+    let (stem, rest) = name.split_once('.').map_or((name.as_str(), None), |(stem, rest)| (stem, Some(rest)));
+
+    if !RESERVED_FILE_NAMES.contains(&stem.trim_end().to_lowercase().as_str()) {
+        return name
+    }
+
+    match rest {
+        Some(rest) => format!("{}_.{}", stem, rest),
+        None => format!("{}_", stem),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::SlugStrategy;
+
+
+    #[test]
+    fn file_names_are_valid_on_every_platform() {
+
+        assert_eq!(SlugStrategy::Kebab.file_name("What is X?", 1, 1), "what-is-x.nmd");
+        assert_eq!(SlugStrategy::Numbered.file_name("What is X?", 3, 12), "03-what-is-x.nmd");
+        assert_eq!(SlugStrategy::Title.file_name("What is X?", 1, 1), "What is X.nmd");
+        assert_eq!(SlugStrategy::Title.file_name("A/B: \"C\" ...", 1, 1), "AB C.nmd");
+        assert_eq!(SlugStrategy::Title.file_name("Con", 1, 1), "Con_.nmd");
+        assert_eq!(SlugStrategy::Title.file_name("Con. Intro", 1, 1), "Con_. Intro.nmd");
+        assert_eq!(SlugStrategy::Title.file_name("aux.v2", 1, 1), "aux_.v2.nmd");
+        assert_eq!(SlugStrategy::Kebab.file_name("CON", 1, 1), "con_.nmd");
+        assert_eq!(SlugStrategy::Kebab.file_name("Aux", 1, 1), "aux_.nmd");
+        assert_eq!(SlugStrategy::Kebab.file_name("LPT1", 1, 1), "lpt1_.nmd");
+        assert_eq!(SlugStrategy::Numbered.file_name("Nul", 1, 1), "01-nul.nmd");
+        assert_eq!(SlugStrategy::Kebab.file_name("???", 1, 1), "document.nmd");
+    }
+}
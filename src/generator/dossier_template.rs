@@ -0,0 +1,280 @@
+use std::{fs, path::{Path, PathBuf}};
+use getset::Getters;
+use nmd_core::{constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME}, dossier::dossier_configuration::DossierConfiguration, resource::ResourceError};
+
+use crate::{dossier_manager::document_template::{render_placeholders, TEMPLATES_DIR}, utility::user_configuration_dir};
+
+
+/// Templates shipped with NMD: name and files (path relative to dossier, content)
+const BUILTIN_TEMPLATES: [(&str, &[(&str, &str)]); 4] = [
+    ("report", &[
+        ("nmd.yml", include_str!("dossier_templates/report/nmd.yml")),
+        ("summary.nmd", include_str!("dossier_templates/report/summary.nmd")),
+        ("introduction.nmd", include_str!("dossier_templates/report/introduction.nmd")),
+        ("analysis.nmd", include_str!("dossier_templates/report/analysis.nmd")),
+        ("conclusion.nmd", include_str!("dossier_templates/report/conclusion.nmd")),
+        ("assets/styles/report.css", include_str!("dossier_templates/report/assets/styles/report.css")),
+    ]),
+    ("thesis", &[
+        ("nmd.yml", include_str!("dossier_templates/thesis/nmd.yml")),
+        ("frontispiece.nmd", include_str!("dossier_templates/thesis/frontispiece.nmd")),
+        ("abstract.nmd", include_str!("dossier_templates/thesis/abstract.nmd")),
+        ("introduction.nmd", include_str!("dossier_templates/thesis/introduction.nmd")),
+        ("state-of-the-art.nmd", include_str!("dossier_templates/thesis/state-of-the-art.nmd")),
+        ("methodology.nmd", include_str!("dossier_templates/thesis/methodology.nmd")),
+        ("results.nmd", include_str!("dossier_templates/thesis/results.nmd")),
+        ("conclusion.nmd", include_str!("dossier_templates/thesis/conclusion.nmd")),
+        ("assets/styles/thesis.css", include_str!("dossier_templates/thesis/assets/styles/thesis.css")),
+    ]),
+    ("meeting-notes", &[
+        ("nmd.yml", include_str!("dossier_templates/meeting-notes/nmd.yml")),
+        ("meeting.nmd", include_str!("dossier_templates/meeting-notes/meeting.nmd")),
+    ]),
+    ("book", &[
+        ("nmd.yml", include_str!("dossier_templates/book/nmd.yml")),
+        ("title-page.nmd", include_str!("dossier_templates/book/title-page.nmd")),
+        ("preface.nmd", include_str!("dossier_templates/book/preface.nmd")),
+        ("chapter-1.nmd", include_str!("dossier_templates/book/chapter-1.nmd")),
+        ("chapter-2.nmd", include_str!("dossier_templates/book/chapter-2.nmd")),
+        ("epilogue.nmd", include_str!("dossier_templates/book/epilogue.nmd")),
+        ("assets/styles/book.css", include_str!("dossier_templates/book/assets/styles/book.css")),
+    ]),
+];
+
+
+#[derive(Debug, Clone)]
+enum DossierTemplateSource {
+    Builtin(&'static [(&'static str, &'static str)]),
+
+    /// Local directory, which is copied as it is
+    Dir(PathBuf),
+}
+
+/// Skeleton of a new dossier: dossier configuration and starter documents, which can contain `{{name}}`, `{{author}}`
+/// and `{{date}}` placeholders
+#[derive(Debug, Clone, Getters)]
+pub struct DossierTemplate {
+
+    #[getset(get = "pub")]
+    name: String,
+
+    source: DossierTemplateSource,
+}
+
+impl DossierTemplate {
+
+    /// Load template `template`, which can be the path of a local directory, the name of a directory in `~/.config/nmd/templates/`
+    /// or the name of a built-in template
+    pub fn load(template: &str) -> Result<Self, ResourceError> {
+
+        let mut dirs = vec![PathBuf::from(template)];
+
+        if let Some(dir) = user_configuration_dir() {
+            dirs.push(dir.join(TEMPLATES_DIR).join(template));
+        }
+
+        for dir in dirs {
+            if dir.is_dir() {
+
+                if !dir.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME).is_file() && !dir.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME).is_file() {
+                    return Err(ResourceError::InvalidResourceVerbose(format!("{:?} is not a dossier template, because it has no dossier configuration file", dir)))
+                }
+
+                log::info!("use dossier template {:?}", dir);
+
+                return Ok(Self {
+                    name: template.to_string(),
+                    source: DossierTemplateSource::Dir(dir),
+                })
+            }
+        }
+
+        match BUILTIN_TEMPLATES.iter().find(|(name, _)| name.eq(&template)) {
+            Some((name, files)) => Ok(Self {
+                name: name.to_string(),
+                source: DossierTemplateSource::Builtin(files),
+            }),
+            None => Err(ResourceError::ResourceNotFound(format!("dossier template '{}' (available: {})", template, Self::builtin_templates().join(", ")))),
+        }
+    }
+
+    pub fn builtin_templates() -> Vec<&'static str> {
+        BUILTIN_TEMPLATES.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Write template files in `dossier_path` replacing placeholders with `value`. Files which are not text are copied as they are.
+    ///
+    /// In dossier configuration files, placeholders must be in double quoted strings, because their values are escaped.
+    /// Nothing is written if a file already exists or if dossier configuration is invalid
+    pub fn apply(&self, dossier_path: &Path, value: impl Fn(&str) -> Option<String>) -> Result<(), ResourceError> {
+
+        let files = self.render(&value)?;
+
+        let existing_files: Vec<String> = files.iter()
+                                            .filter(|(path, _)| dossier_path.join(path).exists())
+                                            .map(|(path, _)| path.to_string_lossy().to_string())
+                                            .collect();
+
+        if !existing_files.is_empty() {
+            return Err(ResourceError::InvalidResourceVerbose(format!("dossier template '{}' can not be applied, because these files already exist: {}", self.name, existing_files.join(", "))))
+        }
+
+        for (path, content) in files.iter() {
+            Self::check_configuration(path, content)
+                .map_err(|err| ResourceError::InvalidResourceVerbose(format!("dossier template '{}' has an invalid dossier configuration: {}", self.name, err)))?;
+        }
+
+        for (path, content) in files {
+            write_file(&dossier_path.join(path), &content)?;
+        }
+
+        log::info!("applied dossier template '{}'", self.name);
+
+        Ok(())
+    }
+
+    /// Template files (path relative to dossier, content) with placeholders replaced
+    fn render(&self, value: &impl Fn(&str) -> Option<String>) -> Result<Vec<(PathBuf, Vec<u8>)>, ResourceError> {
+
+        // values are escaped as JSON strings, which are valid also in YAML double quoted strings
+        let escaped_value = |placeholder: &str| {
+            value(placeholder).map(|value| {
+                let escaped = serde_json::Value::String(value).to_string();
+
+                escaped[1..escaped.len() - 1].to_string()
+            })
+        };
+
+        let render_file = |path: &Path, text: &str| {
+            if is_configuration_file(path) {
+                render_placeholders(text, escaped_value)
+            } else {
+                render_placeholders(text, value)
+            }
+        };
+
+        let mut files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+
+        match &self.source {
+            DossierTemplateSource::Builtin(builtin_files) => {
+                for (path, content) in builtin_files.iter() {
+
+                    let path = PathBuf::from(path);
+
+                    let content = render_file(&path, content).into_bytes();
+
+                    files.push((path, content));
+                }
+            },
+            DossierTemplateSource::Dir(dir) => {
+                for path in files_in_dir(dir)? {
+
+                    let content = fs::read(&path)?;
+
+                    let path = path.strip_prefix(dir).unwrap().to_path_buf();
+
+                    let content = match String::from_utf8(content) {
+                        Ok(text) => render_file(&path, &text).into_bytes(),
+                        Err(err) => err.into_bytes(),
+                    };
+
+                    files.push((path, content));
+                }
+            },
+        }
+
+        Ok(files)
+    }
+
+    /// Check that `content` of file in `path` is a valid dossier configuration, if it is a dossier configuration file
+    fn check_configuration(path: &Path, content: &[u8]) -> Result<(), String> {
+
+        if path.eq(Path::new(DOSSIER_CONFIGURATION_YAML_FILE_NAME)) {
+            serde_yaml::from_slice::<DossierConfiguration>(content).map_err(|err| err.to_string())?;
+        }
+
+        if path.eq(Path::new(DOSSIER_CONFIGURATION_JSON_FILE_NAME)) {
+            serde_json::from_slice::<DossierConfiguration>(content).map_err(|err| err.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_configuration_file(path: &Path) -> bool {
+    path.eq(Path::new(DOSSIER_CONFIGURATION_YAML_FILE_NAME)) || path.eq(Path::new(DOSSIER_CONFIGURATION_JSON_FILE_NAME))
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), ResourceError> {
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, content)?;
+
+    Ok(())
+}
+
+/// Files of `dir` and its subdirectories, `.git` directory excluded
+fn files_in_dir(dir: &Path) -> Result<Vec<PathBuf>, ResourceError> {
+
+    let mut files: Vec<PathBuf> = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+
+        let path = entry?.path();
+
+        if path.is_dir() {
+
+            if !path.ends_with(".git") {
+                files.extend(files_in_dir(&path)?);
+            }
+
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+
+#[cfg(test)]
+mod test {
+    use nmd_core::dossier::dossier_configuration::DossierConfiguration;
+
+    use super::{DossierTemplate, BUILTIN_TEMPLATES};
+
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        for (name, files) in BUILTIN_TEMPLATES {
+
+            let (_, configuration) = files.iter().find(|(path, _)| path.eq(&"nmd.yml")).unwrap();
+
+            let configuration: DossierConfiguration = serde_yaml::from_str(configuration).unwrap_or_else(|err| panic!("invalid '{}' template: {}", name, err));
+
+            for document in configuration.raw_documents_paths() {
+                assert!(files.iter().any(|(path, _)| format!("./{}", path).eq(document)), "document {} of '{}' template not found", document, name);
+            }
+        }
+    }
+
+    #[test]
+    fn placeholders_are_escaped_in_dossier_configuration() {
+
+        let template = DossierTemplate::load("report").unwrap();
+
+        let files = template.render(&|placeholder: &str| match placeholder {
+            "name" => Some(String::from(r#"Q3: "Final" report \ draft"#)),
+            _ => Some(String::new()),
+        }).unwrap();
+
+        let (_, configuration) = files.iter().find(|(path, _)| path.ends_with("nmd.yml")).unwrap();
+
+        let configuration: DossierConfiguration = serde_yaml::from_slice(configuration).unwrap();
+
+        assert_eq!(configuration.name(), r#"Q3: "Final" report \ draft"#);
+    }
+}
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use getset::{CopyGetters, Getters, Setters};
 
+use super::document_slug::SlugStrategy;
+
 
 #[derive(Debug, Clone, Getters, CopyGetters, Setters)]
 pub struct GeneratorConfiguration {
@@ -23,6 +25,22 @@ pub struct GeneratorConfiguration {
 
     #[getset(get_copy = "pub", set = "pub")]
     evaluate_existing_files: bool,
+
+    /// Imported Markdown is split in a document for each heading whose level is up to this one, `None` to not split it
+    #[getset(get_copy = "pub", set = "pub")]
+    split_level: Option<usize>,
+
+    /// How file names of imported documents are built
+    #[getset(get_copy = "pub", set = "pub")]
+    slug_strategy: SlugStrategy,
+
+    /// Dossier template (built-in template name or local directory)
+    #[getset(get = "pub", set = "pub")]
+    template: Option<String>,
+
+    /// Author, used to fill dossier template
+    #[getset(get = "pub", set = "pub")]
+    author: Option<String>,
 }
 
 
@@ -35,6 +53,10 @@ impl GeneratorConfiguration {
             welcome,
             gitkeep,
             evaluate_existing_files,
+            split_level: Some(1),
+            slug_strategy: SlugStrategy::default(),
+            template: None,
+            author: None,
         }
     }
 
@@ -48,7 +70,11 @@ impl Default for GeneratorConfiguration {
             force_generation: false,
             welcome: false,
             gitkeep: false,
-            evaluate_existing_files: true
+            evaluate_existing_files: true,
+            split_level: Some(1),
+            slug_strategy: SlugStrategy::default(),
+            template: None,
+            author: None,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,337 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+use getset::Getters;
+use nmd_core::resource::ResourceError;
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use serde_yaml::Value;
+
+use crate::utility::normalize_path;
+
+
+/// mdBook table of contents
+pub const MDBOOK_SUMMARY_FILE_NAME: &str = "SUMMARY.md";
+
+/// mdBook configuration, used for book title
+pub const MDBOOK_CONFIGURATION_FILE_NAME: &str = "book.toml";
+
+/// MkDocs configuration, used for navigation, documents directory and site name
+pub const MKDOCS_CONFIGURATION_FILE_NAME: &str = "mkdocs.yml";
+
+const MKDOCS_DEFAULT_DOCS_DIR: &str = "docs";
+
+const MDBOOK_DEFAULT_SRC_DIR: &str = "src";
+
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Files which are the first page of their directory
+const INDEX_FILE_STEMS: [&str; 2] = ["index", "readme"];
+
+static SUMMARY_LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(?:[-*+]\s+)?\[([^\]]*)\]\(([^)]*)\)").unwrap());
+
+/// Links to Markdown pages in NMD content, with optional anchor
+static PAGE_LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\]\(([^)\s#:]+\.(?:md|markdown))(?:#([^)\s]*))?\)").unwrap());
+
+static HTML_ENTITY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"&#(\d+);").unwrap());
+
+
+/// Markdown page of a book
+#[derive(Debug, Clone, Getters)]
+pub struct MarkdownPage {
+
+    /// Normalized path of page
+    #[getset(get = "pub")]
+    path: PathBuf,
+
+    /// Title in navigation (if any)
+    #[getset(get = "pub")]
+    title: Option<String>,
+}
+
+/// Markdown pages of a directory in reading order. Order is taken from mdBook `SUMMARY.md`, from MkDocs `mkdocs.yml` navigation
+/// or, as fallback, from directory tree (alphabetical, index pages first)
+#[derive(Debug, Clone, Getters)]
+pub struct MarkdownBook {
+
+    /// Book title (mdBook title or MkDocs site name)
+    #[getset(get = "pub")]
+    title: Option<String>,
+
+    #[getset(get = "pub")]
+    pages: Vec<MarkdownPage>,
+}
+
+impl MarkdownBook {
+
+    pub fn load(dir: &Path) -> Result<Self, ResourceError> {
+
+        if !dir.is_dir() {
+            return Err(ResourceError::InvalidResourceVerbose(format!("{:?} is not a directory", dir)))
+        }
+
+        let mdbook_title = Self::mdbook_title(dir);
+
+        for summary in [dir.join(MDBOOK_SUMMARY_FILE_NAME), dir.join(MDBOOK_DEFAULT_SRC_DIR).join(MDBOOK_SUMMARY_FILE_NAME)] {
+            if summary.is_file() {
+
+                log::info!("pages order is taken from {:?}", summary);
+
+                return Ok(Self {
+                    title: mdbook_title,
+                    pages: Self::pages_from_summary(&summary)?,
+                })
+            }
+        }
+
+        let mkdocs_configuration = dir.join(MKDOCS_CONFIGURATION_FILE_NAME);
+
+        if mkdocs_configuration.is_file() {
+
+            log::info!("pages order is taken from {:?}", mkdocs_configuration);
+
+            return Self::from_mkdocs(&mkdocs_configuration)
+        }
+
+        log::info!("pages order is taken from directory tree");
+
+        Ok(Self {
+            title: mdbook_title,
+            pages: Self::pages_in_dir(dir)?,
+        })
+    }
+
+    fn mdbook_title(dir: &Path) -> Option<String> {
+
+        let content = fs::read_to_string(dir.join(MDBOOK_CONFIGURATION_FILE_NAME)).ok()?;
+
+        let configuration: toml::Table = toml::from_str(&content).ok()?;
+
+        configuration.get("book")?.get("title")?.as_str().map(String::from)
+    }
+
+    fn pages_from_summary(summary: &Path) -> Result<Vec<MarkdownPage>, ResourceError> {
+
+        let base_dir = summary.parent().unwrap_or(Path::new("."));
+
+        let content = fs::read_to_string(summary)?;
+
+        let mut pages: Vec<MarkdownPage> = Vec::new();
+
+        for line in content.lines() {
+            if let Some(captures) = SUMMARY_LINK_REGEX.captures(line) {
+
+                // draft chapters have no file
+                if captures[2].trim().is_empty() {
+                    continue
+                }
+
+                push_page(&mut pages, &base_dir.join(decode_path(&captures[2])), Some(captures[1].to_string()));
+            }
+        }
+
+        Ok(pages)
+    }
+
+    fn from_mkdocs(mkdocs_configuration: &Path) -> Result<Self, ResourceError> {
+
+        let content = fs::read_to_string(mkdocs_configuration)?;
+
+        // MkDocs configuration can have custom tags (e.g. `!!python/name`), so it is read as generic YAML
+        let configuration: Value = serde_yaml::from_str(&content).map_err(|err| ResourceError::InvalidResourceVerbose(format!("invalid {}: {}", MKDOCS_CONFIGURATION_FILE_NAME, err)))?;
+
+        let base_dir = mkdocs_configuration.parent().unwrap_or(Path::new("."));
+
+        let docs_dir = base_dir.join(configuration.get("docs_dir").and_then(Value::as_str).unwrap_or(MKDOCS_DEFAULT_DOCS_DIR));
+
+        let pages = match configuration.get("nav") {
+            Some(nav) => {
+
+                let mut pages: Vec<MarkdownPage> = Vec::new();
+
+                collect_nav_pages(nav, None, &docs_dir, &mut pages);
+
+                pages
+            },
+            None => Self::pages_in_dir(&docs_dir)?,
+        };
+
+        Ok(Self {
+            title: configuration.get("site_name").and_then(Value::as_str).map(String::from),
+            pages,
+        })
+    }
+
+    fn pages_in_dir(dir: &Path) -> Result<Vec<MarkdownPage>, ResourceError> {
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut dirs: Vec<PathBuf> = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+
+            let path = entry?.path();
+
+            if path.file_name().is_some_and(|file_name| file_name.to_string_lossy().starts_with('.')) {
+                continue
+            }
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else if is_markdown_file(&path) && !path.ends_with(MDBOOK_SUMMARY_FILE_NAME) {
+                files.push(path);
+            }
+        }
+
+        files.sort_by_key(|path| {
+
+            let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+
+            (!INDEX_FILE_STEMS.contains(&stem.as_str()), path.clone())
+        });
+
+        dirs.sort();
+
+        let mut pages: Vec<MarkdownPage> = Vec::new();
+
+        for file in files {
+            push_page(&mut pages, &file, None);
+        }
+
+        for dir in dirs {
+            pages.extend(Self::pages_in_dir(&dir)?);
+        }
+
+        Ok(pages)
+    }
+}
+
+fn collect_nav_pages(nav: &Value, title: Option<String>, docs_dir: &Path, pages: &mut Vec<MarkdownPage>) {
+    match nav {
+        // external links are not pages
+        Value::String(path) if !path.contains("://") => push_page(pages, &docs_dir.join(decode_path(path)), title),
+        Value::Sequence(items) => {
+            for item in items {
+                collect_nav_pages(item, None, docs_dir, pages);
+            }
+        },
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                collect_nav_pages(value, key.as_str().map(String::from), docs_dir, pages);
+            }
+        },
+        _ => (),
+    }
+}
+
+fn push_page(pages: &mut Vec<MarkdownPage>, path: &Path, title: Option<String>) {
+
+    if !path.is_file() {
+
+        log::warn!("page {:?} not found, it is skipped", path);
+
+        return
+    }
+
+    let path = normalize_path(path);
+
+    if pages.iter().any(|page| page.path.eq(&path)) {
+        return
+    }
+
+    pages.push(MarkdownPage { path, title });
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| MARKDOWN_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()))
+}
+
+fn decode_path(path: &str) -> String {
+    percent_decode_str(path.trim()).decode_utf8_lossy().to_string()
+}
+
+
+/// Rewrite links to Markdown pages of `content` (NMD content of page in `page_path`) as NMD references to documents.
+/// `documents` contains, for each page path, document file name and its headings (NMD).
+/// Links to a page point to its first heading, links with anchor point to heading which has the same slug
+pub fn rewrite_page_links(content: &str, page_path: &Path, documents: &HashMap<PathBuf, (String, Vec<String>)>) -> String {
+
+    let page_dir = page_path.parent().unwrap_or(Path::new("."));
+
+    PAGE_LINK_REGEX.replace_all(content, |captures: &Captures| {
+
+        let target = normalize_path(&page_dir.join(decode_path(&captures[1])));
+
+        let Some((document_file_name, headings)) = documents.get(&target) else {
+
+            log::warn!("link to {:?} in {:?} is kept as it is, because page is not imported", target, page_path);
+
+            return captures[0].to_string()
+        };
+
+        let heading = match captures.get(2) {
+            Some(anchor) => headings.iter().find(|heading| markdown_slug(&unescape_entities(heading)).eq(&markdown_slug(anchor.as_str()))),
+            None => headings.first(),
+        };
+
+        match heading {
+            Some(heading) => format!("]({}#{})", document_file_name, nmd_id(heading)),
+            None => {
+
+                log::warn!("link to {:?} in {:?} is kept as it is, because target heading is not found", target, page_path);
+
+                captures[0].to_string()
+            }
+        }
+
+    }).to_string()
+}
+
+/// Anchor slug of a heading like GitHub, mdBook and MkDocs build it (e.g. `What is X?` -> `what-is-x`)
+fn markdown_slug(heading: &str) -> String {
+    heading.trim().to_lowercase().chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Value of an NMD internal reference which matches id of `heading`, i.e. its lowercase text whose non-alphanumeric characters are dashes
+fn nmd_id(heading: &str) -> String {
+    heading.to_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).collect()
+}
+
+fn unescape_entities(text: &str) -> String {
+    HTML_ENTITY_REGEX.replace_all(text, |captures: &Captures| {
+        captures[1].parse::<u32>().ok().and_then(char::from_u32).map(String::from).unwrap_or(captures[0].to_string())
+    }).replace("&amp;", "&")
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{markdown_slug, rewrite_page_links};
+
+
+    #[test]
+    fn markdown_slugs() {
+        assert_eq!(markdown_slug("What is X?"), "what-is-x");
+        assert_eq!(markdown_slug("Install `nmd` & run"), "install-nmd--run");
+    }
+
+    #[test]
+    fn page_links_are_rewritten() {
+
+        let page = PathBuf::from("/book/guide/index.md");
+
+        let documents: HashMap<PathBuf, (String, Vec<String>)> = HashMap::from([
+            (PathBuf::from("/book/guide/install.md"), (String::from("install.nmd"), vec![String::from("Install"), String::from("On snake&#95;case OS")])),
+        ]);
+
+        let content = "See [install](install.md), [os](./install.md#on-snake_case-os), [missing](other.md) and [site](https://example.com/a.md).\n";
+
+        assert_eq!(
+            rewrite_page_links(content, &page, &documents),
+            "See [install](install.nmd#install), [os](install.nmd#on-snake--95-case-os), [missing](other.md) and [site](https://example.com/a.md).\n"
+        );
+    }
+}
@@ -0,0 +1,1039 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, str::FromStr};
+use getset::{Getters, Setters};
+use nmd_core::{dossier::{self, dossier_configuration::DossierConfiguration}, resource::ResourceError, theme::Theme};
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use serde_yaml::{Mapping, Value};
+
+
+static LINK_DEFINITION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^ {0,3}\[([^\]^][^\]]*)\]:\s*<?([^\s>]+)>?(?:\s+(?:"[^"]*"|'[^']*'|\([^)]*\)))?\s*$"#).unwrap());
+static FOOTNOTE_DEFINITION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}\[\^([^\]]+)\]:\s?(.*)$").unwrap());
+static FENCE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(`{3,}|~{3,})\s*([^`\s]*)").unwrap());
+static ATX_HEADING_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))??(?:[ \t]+#+)?[ \t]*$").unwrap());
+static THEMATIC_BREAK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(?:(?:-[ \t]*){3,}|(?:\*[ \t]*){3,}|(?:_[ \t]*){3,})$").unwrap());
+static SETEXT_UNDERLINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").unwrap());
+static LIST_ITEM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)([-*+]|\d{1,9}[.)])(?:[ \t]+(.*))?$").unwrap());
+static TASK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[([ xX])\](?:[ \t]+(.*))?$").unwrap());
+static TABLE_DELIMITER_ROW_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)*\|?\s*$").unwrap());
+static BLOCK_QUOTE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)>[ ]?(.*)$").unwrap());
+static HTML_BLOCK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}</?[a-zA-Z][\w-]*(?:\s|/?>|$)").unwrap());
+static HTML_COMMENT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static FOOTNOTE_REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\^([^\]\s]+)\]").unwrap());
+static REFERENCE_LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(!?)\[([^\[\]]+)\](?:\[([^\[\]]*)\])?").unwrap());
+static IMAGE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"!\[([^\]]*)\]\(\s*<?([^)\s>]+)>?(?:\s+(?:"[^"]*"|'[^']*'|\([^)]*\)))?\s*\)"#).unwrap());
+static LINK_WITH_TITLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\[([^\]]*)\]\(\s*<?([^)\s>]+)>?\s+(?:"[^"]*"|'[^']*')\s*\)"#).unwrap());
+static AUTOLINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<((?:https?|ftp)://[^>\s]+|mailto:[^>\s]+|[^@>\s]+@[^>\s]+\.[^>\s]+)>").unwrap());
+static PROTECTED_SPAN_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(`+)[^`]*?(`+)|\$[^$\n]+\$|\]\([^)]*\)|</?[a-zA-Z][^>]*>").unwrap());
+static GREEK_LETTER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"%(\w*)%").unwrap());
+static TEXT_REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"&([\w-]+)&").unwrap());
+static INTRAWORD_UNDERSCORE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"([[:alnum:]])_([[:alnum:]])").unwrap());
+static NMD_HEADING_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6}) (.+)$").unwrap());
+
+/// List item contents which NMD would load as a bullet (e.g. a table cell with only a number)
+static BULLET_LIKE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:\d[.)]?|[a-zA-Z]{1,8}[.)]|[-*+|]|->|--|&[^;]+;)$").unwrap());
+
+const REMOTE_SRC_PREFIXES: [&str; 4] = ["http://", "https://", "data:", "//"];
+
+const FOOTNOTE_ID_PREFIX: &str = "footnote-";
+
+
+/// Section of imported Markdown file, i.e. content of a heading whose level is up to split level
+#[derive(Debug, Clone, Getters)]
+pub struct ImportedSection {
+
+    /// Heading text, `None` if Markdown file has no heading up to split level
+    #[getset(get = "pub")]
+    title: Option<String>,
+
+    /// NMD content, footnotes referenced for the first time in section included
+    #[getset(get = "pub")]
+    content: String,
+}
+
+/// Markdown file converted in NMD
+#[derive(Debug, Clone, Getters)]
+pub struct MarkdownImport {
+
+    /// Front matter (YAML) of Markdown file
+    #[getset(get = "pub")]
+    front_matter: Option<Mapping>,
+
+    /// Whole NMD content, without footnotes
+    #[getset(get = "pub")]
+    content: String,
+
+    /// Footnotes texts (NMD), by number
+    #[getset(get = "pub")]
+    footnotes: Vec<String>,
+
+    /// Local images referenced for the first time by Markdown file (source path, file name in dossier images directory)
+    #[getset(get = "pub")]
+    images: Vec<(PathBuf, String)>,
+}
+
+impl MarkdownImport {
+
+    /// Whole content as a single document, with its footnotes
+    pub fn document(&self) -> String {
+
+        let numbers: Vec<usize> = (1..=self.footnotes.len()).collect();
+
+        let mut document = self.content.clone();
+
+        self.push_footnotes(&mut document, &numbers);
+
+        document
+    }
+
+    /// Texts of headings (NMD), in order
+    pub fn headings(&self) -> Vec<String> {
+
+        let mut in_code_block = false;
+
+        self.content.lines().filter_map(|line| {
+
+            if line.starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+
+            if in_code_block {
+                return None
+            }
+
+            NMD_HEADING_REGEX.captures(line).map(|captures| captures[2].trim().to_string())
+
+        }).collect()
+    }
+
+    fn push_footnotes(&self, content: &mut String, numbers: &[usize]) {
+
+        if numbers.is_empty() {
+            return
+        }
+
+        content.push_str("\n---\n");
+
+        // each footnote is a single row table, because NMD renders table identifiers as ids which footnote references can link
+        for n in numbers {
+
+            let footnote = self.footnotes[n - 1].replace('|', "&#124;");
+
+            content.push_str(&format!("\n|:---|:---|\n|{}|{}|\n#{}{}\n", nmd_table_cell(&format!("({})", n)), nmd_table_cell(&footnote), FOOTNOTE_ID_PREFIX, n));
+        }
+    }
+
+    /// Split content in sections, one per heading whose level is up to `split_level` (e.g. `2` splits at level 1 and 2 headings).
+    /// Content before first heading is part of first section
+    pub fn sections(&self, split_level: usize) -> Vec<ImportedSection> {
+
+        let mut sections: Vec<ImportedSection> = Vec::new();
+
+        let mut current_title: Option<String> = None;
+        let mut current_content = String::new();
+
+        let mut in_code_block = false;
+
+        for line in self.content.lines() {
+
+            if line.starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+
+            if !in_code_block {
+                if let Some(captures) = NMD_HEADING_REGEX.captures(line).filter(|captures| captures[1].len() <= split_level) {
+
+                    if current_title.is_some() {
+                        sections.push(ImportedSection { title: current_title.take(), content: std::mem::take(&mut current_content) });
+                    }
+
+                    current_title = Some(captures[2].trim().to_string());
+                }
+            }
+
+            current_content.push_str(line);
+            current_content.push('\n');
+        }
+
+        if current_title.is_some() || !current_content.trim().is_empty() {
+            sections.push(ImportedSection { title: current_title, content: current_content });
+        }
+
+        // each footnote is placed in the section where it is referenced for the first time
+        let mut placed = vec![false; self.footnotes.len()];
+
+        for section in &mut sections {
+
+            let mut numbers: Vec<usize> = Vec::new();
+
+            for (index, footnote_placed) in placed.iter_mut().enumerate() {
+                if !*footnote_placed && section.content.contains(&format!("(#{}{})", FOOTNOTE_ID_PREFIX, index + 1)) {
+                    *footnote_placed = true;
+                    numbers.push(index + 1);
+                }
+            }
+
+            self.push_footnotes(&mut section.content, &numbers);
+        }
+
+        sections
+    }
+
+    /// Copy local images in `images_dir` (usually dossier `assets/images`)
+    pub fn copy_images(&self, images_dir: &Path) -> Result<(), ResourceError> {
+
+        for (source, file_name) in &self.images {
+
+            fs::copy(source, images_dir.join(file_name))?;
+
+            log::info!("copied image {:?} in {}/{}", source, dossier::ASSETS_DIR, dossier::IMAGES_DIR);
+        }
+
+        Ok(())
+    }
+
+    /// Apply front matter to dossier configuration: `title` (or `name`), `theme`, `toc` (boolean) and `references` are supported
+    pub fn apply_front_matter(&self, dossier_configuration: DossierConfiguration, override_name: bool) -> Result<DossierConfiguration, ResourceError> {
+
+        let Some(front_matter) = &self.front_matter else {
+            return Ok(dossier_configuration)
+        };
+
+        let mut value = serde_yaml::to_value(&dossier_configuration).map_err(|err| ResourceError::ElaborationError(err.to_string()))?;
+
+        for (key, front_matter_value) in front_matter {
+
+            let key = key.as_str().unwrap_or_default();
+
+            match (key, front_matter_value) {
+                ("title" | "name", Value::String(name)) => {
+                    if override_name {
+                        value["name"] = Value::String(name.clone());
+                    }
+                },
+                ("theme", Value::String(theme)) => {
+                    match Theme::from_str(theme) {
+                        Ok(theme) => value["style"]["theme"] = serde_yaml::to_value(theme).map_err(|err| ResourceError::ElaborationError(err.to_string()))?,
+                        Err(err) => log::warn!("front matter theme is ignored: {}", err),
+                    }
+                },
+                ("toc", Value::Bool(include)) => value["toc"]["include_in_output"] = Value::Bool(*include),
+                ("references", Value::Mapping(references)) => value["references"] = Value::Mapping(references.clone()),
+                _ => log::warn!("front matter key '{}' has no NMD equivalent, it is ignored", key),
+            }
+        }
+
+        serde_yaml::from_value(value).map_err(|err| ResourceError::ElaborationError(err.to_string()))
+    }
+}
+
+
+/// Converter of CommonMark/GFM content in NMD. Constructs which NMD has too are kept (e.g. emphasis, lists, quotes, GFM alerts),
+/// while the others are converted:
+///
+/// - setext headings, `~~~` and indented code blocks
+/// - tables (outer pipes and alignments) and task lists
+/// - reference-style links and images, link titles and autolinks
+/// - footnotes (links to a list of notes)
+/// - inline HTML comments (dropped)
+/// - text which would be NMD syntax (e.g. `~`, `^`, `==`, `snake_case`)
+///
+/// Local images are collected, so they can be copied in dossier. An importer can be used for more Markdown files,
+/// images file names are unique across all of them
+#[derive(Debug, Setters)]
+pub struct MarkdownImporter {
+
+    /// Directory of Markdown file, used to resolve images
+    #[getset(set = "pub")]
+    source_dir: PathBuf,
+
+    link_definitions: HashMap<String, String>,
+
+    footnote_definitions: HashMap<String, String>,
+
+    footnote_numbers: HashMap<String, usize>,
+
+    images: Vec<(PathBuf, String)>,
+}
+
+impl MarkdownImporter {
+
+    pub fn new(source_dir: PathBuf) -> Self {
+        Self {
+            source_dir,
+            link_definitions: HashMap::new(),
+            footnote_definitions: HashMap::new(),
+            footnote_numbers: HashMap::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn import(&mut self, markdown: &str) -> MarkdownImport {
+
+        self.link_definitions.clear();
+        self.footnote_definitions.clear();
+        self.footnote_numbers.clear();
+
+        let already_imported_images = self.images.len();
+
+        let (front_matter, body) = split_front_matter(markdown);
+
+        let lines = self.collect_definitions(body);
+
+        let content = self.convert_blocks(&lines);
+
+        let mut footnotes: Vec<(usize, String)> = self.footnote_numbers.iter()
+                                                    .map(|(label, n)| (*n, self.footnote_definitions.get(label).cloned().unwrap_or_default()))
+                                                    .collect();
+
+        footnotes.sort_by_key(|(n, _)| *n);
+
+        let footnotes = footnotes.into_iter().map(|(_, text)| self.convert_inline(&text)).collect();
+
+        MarkdownImport {
+            front_matter,
+            content,
+            footnotes,
+            images: self.images[already_imported_images..].to_vec(),
+        }
+    }
+
+    /// Collect (and remove) link and footnote definitions, they can be anywhere in Markdown file
+    fn collect_definitions<'a>(&mut self, body: &'a str) -> Vec<&'a str> {
+
+        let mut lines: Vec<&str> = Vec::new();
+
+        let mut fence: Option<String> = None;
+        let mut current_footnote: Option<String> = None;
+
+        for line in body.lines() {
+
+            if let Some(open_fence) = &fence {
+
+                if is_closing_fence(line, open_fence) {
+                    fence = None;
+                }
+
+                lines.push(line);
+
+                continue
+            }
+
+            if let Some(label) = &current_footnote {
+
+                // footnote continuation lines are indented
+                if line.starts_with("    ") || line.starts_with('\t') {
+
+                    let definition = self.footnote_definitions.get_mut(label).unwrap();
+
+                    definition.push(' ');
+                    definition.push_str(line.trim());
+
+                    continue
+                }
+
+                if !line.trim().is_empty() {
+                    current_footnote = None;
+                }
+            }
+
+            if let Some(captures) = FENCE_REGEX.captures(line) {
+
+                fence = Some(captures[1].to_string());
+
+                lines.push(line);
+
+                continue
+            }
+
+            if let Some(captures) = FOOTNOTE_DEFINITION_REGEX.captures(line) {
+
+                let label = captures[1].to_string();
+
+                self.footnote_definitions.insert(label.clone(), captures[2].trim().to_string());
+
+                current_footnote = Some(label);
+
+                continue
+            }
+
+            if let Some(captures) = LINK_DEFINITION_REGEX.captures(line) {
+
+                self.link_definitions.entry(normalize_label(&captures[1])).or_insert(captures[2].to_string());
+
+                continue
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn convert_blocks(&mut self, lines: &[&str]) -> String {
+
+        let mut output: Vec<String> = Vec::new();
+
+        let mut fence: Option<String> = None;
+        let mut in_indented_code = false;
+        let mut pending_blank_lines: usize = 0;
+        let mut in_html_comment = false;
+        let mut in_html_block = false;
+        let mut in_list = false;
+        let mut previous_is_paragraph = false;
+
+        let mut index = 0;
+
+        while index < lines.len() {
+
+            let line = lines[index];
+
+            index += 1;
+
+            if let Some(open_fence) = &fence {
+
+                if is_closing_fence(line, open_fence) {
+                    fence = None;
+                    output.push(String::from("```"));
+                } else {
+                    output.push(line.to_string());
+                }
+
+                continue
+            }
+
+            if in_indented_code {
+
+                if line.trim().is_empty() {
+                    pending_blank_lines += 1;
+
+                    continue
+                }
+
+                if let Some(code) = strip_indentation(line) {
+
+                    output.extend(std::iter::repeat_n(String::new(), pending_blank_lines));
+                    output.push(code.to_string());
+
+                    pending_blank_lines = 0;
+
+                    continue
+                }
+
+                output.push(String::from("```"));
+                output.extend(std::iter::repeat_n(String::new(), pending_blank_lines));
+
+                in_indented_code = false;
+                pending_blank_lines = 0;
+            }
+
+            if in_html_comment {
+
+                in_html_comment = !line.contains("-->");
+
+                output.push(line.to_string());
+
+                continue
+            }
+
+            if line.trim().is_empty() {
+
+                in_html_block = false;
+                previous_is_paragraph = false;
+
+                output.push(String::new());
+
+                continue
+            }
+
+            if in_html_block {
+
+                output.push(line.to_string());
+
+                continue
+            }
+
+            let is_paragraph_continuation = previous_is_paragraph;
+            previous_is_paragraph = false;
+
+            if let Some(captures) = FENCE_REGEX.captures(line) {
+
+                fence = Some(captures[1].to_string());
+                in_list = false;
+
+                output.push(format!("```{}", &captures[2]));
+
+                continue
+            }
+
+            if line.trim_start().starts_with("<!--") {
+
+                in_html_comment = !HTML_COMMENT_REGEX.is_match(line);
+
+                output.push(line.trim_start().to_string());
+
+                continue
+            }
+
+            if !in_list && output.last().is_none_or(|last| last.is_empty()) {
+                if let Some(code) = strip_indentation(line) {
+
+                    in_indented_code = true;
+
+                    output.push(String::from("```"));
+                    output.push(code.to_string());
+
+                    continue
+                }
+            }
+
+            if is_paragraph_continuation {
+                if let Some(captures) = SETEXT_UNDERLINE_REGEX.captures(line) {
+
+                    let level = if captures[1].starts_with('=') { "#" } else { "##" };
+
+                    let text = output.pop().unwrap_or_default();
+
+                    output.push(format!("{} {}", level, text.trim()));
+
+                    continue
+                }
+            }
+
+            if THEMATIC_BREAK_REGEX.is_match(line) {
+
+                in_list = false;
+
+                output.push(String::from("---"));
+
+                continue
+            }
+
+            if let Some(captures) = ATX_HEADING_REGEX.captures(line) {
+
+                in_list = false;
+
+                let text = captures.get(2).map_or("", |text| text.as_str()).trim();
+
+                // empty headings would be page breaks in NMD
+                if !text.is_empty() {
+                    output.push(format!("{} {}", &captures[1], self.convert_inline(text)));
+                }
+
+                continue
+            }
+
+            if let Some(captures) = LIST_ITEM_REGEX.captures(line) {
+
+                in_list = true;
+
+                output.push(self.convert_list_item(&captures[1], &captures[2], captures.get(3).map_or("", |content| content.as_str())));
+
+                continue
+            }
+
+            if line.contains('|') && index < lines.len() && TABLE_DELIMITER_ROW_REGEX.is_match(lines[index]) {
+
+                in_list = false;
+
+                let alignments: Vec<String> = split_table_row(lines[index]).into_iter().map(|cell| table_alignment(cell.trim())).collect();
+
+                output.push(self.convert_table_row(line));
+                output.push(format!("|{}|", alignments.join("|")));
+
+                index += 1;
+
+                while index < lines.len() && !lines[index].trim().is_empty() && lines[index].contains('|') {
+
+                    output.push(self.convert_table_row(lines[index]));
+
+                    index += 1;
+                }
+
+                continue
+            }
+
+            if let Some(captures) = BLOCK_QUOTE_REGEX.captures(line) {
+
+                in_list = false;
+
+                output.push(format!("{}> {}", &captures[1], self.convert_inline(&captures[2])));
+
+                continue
+            }
+
+            if HTML_BLOCK_REGEX.is_match(line) {
+
+                in_html_block = true;
+
+                output.push(line.to_string());
+
+                continue
+            }
+
+            if !line.starts_with(' ') && !line.starts_with('\t') {
+                in_list = false;
+            }
+
+            previous_is_paragraph = true;
+
+            let mut text = self.convert_inline(line);
+
+            // it would be an NMD comment
+            if text.starts_with("//") {
+                text.replace_range(..1, "&#47;");
+            }
+
+            output.push(text);
+        }
+
+        if in_indented_code || fence.is_some() {
+            output.push(String::from("```"));
+        }
+
+        let mut content = output.join("\n");
+        content.push('\n');
+
+        content
+    }
+
+    fn convert_list_item(&mut self, indentation: &str, marker: &str, content: &str) -> String {
+
+        if let Some(captures) = TASK_REGEX.captures(content) {
+
+            let checkbox = if captures[1].eq(" ") { "-[ ]" } else { "-[x]" };
+
+            return format!("{}{} {}", indentation, checkbox, self.convert_inline(captures.get(2).map_or("", |content| content.as_str())))
+        }
+
+        let content = self.convert_inline(content);
+
+        // NMD numbered bullets have a single digit, so longer numbers are kept in content using an invisible bullet
+        if marker.len() > 2 && marker.starts_with(|c: char| c.is_ascii_digit()) {
+            return format!("{}| {} {}", indentation, marker, content)
+        }
+
+        format!("{}{} {}", indentation, marker, content)
+    }
+
+    fn convert_table_row(&mut self, row: &str) -> String {
+
+        let cells: Vec<String> = split_table_row(row).into_iter().map(|cell| {
+
+            let cell = self.convert_inline(cell.trim());
+
+            nmd_table_cell(&cell)
+
+        }).collect();
+
+        format!("|{}|", cells.join("|"))
+    }
+
+    fn convert_inline(&mut self, text: &str) -> String {
+
+        let text = HTML_COMMENT_REGEX.replace_all(text, "");
+
+        let text = FOOTNOTE_REFERENCE_REGEX.replace_all(&text, |captures: &Captures| {
+
+            let label = &captures[1];
+
+            if !self.footnote_definitions.contains_key(label) {
+                return captures[0].to_string()
+            }
+
+            let next_number = self.footnote_numbers.len() + 1;
+
+            let n = *self.footnote_numbers.entry(label.to_string()).or_insert(next_number);
+
+            format!("[({})](#{}{})", n, FOOTNOTE_ID_PREFIX, n)
+
+        }).to_string();
+
+        let text = REFERENCE_LINK_REGEX.replace_all(&text, |captures: &Captures| {
+
+            let whole = captures.get(0).unwrap();
+
+            // inline link or image, reference is not needed
+            if captures.get(3).is_none() && text[whole.end()..].starts_with('(') {
+                return whole.as_str().to_string()
+            }
+
+            let label = match captures.get(3) {
+                Some(label) if !label.as_str().trim().is_empty() => label.as_str(),
+                _ => &captures[2],
+            };
+
+            match self.link_definitions.get(&normalize_label(label)) {
+                Some(url) => format!("{}[{}]({})", &captures[1], &captures[2], url),
+                None => whole.as_str().to_string(),
+            }
+
+        }).to_string();
+
+        let text = IMAGE_REGEX.replace_all(&text, |captures: &Captures| {
+            format!("![{}]({})", &captures[1], self.import_image(&captures[2]))
+        }).to_string();
+
+        let text = LINK_WITH_TITLE_REGEX.replace_all(&text, "[$1]($2)");
+
+        let text = AUTOLINK_REGEX.replace_all(&text, |captures: &Captures| {
+
+            let target = &captures[1];
+
+            if target.contains("://") || target.starts_with("mailto:") {
+                format!("[{}]({})", target, target)
+            } else {
+                format!("[{}](mailto:{})", target, target)
+            }
+        });
+
+        escape_nmd_syntax(&text)
+    }
+
+    /// New image source for `src`: local images are collected and moved in dossier images directory
+    fn import_image(&mut self, src: &str) -> String {
+
+        if REMOTE_SRC_PREFIXES.iter().any(|prefix| src.starts_with(prefix)) {
+            return src.to_string()
+        }
+
+        let decoded_src = percent_decode_str(src.split(['?', '#']).next().unwrap_or(src)).decode_utf8_lossy().to_string();
+
+        let source = self.source_dir.join(&decoded_src);
+
+        if !source.is_file() {
+
+            log::warn!("image {:?} not found, its source is kept as it is", source);
+
+            return src.to_string()
+        }
+
+        let file_name = match self.images.iter().find(|(image_source, _)| image_source.eq(&source)) {
+            Some((_, file_name)) => file_name.clone(),
+            None => {
+
+                let file_name = unique_image_file_name(&source, &self.images);
+
+                self.images.push((source, file_name.clone()));
+
+                file_name
+            }
+        };
+
+        format!("{}/{}/{}", dossier::ASSETS_DIR, dossier::IMAGES_DIR, file_name)
+    }
+}
+
+/// Split YAML front matter (between `---` lines at the beginning) from body
+fn split_front_matter(markdown: &str) -> (Option<Mapping>, &str) {
+
+    let Some(rest) = markdown.strip_prefix("---\n").or_else(|| markdown.strip_prefix("---\r\n")) else {
+        return (None, markdown)
+    };
+
+    let mut offset = 0;
+
+    for line in rest.split_inclusive('\n') {
+
+        if matches!(line.trim_end(), "---" | "...") {
+
+            let front_matter = match serde_yaml::from_str::<Mapping>(&rest[..offset]) {
+                Ok(front_matter) => Some(front_matter),
+                Err(err) => {
+                    log::warn!("front matter is ignored because it is not valid YAML: {}", err);
+
+                    None
+                }
+            };
+
+            return (front_matter, &rest[offset + line.len()..])
+        }
+
+        offset += line.len();
+    }
+
+    (None, markdown)
+}
+
+fn is_closing_fence(line: &str, open_fence: &str) -> bool {
+
+    let trimmed = line.trim();
+
+    let fence_char = open_fence.chars().next().unwrap();
+
+    trimmed.len() >= open_fence.len() && trimmed.chars().all(|c| c == fence_char)
+}
+
+/// Line without code block indentation (4 spaces or a tab)
+fn strip_indentation(line: &str) -> Option<&str> {
+    line.strip_prefix("    ").or_else(|| line.strip_prefix('\t'))
+}
+
+fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+/// Pad NMD `cell` content to be placed between table row separators
+fn nmd_table_cell(cell: &str) -> String {
+
+    if cell.is_empty() {
+        return String::from(" ")
+    }
+
+    // trailing space would make NMD load cell as a list item
+    if BULLET_LIKE_REGEX.is_match(cell) {
+        return format!(" {}", cell)
+    }
+
+    format!(" {} ", cell)
+}
+
+/// Cells of a table row, outer pipes excluded. Escaped pipes and pipes in code spans are not separators
+fn split_table_row(row: &str) -> Vec<&str> {
+
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = if row.ends_with('|') && !row.ends_with("\\|") { &row[..row.len() - 1] } else { row };
+
+    let mut cells: Vec<&str> = Vec::new();
+
+    let mut start = 0;
+    let mut in_code = false;
+    let mut escaped = false;
+
+    for (index, c) in row.char_indices() {
+        match c {
+            '\\' if !escaped => {
+                escaped = true;
+
+                continue
+            },
+            '`' => in_code = !in_code,
+            '|' if !escaped && !in_code => {
+
+                cells.push(&row[start..index]);
+
+                start = index + 1;
+            },
+            _ => (),
+        }
+
+        escaped = false;
+    }
+
+    cells.push(&row[start..]);
+
+    cells
+}
+
+fn table_alignment(delimiter: &str) -> String {
+    match (delimiter.starts_with(':'), delimiter.ends_with(':')) {
+        (true, true) => String::from(":---:"),
+        (true, false) => String::from(":---"),
+        (false, true) => String::from("---:"),
+        (false, false) => String::from("---"),
+    }
+}
+
+fn unique_image_file_name(source: &Path, images: &[(PathBuf, String)]) -> String {
+
+    let file_name = source.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+
+    let mut candidate = file_name.clone();
+    let mut n = 1;
+
+    while images.iter().any(|(_, used)| used.eq(&candidate)) {
+
+        n += 1;
+
+        candidate = match file_name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}-{}.{}", stem, n, extension),
+            None => format!("{}-{}", file_name, n),
+        };
+    }
+
+    candidate
+}
+
+/// Replace text which NMD would interpret as its own syntax with HTML entities. Code spans, inline math, link targets and HTML tags are untouched
+fn escape_nmd_syntax(text: &str) -> String {
+
+    let mut escaped = String::new();
+
+    let mut last = 0;
+
+    for protected in PROTECTED_SPAN_REGEX.find_iter(text) {
+
+        escaped.push_str(&escape_plain_text(&text[last..protected.start()]));
+        escaped.push_str(protected.as_str());
+
+        last = protected.end();
+    }
+
+    escaped.push_str(&escape_plain_text(&text[last..]));
+
+    escaped
+}
+
+fn escape_plain_text(text: &str) -> String {
+
+    let text = text.replace("==", "&#61;&#61;").replace("++", "&#43;&#43;").replace('^', "&#94;").replace("@[", "&#64;[");
+
+    // single tilde would be a subscript, double tilde is a strikethrough in both syntaxes
+    let chars: Vec<char> = text.chars().collect();
+
+    let text: String = chars.iter().enumerate().map(|(index, c)| {
+
+        if *c == '~' && (index == 0 || chars[index - 1] != '~') && chars.get(index + 1) != Some(&'~') {
+            return String::from("&#126;")
+        }
+
+        c.to_string()
+
+    }).collect();
+
+    let text = GREEK_LETTER_REGEX.replace_all(&text, "&#37;$1%");
+    let text = TEXT_REFERENCE_REGEX.replace_all(&text, "&amp;$1&");
+
+    // applied twice because matches can't overlap (e.g. `a_b_c`)
+    let text = INTRAWORD_UNDERSCORE_REGEX.replace_all(&text, "$1&#95;$2");
+    let text = INTRAWORD_UNDERSCORE_REGEX.replace_all(&text, "$1&#95;$2");
+
+    text.to_string()
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use crate::builder::{builder_configuration::BuilderConfiguration, Builder};
+
+    use super::MarkdownImporter;
+
+
+    fn import(markdown: &str) -> String {
+        MarkdownImporter::new(PathBuf::from(".")).import(markdown).content().clone()
+    }
+
+    #[test]
+    fn blocks_are_converted() {
+
+        let markdown = concat!(
+            "Title\n=====\n\n",
+            "Sub\n---\n\n",
+            "- [ ] todo\n- [x] done\n\n",
+            "a | b\n:-- | --:\n1 | x y\n\n",
+            "~~~rust\nlet a = 1;\n~~~\n\n",
+            "    indented code\n\n",
+            "***\n\n",
+            "10. ten\n",
+        );
+
+        assert_eq!(import(markdown), concat!(
+            "# Title\n\n",
+            "## Sub\n\n",
+            "-[ ] todo\n-[x] done\n\n",
+            "| a | b |\n|:---|---:|\n| 1| x y |\n\n",
+            "```rust\nlet a = 1;\n```\n\n",
+            "```\nindented code\n```\n\n",
+            "---\n\n",
+            "| 10. ten\n",
+        ));
+    }
+
+    #[test]
+    fn inline_constructs_are_converted() {
+
+        let markdown = concat!(
+            "---\ntitle: My Docs\n---\n",
+            "See [docs][ref], [Ref] and [site](https://example.com \"Site\") <https://nmd.dev>.<!-- hidden -->\n\n",
+            "Note[^n] about snake_case, ~5, 2^10, a==b and `a==b`.\n\n",
+            "[ref]: https://example.com/docs \"Docs\"\n",
+            "[^n]: A *note*.\n",
+        );
+
+        let import = MarkdownImporter::new(PathBuf::from(".")).import(markdown);
+
+        assert_eq!(import.front_matter().as_ref().unwrap()["title"].as_str(), Some("My Docs"));
+
+        assert_eq!(import.content(), concat!(
+            "See [docs](https://example.com/docs), [Ref](https://example.com/docs) and [site](https://example.com) [https://nmd.dev](https://nmd.dev).\n\n",
+            "Note[(1)](#footnote-1) about snake&#95;case, &#126;5, 2&#94;10, a&#61;&#61;b and `a==b`.\n\n",
+        ));
+
+        let sections = import.sections(1);
+
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].content().ends_with("---\n\n|:---|:---|\n| (1) | A *note*. |\n#footnote-1\n"));
+    }
+
+    #[test]
+    fn last_section_is_kept() {
+
+        let import = MarkdownImporter::new(PathBuf::from(".")).import("intro\n\n# One\n\na\n\n# Two\n\n```\n# not a heading\n```\n");
+
+        let sections = import.sections(1);
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title().as_deref(), Some("One"));
+        assert_eq!(sections[0].content(), "intro\n\n# One\n\na\n\n");
+        assert_eq!(sections[1].title().as_deref(), Some("Two"));
+        assert_eq!(sections[1].content(), "# Two\n\n```\n# not a heading\n```\n");
+    }
+
+    #[test]
+    fn sections_are_split_at_level() {
+
+        let import = MarkdownImporter::new(PathBuf::from(".")).import("# Guide
+
+## Install
+
+a
+
+### Linux
+
+b
+
+## Usage
+
+c
+");
+
+        assert_eq!(import.sections(1).len(), 1);
+
+        let titles: Vec<Option<String>> = import.sections(2).iter().map(|section| section.title().clone()).collect();
+
+        assert_eq!(titles, vec![Some(String::from("Guide")), Some(String::from("Install")), Some(String::from("Usage"))]);
+    }
+
+    #[tokio::test]
+    async fn footnote_references_link_footnotes() {
+
+        let import = MarkdownImporter::new(PathBuf::from(".")).import("First[^a] and second[^b] note.\n\n[^a]: A *note*.\n[^b]: A note with a | pipe.\n");
+
+        let document_path = std::env::temp_dir().join(format!("nmd-test-footnotes-{}.nmd", std::process::id()));
+
+        fs::write(&document_path, import.document()).unwrap();
+
+        let builder_configuration = BuilderConfiguration::new(document_path.clone(), PathBuf::from("."));
+
+        let mut document = Builder::load_document(&builder_configuration).await.unwrap();
+
+        let output = Builder::compile_document(&mut document, &builder_configuration).await.unwrap();
+
+        fs::remove_file(&document_path).unwrap();
+
+        for n in 1..=2 {
+
+            let id = output.split("href=\"#").skip(1)
+                        .map(|rest| rest.split('"').next().unwrap())
+                        .find(|id| id.ends_with(&format!("footnote-{}", n)))
+                        .unwrap_or_else(|| panic!("reference to footnote {} not found", n));
+
+            assert!(output.contains(&format!("id=\"{}\"", id)), "footnote {} has not id {}", n, id);
+        }
+
+        assert!(output.contains("A note with a &#124; pipe."));
+    }
+}
@@ -4,7 +4,11 @@ pub mod watcher;
 pub mod dossier_manager;
 pub mod generator;
 pub mod builder;
+pub mod checker;
+pub mod server;
+pub mod shutdown;
 pub mod constants;
+pub mod utility;
 
 
 use cli::{NmdCli, NmdCliError};
@@ -1,4 +1,5 @@
 use html_preview::HtmlPreviewError;
+use serde::Serialize;
 use thiserror::Error;
 use tokio::task::JoinError;
 
@@ -19,6 +20,27 @@ pub enum PreviewError {
 }
 
 
+/// Build failure shown by preview in place of an update, last good output is kept
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewFailure {
+
+    /// Error message
+    pub message: String,
+
+    /// Name (or path) of document which has caused the failure, if known
+    pub document: Option<String>,
+}
+
+impl PreviewFailure {
+    pub fn new(message: String, document: Option<String>) -> Self {
+        Self {
+            message,
+            document,
+        }
+    }
+}
+
+
 pub trait Preview {
 
     fn start(&mut self) -> impl std::future::Future<Output = Result<(), PreviewError>> + Send;
@@ -27,5 +49,8 @@ pub trait Preview {
 
     fn update(&mut self) -> impl std::future::Future<Output = Result<(), PreviewError>> + Send;
 
+    /// Report a build failure to preview, it is cleared by next update
+    fn fail(&mut self, failure: PreviewFailure) -> impl std::future::Future<Output = Result<(), PreviewError>> + Send;
+
     fn stop(&mut self) -> impl std::future::Future<Output = Result<(), PreviewError>> + Send;
 }
\ No newline at end of file
@@ -1,65 +1,121 @@
-const url = 'http://127.0.0.1:1234/preview-state-info';
-const MIN_SCRAPE_INTERVAL = 1000;
+const PREVIEW_URL = '{{PREVIEW_URL}}';
+const PREVIEW_EVENTS_ROUTE = '/preview-events';
 
+// same origin if page is served by preview server, otherwise (e.g. file opened from disk) injected preview URL is used
+const url = window.location.protocol.startsWith('http') ? PREVIEW_EVENTS_ROUTE : PREVIEW_URL + PREVIEW_EVENTS_ROUTE;
+const UPDATE_EVENT = 'update';
+const FAILURE_EVENT = 'build-failure';
+const FAILURE_OVERLAY_ID = 'nmd-preview-failure-overlay';
+const SCROLL_POSITION_KEY = 'nmd-preview-scroll-position';
 
-var scrapeInterval = MIN_SCRAPE_INTERVAL;
 
-var interval = null;
+function saveScrollPosition() {
 
-function stopScraping() {
-    if (!!interval) {
-        clearInterval(interval);
-        interval = null;
+    sessionStorage.setItem(SCROLL_POSITION_KEY, JSON.stringify({
+        x: window.scrollX,
+        y: window.scrollY,
+    }));
+}
+
+function restoreScrollPosition() {
+
+    const scrollPosition = sessionStorage.getItem(SCROLL_POSITION_KEY);
+
+    if (scrollPosition === null) {
+        return;
     }
 
+    sessionStorage.removeItem(SCROLL_POSITION_KEY);
+
+    try {
+        const position = JSON.parse(scrollPosition);
+
+        window.scrollTo(position.x, position.y);
+
+    } catch (error) {
+        console.error('error occurs during scroll position restoring:', error);
+    }
 }
 
-function startScraping() {
+function showFailureOverlay(failure) {
+
+    let overlay = document.getElementById(FAILURE_OVERLAY_ID);
+
+    if (overlay === null) {
+        overlay = document.createElement('div');
+        overlay.id = FAILURE_OVERLAY_ID;
+        overlay.style.cssText = 'position: fixed; inset: 0; z-index: 2147483647; overflow: auto; padding: 2em; '
+                                + 'background: rgba(20, 20, 20, 0.92); color: #f5f5f5; font-family: monospace;';
+
+        document.body.appendChild(overlay);
+    }
+
+    overlay.replaceChildren();
+
+    const title = document.createElement('h2');
+    title.style.color = '#ff6b6b';
+    title.textContent = 'Build failed';
+    overlay.appendChild(title);
+
+    if (failure.document) {
+        const failingDocument = document.createElement('p');
+        failingDocument.textContent = 'Document: ' + failure.document;
+        overlay.appendChild(failingDocument);
+    }
+
+    const message = document.createElement('pre');
+    message.style.whiteSpace = 'pre-wrap';
+    message.textContent = failure.message;
+    overlay.appendChild(message);
+
+    const hint = document.createElement('p');
+    hint.style.opacity = '0.7';
+    hint.textContent = 'Last good output is kept, preview will be reloaded after next successful build. ';
+
+    const dismiss = document.createElement('button');
+    dismiss.textContent = 'Show last good output';
+    dismiss.onclick = () => overlay.remove();
+    hint.appendChild(dismiss);
 
-    stopScraping();
-    interval = setInterval(checkPreviewUpdates, scrapeInterval);
+    overlay.appendChild(hint);
 }
 
-async function checkPreviewUpdates() {
+function listenPreviewUpdates() {
 
-    console.log("checking preview updates...");
+    console.log("listening preview updates...");
 
-    try {
-        const response = await fetch(url);
-        if (!response.ok) {
-            throw new Error('network response was not ok');
-        }
+    const source = new EventSource(url);
 
-        const data = await response.json();
-        const lastUpdateTimestamp = !data.last_update_timestamp ? null : new Date(data.last_update_timestamp);
-        const lastSeenTimestamp = !data.last_seen_timestamp ? null : new Date(data.last_seen_timestamp);
+    source.addEventListener(UPDATE_EVENT, (event) => {
 
-        if (!!data.scrape_interval && data.scrape_interval != scrapeInterval) {
+        console.log("new preview found (update timestamp: " + event.data + ")");
+        console.log("reloading...");
 
-            console.log(`new scrape interval found (before: ${scrapeInterval})`);
+        source.close();
 
-            scrapeInterval = Math.max(data.scrape_interval, MIN_SCRAPE_INTERVAL);
+        saveScrollPosition();
 
-            console.log(`new scrape interval: ${scrapeInterval}`);
-            
-            stopScraping();
-            startScraping();
-        }
+        window.location.reload();
+    });
 
-        console.log("last update timestamp: " + lastUpdateTimestamp);
-        console.log("last seen timestamp: " + lastSeenTimestamp);
+    source.addEventListener(FAILURE_EVENT, (event) => {
 
-        if (lastUpdateTimestamp !== null && lastUpdateTimestamp >= lastSeenTimestamp) {
+        console.error("build failed, last good output is shown");
 
-            console.log("new preview found!");
-            console.log("reloading...");
+        try {
+            showFailureOverlay(JSON.parse(event.data));
 
-            window.location.reload();
+        } catch (error) {
+            console.error('error occurs during build failure showing:', error);
         }
-    } catch (error) {
-        console.error('error occurs during check preview updates:', error);
-    }
+    });
+
+    source.onerror = (error) => {
+        console.error('error occurs during listening preview updates (browser will retry):', error);
+    };
 }
 
 
-startScraping();
\ No newline at end of file
+window.addEventListener('load', restoreScrollPosition);
+
+listenPreviewUpdates();
@@ -1,58 +1,116 @@
-use std::{path::PathBuf, sync::RwLock};
-use chrono::{DateTime, Local};
-use getset::{Getters, Setters};
-use once_cell::sync::Lazy;
-use serde::Serialize;
+use std::{convert::Infallible, net::{IpAddr, Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, sync::{Arc, RwLock}};
+use getset::{CopyGetters, Getters, Setters};
 use thiserror::Error;
-use tokio::{fs::File, io::AsyncReadExt, task::JoinHandle};
-use warp::Filter;
+use tokio::{fs::File, io::AsyncReadExt, sync::broadcast::{self, Sender}, task::JoinHandle};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use percent_encoding::percent_decode_str;
+use warp::{path::Tail, sse::Event, Filter};
 
-use super::{Preview, PreviewError};
+use super::{Preview, PreviewError, PreviewFailure};
 
-pub const PREVIEW_STATE_INFO_ROUTE: &str = "preview-state-info";
-const DEFAULT_SCRAPE_INTERVAL: u32 = 2000; 
-
-
-pub static LAST_UPDATE: Lazy<RwLock<Option<DateTime<Local>>>> = Lazy::new(|| RwLock::new(None));
-pub static LAST_SEEN: Lazy<RwLock<Option<DateTime<Local>>>> = Lazy::new(|| RwLock::new(None));
-
-
-#[derive(Debug, Serialize)]
-struct PreviewStateInfo {
-    last_update_timestamp: Option<i64>,
-    last_seen_timestamp: Option<i64>,
-    scrape_interval: Option<u32>,
-}
+pub const PREVIEW_EVENTS_ROUTE: &str = "preview-events";
+pub const PREVIEW_UPDATE_EVENT: &str = "update";
+pub const PREVIEW_FAILURE_EVENT: &str = "build-failure";
+const UPDATE_CHANNEL_CAPACITY: usize = 16;
 
 
 #[derive(Error, Debug)]
 pub enum HtmlPreviewError {
+
+    #[error("no available port found in {0}..{1}")]
+    NoAvailablePort(u16, u16),
 }
 
 
+pub const PREVIEW_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
 pub const PREVIEW_PORT: u16 = 1234;
-pub const PREVIEW_URL: &str = "http://127.0.0.1:1234";      // change if PREVIEW_PORT changes
+
+/// Number of ports tried (starting from the requested one) before giving up
+const PREVIEW_PORT_ATTEMPTS: u16 = 16;
+
+/// Placeholder replaced with preview URL in client script
+const PREVIEW_URL_PLACEHOLDER: &str = "{{PREVIEW_URL}}";
+
+
+/// Message pushed to event stream of connected clients
+#[derive(Debug, Clone)]
+enum PreviewEvent {
+
+    /// New build is available (timestamp in millis)
+    Update(i64),
+
+    /// Last build is failed, previous output is still served
+    Failure(PreviewFailure),
+
+    /// Preview is stopping, so event streams must be closed to permit graceful shutdown
+    Shutdown,
+}
 
 
-#[derive(Debug, Getters, Setters)]
+#[derive(Debug, Getters, CopyGetters, Setters)]
 pub struct HtmlPreview {
     
     #[getset(get = "pub", set = "pub")]
     src: PathBuf,
 
+    /// Directory served as static files (e.g. dossier directory), so relative assets can be resolved
+    #[getset(get = "pub", set = "pub")]
+    root: PathBuf,
+
+    #[getset(get_copy = "pub")]
+    host: IpAddr,
+
+    /// Requested port before start, bound port after start
+    #[getset(get_copy = "pub")]
+    port: u16,
+
     server_thread_handle: Option<JoinHandle<()>>,
     
-    client_preview_scraping_interval: u32,
+    /// Used to push update events to connected clients
+    update_sender: Sender<PreviewEvent>,
+
+    /// Failure of last build, it is pushed to clients which connect after the failure
+    last_failure: Arc<RwLock<Option<PreviewFailure>>>,
+
+    /// Server is shut down gracefully when this token is cancelled
+    #[getset(get = "pub", set = "pub")]
+    shutdown_token: CancellationToken,
 }
 
 impl HtmlPreview {
-    pub fn new(src: PathBuf, client_preview_scraping_interval: Option<u32>) -> Self {
+    pub fn new(src: PathBuf, root: PathBuf, host: Option<IpAddr>, port: Option<u16>) -> Self {
+
+        let (update_sender, _) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+
         Self {
             src,
+            root,
+            host: host.unwrap_or(PREVIEW_HOST),
+            port: port.unwrap_or(PREVIEW_PORT),
             server_thread_handle: None,
-            client_preview_scraping_interval: client_preview_scraping_interval.unwrap_or(DEFAULT_SCRAPE_INTERVAL)
+            update_sender,
+            last_failure: Arc::new(RwLock::new(None)),
+            shutdown_token: CancellationToken::new(),
         }
     }
+
+    /// URL which can be used by a browser to reach preview
+    pub fn url(&self) -> String {
+
+        let host = if self.host.is_unspecified() {
+            PREVIEW_HOST
+        } else {
+            self.host
+        };
+
+        format!("http://{}", SocketAddr::new(host, self.port))
+    }
+
+    /// Client script which reloads page when preview is updated
+    pub fn client_script(preview_url: &str) -> String {
+        include_str!("check_preview_updates.js").replace(PREVIEW_URL_PLACEHOLDER, preview_url)
+    }
 }
 
 impl Preview for HtmlPreview {
@@ -65,75 +123,107 @@ impl Preview for HtmlPreview {
 
         log::set_max_level(log::LevelFilter::Warn);
 
-        let client_preview_scraping_interval = self.client_preview_scraping_interval;
+        let update_sender = self.update_sender.clone();
+
+        let last_failure = self.last_failure.clone();
 
-        self.server_thread_handle = Some(tokio::spawn(async move {
+        let show_preview = move || {
+            let src = src.clone();
 
-            let show_preview = move || {
-                let src = src.clone();
+            log::info!("serving preview...");
 
-                log::info!("serving preview...");
+            serve_preview(src)
+        };
 
-                serve_preview(src)
-            };
+        let preview_route_implicite = warp::path::end()
+                            .and_then(show_preview.clone());
 
-            let preview_route_implicite = warp::path::end()
-                                .and_then(show_preview.clone());
+        let preview_route_explicit = warp::path!("preview")
+                            .and_then(show_preview);
 
-            let preview_route_explicit = warp::path!("preview")
-                                .and_then(show_preview);
+        let preview_events_route = warp::path(PREVIEW_EVENTS_ROUTE)
+                                    .and(warp::get())
+                                    .map(move || {
 
-            let preview_state_info_route = warp::path(PREVIEW_STATE_INFO_ROUTE)
-                                        .map(move || {
+                                        log::debug!("new html preview client connected");
 
-                                            let last_update_timestamp: Option<i64>;
-                                            let last_seen_timestamp: Option<i64>;
+                                        // a client which connects after a failure (e.g. reloaded page) must show it too
+                                        let pending_failure: Vec<PreviewEvent> = last_failure.read().unwrap().clone()
+                                                                                    .map(PreviewEvent::Failure)
+                                                                                    .into_iter()
+                                                                                    .collect();
 
-                                            if let Some(l) = *LAST_UPDATE.read().unwrap() {
+                                        let updates = tokio_stream::iter(pending_failure)
+                                                            .chain(BroadcastStream::new(update_sender.subscribe()).filter_map(|event| event.ok()))
+                                                            .take_while(|event| !matches!(event, PreviewEvent::Shutdown))
+                                                            .filter_map(|event| match event {
+                                                                PreviewEvent::Update(timestamp) => Some(Ok::<Event, Infallible>(
+                                                                    Event::default()
+                                                                        .event(PREVIEW_UPDATE_EVENT)
+                                                                        .data(timestamp.to_string())
+                                                                )),
+                                                                PreviewEvent::Failure(failure) => Some(Ok::<Event, Infallible>(
+                                                                    Event::default()
+                                                                        .event(PREVIEW_FAILURE_EVENT)
+                                                                        .data(serde_json::to_string(&failure).unwrap_or_default())
+                                                                )),
+                                                                PreviewEvent::Shutdown => None,
+                                                            });
 
-                                                last_update_timestamp = Some(l.timestamp());
-                                            
-                                            } else {
+                                        warp::sse::reply(warp::sse::keep_alive().stream(updates))
+                                    });
 
-                                                last_update_timestamp = None;
-                                            }
+        let root = self.root.canonicalize()?;
 
-                                            if let Some(l) = *LAST_SEEN.read().unwrap() {
+        let assets_route = warp::get()
+                            .and(warp::path::tail())
+                            .and_then(move |tail: Tail| {
+                                serve_asset(root.clone(), tail)
+                            });
 
-                                                last_seen_timestamp = Some(l.timestamp());
-                                            
-                                            } else {
+        let routes = preview_route_implicite
+                        .or(preview_route_explicit)
+                        .or(preview_events_route)
+                        .or(assets_route);
 
-                                                last_seen_timestamp = None;
-                                            }
+        let last_port = self.port.saturating_add(PREVIEW_PORT_ATTEMPTS);
 
-                                            let response = PreviewStateInfo {
-                                                last_update_timestamp,
-                                                last_seen_timestamp,
-                                                scrape_interval: Some(client_preview_scraping_interval)
-                                            };
+        let mut server = None;
 
-                                            let now = chrono::offset::Local::now();
+        for port in self.port..last_port {
 
-                                            log::debug!("html preview seen (new last seen: {})", now);
+            match warp::serve(routes.clone()).try_bind_with_graceful_shutdown((self.host, port), self.shutdown_token.clone().cancelled_owned()) {
+                Ok(bound) => {
+                    server = Some(bound);
+                    break;
+                },
+                Err(err) => log::warn!("html preview can not use port {}: {}", port, err),
+            }
+        }
 
-                                            *LAST_SEEN.write().unwrap() = Some(now);
+        log::set_max_level(original_log_max_level);
 
-                                            warp::reply::json(&response)
-                                        });
-    
-            log::info!("html preview will be running on: {}", PREVIEW_URL);
+        let (address, server) = server.ok_or(HtmlPreviewError::NoAvailablePort(self.port, last_port))?;
 
-            warp::serve(
-                preview_route_implicite
-                .or(preview_route_explicit)
-                .or(preview_state_info_route)
-            )
-            .run(([127, 0, 0, 1], PREVIEW_PORT))
-            .await
-        }));
+        self.port = address.port();
 
-        log::set_max_level(original_log_max_level);
+        self.server_thread_handle = Some(tokio::spawn(server));
+
+        // open event streams never end by themselves, so they are closed before shutting down
+        tokio::spawn({
+
+            let shutdown_token = self.shutdown_token.clone();
+            let update_sender = self.update_sender.clone();
+
+            async move {
+
+                shutdown_token.cancelled().await;
+
+                let _ = update_sender.send(PreviewEvent::Shutdown);
+            }
+        });
+
+        log::info!("html preview is running on: {} (bound to {})", self.url(), address);
         
         Ok(())
     }
@@ -149,17 +239,40 @@ impl Preview for HtmlPreview {
 
         let now = chrono::offset::Local::now();
 
-        *LAST_UPDATE.write().unwrap() = Some(now);
+        *self.last_failure.write().unwrap() = None;
+
+        // an error means that there are no connected clients
+        if let Ok(clients) = self.update_sender.send(PreviewEvent::Update(now.timestamp_millis())) {
+
+            log::debug!("update event pushed to {} client(s)", clients);
+        }
 
         log::info!("html preview updated (new last update: {})", now);
 
         Ok(())
     }
     
+    async fn fail(&mut self, failure: PreviewFailure) -> Result<(), PreviewError> {
+
+        *self.last_failure.write().unwrap() = Some(failure.clone());
+
+        // an error means that there are no connected clients
+        if let Ok(clients) = self.update_sender.send(PreviewEvent::Failure(failure)) {
+
+            log::debug!("failure event pushed to {} client(s)", clients);
+        }
+
+        log::info!("html preview shows last build failure (last good output is kept)");
+
+        Ok(())
+    }
+
     async fn stop(&mut self) -> Result<(), PreviewError> {
 
+        self.shutdown_token.cancel();
+
         if let Some(j) = self.server_thread_handle.take() {
-            j.await?;       // need Ctrl + C to terminate
+            j.await?;       // wait in-flight requests
         }
 
         log::info!("html preview stop");
@@ -170,6 +283,14 @@ impl Preview for HtmlPreview {
 
 async fn serve_preview(file_path: PathBuf) -> Result<impl warp::Reply, warp::Rejection> {
 
+    // there isn't a good output yet (e.g. first build is failed), so a page which waits next update is served
+    if !file_path.exists() {
+
+        log::warn!("preview file not found, waiting page is served ({:?})", file_path);
+
+        return Ok(warp::reply::html(waiting_page()))
+    }
+
     let mut file = File::open(file_path.clone()).await.map_err(|err| {
 
         log::error!("error occurs during preview file opening: {} ({:?})", err.to_string(), file_path);
@@ -187,4 +308,80 @@ async fn serve_preview(file_path: PathBuf) -> Result<impl warp::Reply, warp::Rej
     })?;
 
     Ok(warp::reply::html(contents))
-}
\ No newline at end of file
+}
+
+/// Page served in place of preview while there isn't an output, it is reloaded (or shows failure) like output
+fn waiting_page() -> String {
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>NMD preview</title></head><body><p>Waiting for a successful build...</p><script>{}</script></body></html>"#,
+        HtmlPreview::client_script("")
+    )
+}
+
+async fn serve_asset(root: PathBuf, tail: Tail) -> Result<impl warp::Reply, warp::Rejection> {
+
+    let requested_path = percent_decode_str(tail.as_str()).decode_utf8().map_err(|_| warp::reject())?;
+
+    let asset_path = resolve_asset_path(&root, &requested_path).ok_or_else(|| {
+
+        log::debug!("asset '{}' not found or outside preview root", requested_path);
+
+        warp::reject::not_found()
+    })?;
+
+    let content = tokio::fs::read(&asset_path).await.map_err(|err| {
+
+        log::error!("error occurs during asset reading: {} ({:?})", err, asset_path);
+
+        warp::reject()
+    })?;
+
+    let mime_type = mime_guess::from_path(&asset_path).first_or_octet_stream();
+
+    Ok(warp::reply::with_header(content, "content-type", mime_type.to_string()))
+}
+
+/// Resolve a requested path (relative to preview root or absolute, but inside preview root) to an existing file.
+/// `None` is returned if file doesn't exist or it is outside root (e.g. path traversal using `..`)
+fn resolve_asset_path(root: &Path, requested_path: &str) -> Option<PathBuf> {
+
+    let absolute_requested_path = Path::new("/").join(requested_path);
+
+    let candidate = if absolute_requested_path.starts_with(root) {
+        absolute_requested_path
+    } else {
+        root.join(requested_path)
+    };
+
+    let candidate = candidate.canonicalize().ok()?;
+
+    if candidate.starts_with(root) && candidate.is_file() {
+        return Some(candidate)
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::resolve_asset_path;
+
+
+    #[test]
+    fn asset_path_resolution() {
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-resources").join("nmd-test-dossier-2").canonicalize().unwrap();
+
+        let expected = root.join("welcome.nmd");
+
+        assert_eq!(resolve_asset_path(&root, "welcome.nmd"), Some(expected.clone()));
+        assert_eq!(resolve_asset_path(&root, expected.strip_prefix("/").unwrap().to_str().unwrap()), Some(expected));
+
+        assert_eq!(resolve_asset_path(&root, "not-exists.nmd"), None);
+        assert_eq!(resolve_asset_path(&root, "../wikipedia-logo.png"), None);
+        assert_eq!(resolve_asset_path(&root, "etc/passwd"), None);
+    }
+}
@@ -0,0 +1,416 @@
+pub mod build_request;
+
+
+use std::{collections::HashMap, net::{IpAddr, Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, sync::Arc, time::{Instant, SystemTime}};
+use build_request::BuildRequest;
+use getset::{CopyGetters, Getters, Setters};
+use nmd_core::{constants::{DOSSIER_CONFIGURATION_JSON_FILE_NAME, DOSSIER_CONFIGURATION_YAML_FILE_NAME}, dossier::Dossier};
+use serde::Serialize;
+use thiserror::Error;
+use once_cell::sync::Lazy;
+use tokio::sync::{Mutex as TokioMutex, RwLock as TokioRwLock};
+use tokio_util::sync::CancellationToken;
+use warp::{http::StatusCode, reply::Response, Filter, Reply};
+
+use crate::{builder::{builder_configuration::BuilderConfiguration, builder_error::BuilderError, Builder}, dossier_manager::DossierManager};
+
+
+pub const SERVER_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+pub const SERVER_PORT: u16 = 8080;
+
+/// Maximum size of a build request body
+const MAX_REQUEST_BODY_SIZE: u64 = 1024 * 1024;
+
+/// Dossier configuration resolves documents and styles paths using a global root path (the last loaded dossier),
+/// so dossiers must be loaded and compiled one at a time
+static DOSSIER_CONFIGURATION_LOCK: Lazy<TokioMutex<()>> = Lazy::new(|| TokioMutex::new(()));
+
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+
+    #[error("invalid input location: {0}")]
+    InvalidInputLocation(String),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("document '{0}' not found")]
+    DocumentNotFound(String),
+
+    #[error(transparent)]
+    BuilderError(#[from] BuilderError),
+
+    #[error(transparent)]
+    WarpError(#[from] warp::Error),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+impl ServerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ServerError::InvalidInputLocation(_) | ServerError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ServerError::DocumentNotFound(_) => StatusCode::NOT_FOUND,
+            ServerError::BuilderError(BuilderError::LoadError(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+
+/// Dossiers with different load options are kept separately
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct LoadedDossierKey {
+    input_location: PathBuf,
+    nuid: bool,
+    documents_subset: Option<Vec<String>>,
+}
+
+
+struct LoadedDossier {
+    dossier: Dossier,
+    loaded_at: SystemTime,
+}
+
+impl LoadedDossier {
+
+    fn new(dossier: Dossier) -> Self {
+        Self {
+            dossier,
+            loaded_at: SystemTime::now(),
+        }
+    }
+
+    /// Set global root path used by dossier configuration to resolve paths
+    fn restore_root_path(&self, input_location: &Path) {
+        self.dossier.configuration().clone().set_root_path(input_location.to_path_buf());
+    }
+
+    /// A loaded dossier is outdated if its configuration or one of its documents is modified after loading
+    fn is_outdated(&self, input_location: &Path) -> bool {
+
+        let mut paths: Vec<PathBuf> = vec![
+            input_location.join(DOSSIER_CONFIGURATION_YAML_FILE_NAME),
+            input_location.join(DOSSIER_CONFIGURATION_JSON_FILE_NAME),
+        ];
+
+        paths.extend(self.dossier.configuration().raw_documents_paths().iter().map(|raw_path| DossierManager::resolve_raw_document_path(input_location, raw_path)));
+
+        paths.iter().any(|path| {
+            path.metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| modified > self.loaded_at)
+                .unwrap_or(false)
+        })
+    }
+}
+
+
+#[derive(Debug, Serialize)]
+struct LoadedDossierInfo {
+    input_location: PathBuf,
+    name: String,
+    documents: Vec<String>,
+}
+
+
+type LoadedDossiers = Arc<TokioRwLock<HashMap<LoadedDossierKey, Arc<TokioMutex<LoadedDossier>>>>>;
+
+
+/// Long-running HTTP build server. Dossiers are kept in memory and they are reloaded only if they change
+#[derive(Getters, CopyGetters, Setters)]
+pub struct NmdServer {
+
+    /// Only dossiers and documents inside root can be built
+    #[getset(get = "pub")]
+    root: PathBuf,
+
+    #[getset(get_copy = "pub")]
+    host: IpAddr,
+
+    #[getset(get_copy = "pub")]
+    port: u16,
+
+    loaded_dossiers: LoadedDossiers,
+
+    /// Server stops accepting requests when this token is cancelled, in-flight builds are completed
+    #[getset(get = "pub", set = "pub")]
+    shutdown_token: CancellationToken,
+}
+
+impl NmdServer {
+
+    pub fn new(root: PathBuf, host: Option<IpAddr>, port: Option<u16>) -> Self {
+        Self {
+            root,
+            host: host.unwrap_or(SERVER_HOST),
+            port: port.unwrap_or(SERVER_PORT),
+            loaded_dossiers: Arc::new(TokioRwLock::new(HashMap::new())),
+            shutdown_token: CancellationToken::new(),
+        }
+    }
+
+    /// Start server and serve requests until shutdown token is cancelled
+    pub async fn serve(&self) -> Result<(), ServerError> {
+
+        let root = Arc::new(self.root.canonicalize()?);
+
+        let build_dossier_route = warp::post()
+                                    .and(warp::path!("build" / "dossier"))
+                                    .and(warp::body::content_length_limit(MAX_REQUEST_BODY_SIZE))
+                                    .and(warp::body::json())
+                                    .then({
+                                        let root = root.clone();
+                                        let loaded_dossiers = self.loaded_dossiers.clone();
+
+                                        move |request: BuildRequest| {
+                                            let root = root.clone();
+                                            let loaded_dossiers = loaded_dossiers.clone();
+
+                                            async move {
+                                                reply(build_dossier(&root, loaded_dossiers, request).await)
+                                            }
+                                        }
+                                    });
+
+        let build_document_route = warp::post()
+                                    .and(warp::path!("build" / "document"))
+                                    .and(warp::body::content_length_limit(MAX_REQUEST_BODY_SIZE))
+                                    .and(warp::body::json())
+                                    .then({
+                                        let root = root.clone();
+                                        let loaded_dossiers = self.loaded_dossiers.clone();
+
+                                        move |request: BuildRequest| {
+                                            let root = root.clone();
+                                            let loaded_dossiers = loaded_dossiers.clone();
+
+                                            async move {
+                                                reply(build_document(&root, loaded_dossiers, request).await)
+                                            }
+                                        }
+                                    });
+
+        let dossiers_route = warp::get()
+                                .and(warp::path!("dossiers"))
+                                .then({
+                                    let loaded_dossiers = self.loaded_dossiers.clone();
+
+                                    move || {
+                                        let loaded_dossiers = loaded_dossiers.clone();
+
+                                        async move {
+                                            warp::reply::json(&loaded_dossiers_info(loaded_dossiers).await)
+                                        }
+                                    }
+                                });
+
+        let routes = build_dossier_route
+                        .or(build_document_route)
+                        .or(dossiers_route);
+
+        let (address, server) = warp::serve(routes).try_bind_with_graceful_shutdown(SocketAddr::new(self.host, self.port), self.shutdown_token.clone().cancelled_owned())?;
+
+        log::info!("build server is running on: http://{} (root: {:?})", address, root);
+        log::info!("press CTRL + C to terminate");
+
+        server.await;
+
+        log::info!("build server stopped");
+
+        Ok(())
+    }
+}
+
+
+fn reply(result: Result<String, ServerError>) -> Response {
+
+    match result {
+        Ok(html) => warp::reply::html(html).into_response(),
+        Err(err) => {
+
+            log::error!("build request failed: {}", err);
+
+            warp::reply::with_status(err.to_string(), err.status_code()).into_response()
+        },
+    }
+}
+
+async fn build_dossier(root: &Path, loaded_dossiers: LoadedDossiers, request: BuildRequest) -> Result<String, ServerError> {
+
+    let request_start = Instant::now();
+
+    let input_location = resolve_input_location(root, &request.input_location)
+                                .filter(|path| path.is_dir())
+                                .ok_or(ServerError::InvalidInputLocation(request.input_location.to_string_lossy().to_string()))?;
+
+    let mut builder_configuration = request.builder_configuration(input_location.clone())?;
+
+    let loaded_dossier = get_loaded_dossier(loaded_dossiers, &request, &input_location).await?;
+
+    let mut loaded_dossier = loaded_dossier.lock().await;
+
+    builder_configuration.merge_dossier_configuration(loaded_dossier.dossier.configuration());
+
+    let compiled_dossier = {
+
+        let _lock = DOSSIER_CONFIGURATION_LOCK.lock().await;
+
+        loaded_dossier.restore_root_path(&input_location);
+
+        Builder::compile_dossier(&mut loaded_dossier.dossier, &builder_configuration).await?
+    };
+
+    log::info!("dossier {:?} built in {} ms", input_location, request_start.elapsed().as_millis());
+
+    Ok(compiled_dossier.content().clone())
+}
+
+async fn build_document(root: &Path, loaded_dossiers: LoadedDossiers, request: BuildRequest) -> Result<String, ServerError> {
+
+    let request_start = Instant::now();
+
+    let input_location = resolve_input_location(root, &request.input_location)
+                                .ok_or(ServerError::InvalidInputLocation(request.input_location.to_string_lossy().to_string()))?;
+
+    let mut builder_configuration = request.builder_configuration(input_location.clone())?;
+
+    let compiled_document = if input_location.is_dir() {
+
+        // document of a dossier
+        let document_name = request.document.clone().ok_or(ServerError::InvalidRequest(String::from("document name is needed to build a document of a dossier")))?;
+
+        let loaded_dossier = get_loaded_dossier(loaded_dossiers, &request, &input_location).await?;
+
+        let mut loaded_dossier = loaded_dossier.lock().await;
+
+        builder_configuration.merge_dossier_configuration(loaded_dossier.dossier.configuration());
+
+        let _lock = DOSSIER_CONFIGURATION_LOCK.lock().await;
+
+        loaded_dossier.restore_root_path(&input_location);
+
+        let document = loaded_dossier.dossier.documents_mut().iter_mut()
+                                        .find(|document| document.name().eq(&document_name))
+                                        .ok_or(ServerError::DocumentNotFound(document_name.clone()))?;
+
+        Builder::compile_document(document, &builder_configuration).await?
+
+    } else {
+
+        // single file
+        let mut document = Builder::load_document(&builder_configuration).await?;
+
+        builder_configuration.fill_with_default();
+
+        Builder::compile_document(&mut document, &builder_configuration).await?
+    };
+
+    log::info!("document {:?} built in {} ms", input_location, request_start.elapsed().as_millis());
+
+    Ok(compiled_document)
+}
+
+/// Return loaded dossier, loading it if it is not loaded yet, it is outdated or reload is requested
+async fn get_loaded_dossier(loaded_dossiers: LoadedDossiers, request: &BuildRequest, input_location: &Path) -> Result<Arc<TokioMutex<LoadedDossier>>, ServerError> {
+
+    let key = LoadedDossierKey {
+        input_location: input_location.to_path_buf(),
+        nuid: request.nuid.unwrap_or(false),
+        documents_subset: request.documents_subset_to_compile.as_ref().map(|subset| {
+            let mut subset: Vec<String> = subset.iter().cloned().collect();
+            subset.sort();
+            subset
+        }),
+    };
+
+    let builder_configuration = request.builder_configuration(input_location.to_path_buf())?;
+
+    let loaded_dossier = loaded_dossiers.read().await.get(&key).cloned();
+
+    if let Some(loaded_dossier) = loaded_dossier {
+
+        {
+            let mut loaded_dossier = loaded_dossier.lock().await;
+
+            if request.reload || loaded_dossier.is_outdated(input_location) {
+
+                log::info!("reload dossier {:?}", input_location);
+
+                *loaded_dossier = LoadedDossier::new(load_dossier(&builder_configuration).await?);
+            }
+        }
+
+        return Ok(loaded_dossier)
+    }
+
+    let loaded_dossier = Arc::new(TokioMutex::new(LoadedDossier::new(load_dossier(&builder_configuration).await?)));
+
+    loaded_dossiers.write().await.insert(key, loaded_dossier.clone());
+
+    Ok(loaded_dossier)
+}
+
+async fn load_dossier(builder_configuration: &BuilderConfiguration) -> Result<Dossier, BuilderError> {
+
+    let _lock = DOSSIER_CONFIGURATION_LOCK.lock().await;
+
+    Builder::load_dossier(builder_configuration).await
+}
+
+async fn loaded_dossiers_info(loaded_dossiers: LoadedDossiers) -> Vec<LoadedDossierInfo> {
+
+    let mut info: Vec<LoadedDossierInfo> = Vec::new();
+
+    for (key, loaded_dossier) in loaded_dossiers.read().await.iter() {
+
+        let loaded_dossier = loaded_dossier.lock().await;
+
+        info.push(LoadedDossierInfo {
+            input_location: key.input_location.clone(),
+            name: loaded_dossier.dossier.name().clone(),
+            documents: loaded_dossier.dossier.documents().iter().map(|document| document.name().clone()).collect(),
+        });
+    }
+
+    info
+}
+
+/// Resolve requested input location (relative to root) to an existing dossier or document inside root.
+/// `None` is returned if it doesn't exist or it is outside root (e.g. path traversal using `..`)
+fn resolve_input_location(root: &Path, requested_path: &Path) -> Option<PathBuf> {
+
+    let candidate = root.join(requested_path).canonicalize().ok()?;
+
+    if candidate.starts_with(root) {
+        return Some(candidate)
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::resolve_input_location;
+
+
+    #[test]
+    fn input_location_resolution() {
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-resources").canonicalize().unwrap();
+
+        let dossier = root.join("nmd-test-dossier-2");
+
+        assert_eq!(resolve_input_location(&root, Path::new("nmd-test-dossier-2")), Some(dossier.clone()));
+        assert_eq!(resolve_input_location(&root, Path::new("nmd-test-dossier-2/welcome.nmd")), Some(dossier.join("welcome.nmd")));
+
+        assert_eq!(resolve_input_location(&root, Path::new("not-exists")), None);
+        assert_eq!(resolve_input_location(&root, Path::new("..")), None);
+        assert_eq!(resolve_input_location(&dossier, Path::new("/etc")), None);
+    }
+}
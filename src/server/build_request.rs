@@ -0,0 +1,71 @@
+use std::{collections::HashSet, path::PathBuf, str::FromStr};
+use nmd_core::{output_format::OutputFormat, resource::text_reference::TextReferenceMap, theme::Theme};
+use serde::Deserialize;
+
+use crate::builder::builder_configuration::BuilderConfiguration;
+use super::ServerError;
+
+
+/// Body of a build request. Options are the same of `BuilderConfiguration`, missing ones are taken from dossier configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildRequest {
+
+    /// Dossier or document path, relative to server root
+    pub input_location: PathBuf,
+
+    /// Document name, used to compile a single document of a dossier
+    pub document: Option<String>,
+
+    pub format: Option<String>,
+    pub theme: Option<String>,
+    pub fast_draft: Option<bool>,
+    pub embed_local_image: Option<bool>,
+    pub embed_remote_image: Option<bool>,
+    pub compress_embed_image: Option<bool>,
+    pub strict_image_src_check: Option<bool>,
+    pub parallelization: Option<bool>,
+    pub nuid: Option<bool>,
+    pub references: Option<TextReferenceMap>,
+    pub documents_subset_to_compile: Option<HashSet<String>>,
+
+    /// Force dossier reloading, even if it is not changed
+    pub reload: bool,
+}
+
+impl BuildRequest {
+
+    /// Build `BuilderConfiguration` using `input_location` as (already resolved) input location
+    pub fn builder_configuration(&self, input_location: PathBuf) -> Result<BuilderConfiguration, ServerError> {
+
+        let mut builder_configuration = BuilderConfiguration::default();
+
+        builder_configuration.set_input_location(input_location);
+
+        if let Some(format) = self.format.as_ref() {
+            builder_configuration.set_format(OutputFormat::from_str(format).map_err(|err| ServerError::InvalidRequest(err.to_string()))?);
+        }
+
+        if let Some(theme) = self.theme.as_ref() {
+            builder_configuration.set_theme(Some(Theme::from_str(theme).map_err(|err| ServerError::InvalidRequest(err.to_string()))?));
+        }
+
+        if self.fast_draft.is_some() {
+            builder_configuration.set_fast_draft(self.fast_draft);
+        }
+
+        builder_configuration.set_embed_local_image(self.embed_local_image);
+        builder_configuration.set_embed_remote_image(self.embed_remote_image);
+        builder_configuration.set_compress_embed_image(self.compress_embed_image);
+        builder_configuration.set_strict_image_src_check(self.strict_image_src_check);
+        builder_configuration.set_parallelization(self.parallelization);
+        builder_configuration.set_references(self.references.clone());
+        builder_configuration.set_documents_subset_to_compile(self.documents_subset_to_compile.clone());
+
+        if self.nuid.is_some() {
+            builder_configuration.set_nuid(self.nuid);
+        }
+
+        Ok(builder_configuration)
+    }
+}
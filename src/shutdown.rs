@@ -0,0 +1,72 @@
+use tokio_util::sync::CancellationToken;
+
+
+/// Exit code used if process is terminated by a second shutdown signal
+pub const FORCED_SHUTDOWN_EXIT_CODE: i32 = 130;
+
+
+/// Create a token which is cancelled as soon as SIGINT (CTRL + C) or SIGTERM is received, so watchers, servers and
+/// in-flight builds can terminate gracefully. A second signal terminates process immediately
+pub fn shutdown_token() -> CancellationToken {
+
+    let shutdown_token = CancellationToken::new();
+
+    tokio::spawn({
+
+        let shutdown_token = shutdown_token.clone();
+
+        async move {
+
+            wait_shutdown_signal().await;
+
+            log::info!("shutdown requested, waiting for in-flight operations (press CTRL + C again to force)...");
+
+            shutdown_token.cancel();
+
+            wait_shutdown_signal().await;
+
+            log::warn!("forced shutdown");
+
+            std::process::exit(FORCED_SHUTDOWN_EXIT_CODE);
+        }
+    });
+
+    shutdown_token
+}
+
+#[cfg(unix)]
+async fn wait_shutdown_signal() {
+
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(err) => {
+            log::error!("SIGTERM handler can not be installed: {}", err);
+
+            wait_ctrl_c().await;
+
+            return
+        },
+    };
+
+    tokio::select! {
+        _ = wait_ctrl_c() => (),
+        _ = sigterm.recv() => (),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_shutdown_signal() {
+    wait_ctrl_c().await
+}
+
+async fn wait_ctrl_c() {
+
+    if let Err(err) = tokio::signal::ctrl_c().await {
+
+        log::error!("CTRL + C handler can not be installed: {}", err);
+
+        std::future::pending::<()>().await
+    }
+}
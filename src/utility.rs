@@ -0,0 +1,68 @@
+use std::{env, path::{Component, Path, PathBuf}};
+
+
+/// Normalize `path` so it can be compared with other paths (e.g. paths of watcher events): it is made absolute and symbolic links are resolved.
+/// Paths which don't exist anymore (e.g. removed or renamed files) are normalized lexically, resolving their parent if possible
+pub fn normalize_path(path: &Path) -> PathBuf {
+
+    let absolute_path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    if let Ok(canonical_path) = absolute_path.canonicalize() {
+        return canonical_path
+    }
+
+    let mut normalized_path = PathBuf::new();
+
+    for component in absolute_path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized_path.pop();
+            },
+            component => normalized_path.push(component),
+        }
+    }
+
+    if let (Some(parent), Some(file_name)) = (normalized_path.parent(), normalized_path.file_name()) {
+        if let Ok(canonical_parent) = parent.canonicalize() {
+            return canonical_parent.join(file_name)
+        }
+    }
+
+    normalized_path
+}
+
+/// User-level NMD configuration directory, i.e. `$XDG_CONFIG_HOME/nmd` or `~/.config/nmd`
+pub fn user_configuration_dir() -> Option<PathBuf> {
+
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+                        .map(PathBuf::from)
+                        .or_else(|| env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("nmd"))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::normalize_path;
+
+
+    #[test]
+    fn removed_paths_are_normalized() {
+
+        let dossier_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-resources").join("nmd-test-dossier-2");
+
+        let canonical_dossier_path = dossier_path.canonicalize().unwrap();
+
+        assert_eq!(normalize_path(&dossier_path.join("./welcome.nmd")), canonical_dossier_path.join("welcome.nmd"));
+        assert_eq!(normalize_path(&dossier_path.join("assets/../not-exists.nmd")), canonical_dossier_path.join("not-exists.nmd"));
+        assert_eq!(normalize_path(&dossier_path.join("not-exists-dir/../../not-exists.nmd")), canonical_dossier_path.parent().unwrap().join("not-exists.nmd"));
+    }
+}
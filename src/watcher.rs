@@ -1,9 +1,10 @@
-use std::{collections::HashSet, future::Future, path::PathBuf, pin::Pin, sync::mpsc::RecvError, time::SystemTime};
+use std::{collections::HashSet, future::Future, path::{Path, PathBuf}, pin::Pin, sync::{atomic::{AtomicU64, Ordering}, mpsc::{RecvError, RecvTimeoutError}, Arc, Mutex}, time::Duration};
 
 use getset::{Getters, Setters};
 use notify::{Error, Event, RecursiveMode, Watcher};
 use thiserror::Error;
-use tokio::{sync::mpsc::Receiver, task::{JoinError, JoinHandle}};
+use tokio::{sync::mpsc::Receiver, task::{JoinError, JoinHandle}, time::Instant};
+use tokio_util::sync::CancellationToken;
 
 use super::preview::PreviewError;
 
@@ -30,8 +31,64 @@ pub enum WatcherError {
     SendError,
 }
 
+/// How often notify thread checks if watcher is stopped
+const NOTIFY_THREAD_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+
 pub type CheckIfElaborateFn<'a> = Box<dyn FnMut(Event) -> Pin<Box<dyn Future<Output = Result<bool, WatcherError>> + Send>> + Send + Sync + 'a>;
 pub type OnStartFn<'a> = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), WatcherError>> + Send>> + Send + Sync + 'a>;
+
+/// Extra paths watched besides input path (e.g. style files or images outside dossier directory).
+/// They can be changed while watcher is running, watcher applies changes within `NOTIFY_THREAD_POLL_INTERVAL`
+#[derive(Debug, Clone, Default)]
+pub struct WatchRoots {
+    paths: Arc<Mutex<HashSet<PathBuf>>>,
+
+    /// Incremented each time paths are replaced, so watcher reconfigures itself only if needed
+    version: Arc<AtomicU64>,
+}
+
+impl WatchRoots {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace watched paths
+    pub fn set(&self, paths: HashSet<PathBuf>) {
+        *self.paths.lock().unwrap() = paths;
+
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Check if `path` is one of watched paths
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.lock().unwrap().contains(path)
+    }
+
+    fn paths(&self) -> HashSet<PathBuf> {
+        self.paths.lock().unwrap().clone()
+    }
+
+    fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+}
+
+/// Directory which must be watched to capture changes of `path`. Files are watched through their parent directory,
+/// because many editors save a file replacing it (i.e. watched file would be lost)
+fn watch_target(path: &Path) -> Option<(PathBuf, RecursiveMode)> {
+
+    if path.is_dir() {
+        return Some((path.to_path_buf(), RecursiveMode::Recursive))
+    }
+
+    path.parent()
+        .filter(|parent| parent.is_dir())
+        .map(|parent| (parent.to_path_buf(), RecursiveMode::NonRecursive))
+}
+
+
 pub type ElaborateFn<'a> = Box<dyn Fn(HashSet<PathBuf>) -> Pin<Box<dyn Future<Output = Result<(), WatcherError>> + Send>> + Send + Sync + 'a>;
 
 
@@ -40,52 +97,135 @@ pub struct NmdWatcher<'a> {
 
     rx: Receiver<Result<Event, Error>>,
 
+    /// Thread which forwards notify events, it terminates when watcher is stopped
+    notify_thread_handle: Option<JoinHandle<Result<(), WatcherError>>>,
+
+    /// Watcher is stopped (after in-flight elaboration) when this token is cancelled
+    shutdown_token: CancellationToken,
+
     on_start_fn: OnStartFn<'a>,
 
     check_if_elaborate_fn: CheckIfElaborateFn<'a>,
 
-    check_if_elaborate_skipping_timeout_fn: CheckIfElaborateFn<'a>,
-
     elaborate_fn: ElaborateFn<'a>,
 
-    min_elapsed_time_between_events_in_secs: u64,
+    /// Quiet time after last event before elaboration (trailing-edge debounce)
+    debounce_time: Duration,
 }
 
 impl<'a> NmdWatcher<'a> {
 
-    pub async fn new(min_elapsed_time_between_events_in_secs: u64, input_path: &PathBuf, on_start_fn: OnStartFn<'a>, check_if_elaborate_skipping_timeout_fn: CheckIfElaborateFn<'a>, check_if_elaborate_fn: CheckIfElaborateFn<'a>, elaborate_fn: ElaborateFn<'a>) -> Result<Self, WatcherError> {
+    /// Create a new watcher of `input_path` and of `extra_roots`. Elaboration is done when no events are received for `debounce_time`
+    /// and at least one of them is accepted by `check_if_elaborate_fn`
+    pub async fn new(debounce_time: Duration, input_path: &PathBuf, extra_roots: WatchRoots, on_start_fn: OnStartFn<'a>, check_if_elaborate_fn: CheckIfElaborateFn<'a>, elaborate_fn: ElaborateFn<'a>, shutdown_token: CancellationToken) -> Result<Self, WatcherError> {
         
         let (tx, rx) = tokio::sync::mpsc::channel(4096);
 
         let input_path = input_path.clone();
 
-        let _: JoinHandle<Result<(), WatcherError>> = tokio::spawn(async move {
-            let (notify_tx, notify_rx) = std::sync::mpsc::channel();
+        let notify_thread_handle: JoinHandle<Result<(), WatcherError>> = tokio::task::spawn_blocking({
+
+            let shutdown_token = shutdown_token.clone();
+            
+            move || {
+                let (notify_tx, notify_rx) = std::sync::mpsc::channel();
+
+                let mut watcher = notify::recommended_watcher(move |res| {
+                        
+                    notify_tx.send(res).unwrap_or_else(|val| {
+                        log::error!("error occurs during watching: {}", val);
+                    });
+                })?;
+
+                watcher.watch(&input_path, RecursiveMode::Recursive)?;
+
+                let mut watched_extra_targets: HashSet<(PathBuf, RecursiveMode)> = HashSet::new();
+
+                // version of extra roots which watcher is configured for
+                let mut configured_version: Option<u64> = None;
+
+                // some extra roots can not be watched yet (e.g. not existing), so they are retried each poll interval
+                let mut unresolved_extra_roots = false;
+                let mut last_configuration = std::time::Instant::now();
+
+                while !shutdown_token.is_cancelled() {
+
+                    let version = extra_roots.version();
+
+                    // watch targets are computed only when extra roots change (or to retry unresolved ones), not for each event
+                    let reconfigure = configured_version != Some(version)
+                                        || (unresolved_extra_roots && last_configuration.elapsed() >= NOTIFY_THREAD_POLL_INTERVAL);
+
+                    if reconfigure {
+
+                        // paths inside input path are already watched
+                        let resolved_targets: Vec<Option<(PathBuf, RecursiveMode)>> = extra_roots.paths().iter()
+                                                                                        .filter(|path| !path.starts_with(&input_path))
+                                                                                        .map(|path| watch_target(path))
+                                                                                        .collect();
 
-            let mut watcher = notify::recommended_watcher(move |res| {
-                    
-                notify_tx.send(res).unwrap_or_else(|val| {
-                    log::error!("error occurs during watching: {}", val);
-                });
-            })?;
+                        unresolved_extra_roots = resolved_targets.iter().any(Option::is_none);
 
-            watcher.watch(&input_path, RecursiveMode::Recursive)?;
+                        let extra_targets: HashSet<(PathBuf, RecursiveMode)> = resolved_targets.into_iter().flatten().collect();
 
-            while let Ok(event) = notify_rx.recv() {
-                if let Err(_) = tx.send(event).await {
-                    return Err(WatcherError::SendError)
+                        for target in watched_extra_targets.difference(&extra_targets) {
+
+                            log::debug!("stop watching extra root: {:?}", target.0);
+
+                            if let Err(err) = watcher.unwatch(&target.0) {
+                                log::warn!("extra root {:?} can not be unwatched: {}", target.0, err);
+                            }
+                        }
+
+                        watched_extra_targets.retain(|target| extra_targets.contains(target));
+
+                        for target in extra_targets {
+
+                            if watched_extra_targets.contains(&target) {
+                                continue
+                            }
+
+                            match watcher.watch(&target.0, target.1) {
+                                Ok(_) => {
+                                    log::info!("watching extra root: {:?}", target.0);
+
+                                    watched_extra_targets.insert(target);
+                                },
+                                Err(err) => {
+                                    log::warn!("extra root {:?} can not be watched: {}", target.0, err);
+
+                                    unresolved_extra_roots = true;
+                                },
+                            }
+                        }
+
+                        configured_version = Some(version);
+                        last_configuration = std::time::Instant::now();
+                    }
+
+                    match notify_rx.recv_timeout(NOTIFY_THREAD_POLL_INTERVAL) {
+                        Ok(event) => {
+                            // send fails only if receiver is closed, i.e. watcher is stopped and events are no longer needed
+                            if tx.blocking_send(event).is_err() {
+                                break
+                            }
+                        },
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
                 }
-            }
 
-            Ok(())
+                Ok(())
+            }
         });
 
         let s = Self {
-            min_elapsed_time_between_events_in_secs,
+            debounce_time,
             rx,
+            notify_thread_handle: Some(notify_thread_handle),
+            shutdown_token,
             on_start_fn,
             check_if_elaborate_fn,
-            check_if_elaborate_skipping_timeout_fn,
             elaborate_fn
         };
 
@@ -94,59 +234,97 @@ impl<'a> NmdWatcher<'a> {
 
     pub async fn start(&mut self) -> Result<(), WatcherError> {
 
-        let mut last_event_time = SystemTime::now();
+        let mut failed_elaborations: usize = if Self::recover((self.on_start_fn)().await)? { 0 } else { 1 };
+
+        // all paths changed during current quiet window
+        let mut changed_paths: HashSet<PathBuf> = HashSet::new();
+
+        let mut elaboration_needed = false;
 
-        let mut paths_change_detection_from_last_elaboration: HashSet<PathBuf> = HashSet::new(); 
+        // end of current quiet window, restarted by each event
+        let mut deadline: Option<Instant> = None;
 
-        (self.on_start_fn)().await?;
+        let mut elaborations: usize = 0;
 
         loop {
 
-            if let Ok(recv_res) = self.rx.try_recv() {
+            // in-flight elaboration is never interrupted, shutdown is checked only between events
+            tokio::select! {
+                biased;
 
-                match recv_res {
-                    Ok(event) => {
-                        log::debug!("new event from watcher: {:?}", event);
-                        log::debug!("change detected on file(s): {:?}", event.paths);
+                _ = self.shutdown_token.cancelled() => break,
 
-                        event.clone().paths.iter().for_each(|path| {
-                            paths_change_detection_from_last_elaboration.insert(path.clone());
-                        });
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
 
-                        if (self.check_if_elaborate_skipping_timeout_fn)(event.clone()).await? {
+                    deadline = None;
 
-                            (self.elaborate_fn)(paths_change_detection_from_last_elaboration.clone()).await?;
+                    let paths = std::mem::take(&mut changed_paths);
 
-                            continue;
-                        }
-                        
-                        let event_time = SystemTime::now();
+                    if !elaboration_needed {
+                        log::debug!("{} path(s) changed, but elaboration is not needed", paths.len());
 
-                        let elapsed_time = event_time.duration_since(last_event_time).unwrap();
+                        continue;
+                    }
 
-                        if elapsed_time.as_secs() < self.min_elapsed_time_between_events_in_secs {
-                            log::info!("change detected, but minimum elapsed time not satisfied ({}/{} s)", elapsed_time.as_secs(), self.min_elapsed_time_between_events_in_secs);
+                    elaboration_needed = false;
 
-                            continue;
-                        }
+                    log::info!("elaborating {} changed path(s)...", paths.len());
 
-                        if (self.check_if_elaborate_fn)(event).await? {
-                            (self.elaborate_fn)(paths_change_detection_from_last_elaboration.clone()).await?;
+                    if !Self::recover((self.elaborate_fn)(paths).await)? {
+                        failed_elaborations += 1;
+                    }
 
-                            last_event_time = event_time;
-                            
-                            continue;
+                    elaborations += 1;
+                },
+
+                recv_res = self.rx.recv() => match recv_res {
+                    Some(Ok(event)) => {
+                        log::debug!("new event from watcher: {:?}", event);
+                        log::debug!("change detected on file(s): {:?}", event.paths);
+
+                        changed_paths.extend(event.paths.iter().cloned());
+
+                        if !elaboration_needed && (self.check_if_elaborate_fn)(event).await? {
+                            elaboration_needed = true;
                         }
+
+                        deadline = Some(Instant::now() + self.debounce_time);
                     },
-                    Err(err) => {
-                        log::error!("error: {}", err.to_string());
+                    Some(Err(err)) => {
+                        log::error!("error: {}", err);
                     },
-                }
-
-            } else {
-                
-                tokio::task::yield_now().await;
+                    None => break,
+                },
             }
         }
+
+        self.rx.close();
+
+        if let Some(notify_thread_handle) = self.notify_thread_handle.take() {
+            notify_thread_handle.await??;
+        }
+
+        log::info!("watcher stopped ({} elaboration(s) after start, {} failed)", elaborations, failed_elaborations);
+
+        Ok(())
+    }
+
+    /// Elaboration errors (e.g. a typo in a watched file) are normal while files are edited, so they are reported and
+    /// watcher keeps watching waiting a fix. Other errors stop watcher.
+    ///
+    /// Return `true` if elaboration is succeeded
+    fn recover(elaboration_result: Result<(), WatcherError>) -> Result<bool, WatcherError> {
+
+        match elaboration_result {
+            Ok(_) => Ok(true),
+            Err(WatcherError::ElaborationError(err)) => {
+
+                log::error!("elaboration failed: {}", err);
+                log::warn!("last good output is kept, watcher is waiting for next change...");
+
+                Ok(false)
+            },
+            Err(err) => Err(err),
+        }
     }
-}
\ No newline at end of file
+}